@@ -1,4 +1,5 @@
 pub mod orbit;
 pub mod planets;
+pub mod stars;
 
 pub use orbit::System;
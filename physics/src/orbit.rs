@@ -2,7 +2,10 @@
 use core::f64;
 
 use crate::planets::{Body, BODIES, N_BODIES};
-use agc_utils::{FloatConversionError, PrintType, SolarFp, StepFp, StepVec3D};
+use agc_utils::{
+    ArithmeticError, FloatConversionError, PrintType, SimClock, SimClockReader, SimTime, SolarFp,
+    StepFp, StepVec3D,
+};
 
 const TIME_STEP: f64 = 43.20; // 200 steps per day
 const SIM_TIME: f64 = 86400.0 * 365.25 * 2.0; // 2 earth years; duration of full simulations done by System.simulate()
@@ -12,6 +15,7 @@ const STEPS: usize = (SIM_TIME / TIME_STEP) as usize; // (MR A.2b) Both of the a
 pub enum SimulationError {
     BadTimeStep,
     BadPrintIndex,
+    Arithmetic(ArithmeticError), // e.g. two bodies at the same position, giving no direction for gravity.
 }
 
 impl From<FloatConversionError> for SimulationError {
@@ -20,10 +24,16 @@ impl From<FloatConversionError> for SimulationError {
     }
 }
 
+impl From<ArithmeticError> for SimulationError {
+    fn from(value: ArithmeticError) -> Self {
+        Self::Arithmetic(value)
+    }
+}
+
 /// stores the live state of all the bodies, and the means to simulate their movement.
 pub struct System {
     pub bodies: [Body; N_BODIES],
-    time_passed: f64,
+    clock: SimClock, // the one true simulation clock; sensors get read-only handles via clock_reader().
     pub log_verlet: bool,
 }
 
@@ -32,7 +42,7 @@ impl System {
         //! creates a new instance of the Solar system, loading in all bodies.
         let mut out = Self {
             bodies: BODIES,
-            time_passed: 0.0,
+            clock: SimClock::new(),
             log_verlet: false,
         };
 
@@ -50,6 +60,16 @@ impl System {
         self
     }
 
+    pub fn now(&self) -> SimTime {
+        //! the current simulation time, measured from epoch.
+        self.clock.now()
+    }
+
+    pub fn clock_reader(&self) -> SimClockReader {
+        //! produces a read-only handle on the simulation clock, to be shared with sensor threads.
+        self.clock.reader()
+    }
+
     pub fn simulate(
         &mut self,
         print_type: PrintType,
@@ -148,7 +168,7 @@ impl System {
     fn step_time_forwards(&mut self, time: f64) -> Result<f64, SimulationError> {
         //! steps time forwards by the given time in seconds.
         //! Internal function only - used by simulate() and advance_time_multistep().
-        self.time_passed += time;
+        self.clock.advance(SimTime::from_f64(time)?);
         let time_step_fp = SolarFp::from_f64(time)?; // used for velocities/positions, so SolarFp
         let half_time_step_fp = StepFp::from_f64(time / 2.0)?; // used for accelerations/velocities, so StepFp
 
@@ -179,7 +199,7 @@ impl System {
                 let dir_vec = current.position.vector_to(&other.position);
                 let distance = dir_vec.magnitude(); //fp60
 
-                accel = accel.add(&calculate_accel(current, other));
+                accel = accel.add(&calculate_accel(current, other)?);
 
                 // only calculate potential against earlier planets; avoids double-counting
                 if j < i {
@@ -253,7 +273,7 @@ impl System {
             let mut accel_second = StepVec3D::new();
 
             for other in others_iterator {
-                accel_second = accel_second.add(&calculate_accel(current, other))
+                accel_second = accel_second.add(&calculate_accel(current, other)?)
             }
 
             // add other half of acceleration-time to velocity with new accel.
@@ -285,10 +305,10 @@ impl System {
     }
 }
 
-fn calculate_accel(pulled: &Body, pulling_body: &Body) -> StepVec3D {
+fn calculate_accel(pulled: &Body, pulling_body: &Body) -> Result<StepVec3D, SimulationError> {
     let v_to = pulled.position.vector_to(&pulling_body.position);
     let distance = v_to.magnitude();
-    let direction_vector = v_to.to_unit_vector();
+    let direction_vector = v_to.to_unit_vector()?;
 
     #[cfg(test)]
     println!(
@@ -332,7 +352,21 @@ fn calculate_accel(pulled: &Body, pulling_body: &Body) -> StepVec3D {
     println!("gm/d2={:.4e}", grav.to_f64());
 
     // grav is now the magnitude of the gravitational force, the unit vector by it.
-    direction_vector.scale_from_unit(grav)
+    Ok(direction_vector.scale_from_unit(grav))
+}
+
+#[test]
+fn test_clock_advances_with_steps() {
+    let mut system = System::create();
+    let reader = system.clock_reader();
+
+    system
+        .advance_time_multistep(SolarFp::from_int(100), None)
+        .unwrap();
+
+    // 2 full steps of 43.2s, then a remainder step of 13.6s.
+    assert!((reader.now().to_f64() - 100.0).abs() < 1e-2);
+    assert_eq!(reader.now(), system.now());
 }
 
 #[test]
@@ -342,7 +376,7 @@ fn test_sun_earth_acceleration() {
 
     let earth = &b[3];
 
-    let accel = calculate_accel(earth, sun);
+    let accel = calculate_accel(earth, sun).unwrap();
 
     // Expected: GM/r^2 ≈ 1.327e20 / (1.471e11)^2 ≈ 6.13e-3 m/s²
     // Direction should point from Earth toward Sun (roughly +x, -y given Earth's position)
@@ -20,6 +20,7 @@ impl Gravity {
 pub struct Body {
     pub name: str16,
     pub gravity: Gravity, // G*m_1; divide by d^2 for acceleration of external body. Bitshifted by grav_scale
+    pub radius: SolarFp,  // mean radius, m; what hides the stars behind the body.
     pub position: SolarVec3D,
     pub velocity: StepVec3D,
     pub parent_id: Option<usize>,
@@ -44,6 +45,7 @@ impl Body {
                 stored_solar: SolarFp::from_f64_trusted(gravity),
                 scale,
             },
+            radius: SolarFp::from_f64_trusted(0.0), // set by with_radius().
             position,
             velocity,
             parent_id: Some(parent_id),
@@ -52,6 +54,11 @@ impl Body {
         }
     }
 
+    const fn with_radius(mut self, radius: f64) -> Self {
+        self.radius = SolarFp::from_f64_trusted(radius);
+        self
+    }
+
     pub fn fill_influencers(&mut self, body_list: &[Body; 10]) {
         //! each body is influenced by their parent, siblings and children
 
@@ -78,6 +85,7 @@ pub const BODIES: [Body; N_BODIES] = [
             stored_solar: SolarFp::from_f64_trusted(1.26558e14),
             scale: 20,
         },
+        radius: SolarFp::from_f64_trusted(6.957e8),
         position: SolarVec3D::from_floats_trusted(0.0, 0.0, 0.0),
         velocity: StepVec3D::from_floats_trusted(0.0, 0.0, 0.0),
         parent_id: None,
@@ -100,7 +108,8 @@ pub const BODIES: [Body; N_BODIES] = [
         ),
         0,
         1,
-    ),
+    )
+    .with_radius(2.4397e6),
     Body::new(
         "Venus",
         3.24924e14,
@@ -117,7 +126,8 @@ pub const BODIES: [Body; N_BODIES] = [
         ),
         0,
         2,
-    ),
+    )
+    .with_radius(6.0518e6),
     Body::new(
         "Earth",
         3.98438e14,
@@ -134,7 +144,8 @@ pub const BODIES: [Body; N_BODIES] = [
         ),
         0,
         3,
-    ),
+    )
+    .with_radius(6.371e6),
     Body::new(
         "Mars",
         4.27277e13,
@@ -151,7 +162,8 @@ pub const BODIES: [Body; N_BODIES] = [
         ),
         0,
         4,
-    ),
+    )
+    .with_radius(3.3895e6),
     Body::new(
         "Jupiter",
         1.23183e14,
@@ -168,7 +180,8 @@ pub const BODIES: [Body; N_BODIES] = [
         ),
         0,
         5,
-    ),
+    )
+    .with_radius(6.9911e7),
     Body::new(
         "Saturn",
         3.7041e13,
@@ -185,7 +198,8 @@ pub const BODIES: [Body; N_BODIES] = [
         ),
         0,
         6,
-    ),
+    )
+    .with_radius(5.8232e7),
     Body::new(
         "Uranus",
         5.65811e12,
@@ -202,7 +216,8 @@ pub const BODIES: [Body; N_BODIES] = [
         ),
         0,
         7,
-    ),
+    )
+    .with_radius(2.5362e7),
     Body::new(
         "Neptune",
         6.67459e12,
@@ -219,7 +234,8 @@ pub const BODIES: [Body; N_BODIES] = [
         ),
         0,
         8,
-    ),
+    )
+    .with_radius(2.4622e7),
     Body::new(
         "Pluto",
         8.72292e11,
@@ -236,5 +252,6 @@ pub const BODIES: [Body; N_BODIES] = [
         ),
         0,
         9,
-    ),
+    )
+    .with_radius(1.1883e6),
 ];
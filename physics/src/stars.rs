@@ -0,0 +1,168 @@
+//! This file holds the catalogue of navigation stars: bright stars used for sightings and platform alignment.
+//! Stars are far enough away to have no parallax across the Solar System, so each is just a fixed direction in the Sun
+//! Centre at Epoch (SCE) frame used by BODIES; ecliptic, J2000. Only a body in the way can stop one being seen.
+use agc_utils::{ArithmeticError, SolarFp, SolarVec3D, UnitVec3D};
+use arrayvec::ArrayVec;
+use fixedstr::str16;
+
+use crate::planets::Body;
+
+/// a navigation star, as catalogued.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Star {
+    pub id: usize, // index in STARS.
+    pub name: str16,
+    pub direction: UnitVec3D, // from anywhere in the Solar System towards the star, SCE frame.
+}
+
+impl Star {
+    const fn new(id: usize, name: &str, x: f64, y: f64, z: f64) -> Self {
+        Star {
+            id,
+            name: str16::const_make(name),
+            direction: UnitVec3D::from_floats_trusted(x, y, z),
+        }
+    }
+
+    pub fn is_occluded_by(
+        &self,
+        observer: &SolarVec3D,
+        body: &Body,
+    ) -> Result<bool, ArithmeticError> {
+        //! whether body blocks the line of sight from observer towards this star. An observer inside a body sees nothing.
+        let to_body = body.position.checked_sub(observer)?;
+        if to_body.checked_magnitude()? <= body.radius {
+            return Ok(true);
+        }
+        let along = to_body.component_along(&self.direction)?;
+        if along <= SolarFp::from_int(0) {
+            return Ok(false); // the body is behind the observer.
+        }
+        // the closest the line of sight passes to the body's centre.
+        let miss = to_body
+            .checked_sub(&self.direction.checked_scale_from_unit(along)?)?
+            .checked_magnitude()?;
+        Ok(miss < body.radius)
+    }
+}
+
+pub fn star(id: usize) -> Option<&'static Star> {
+    //! looks up a star by its id.
+    STARS.get(id)
+}
+
+pub fn star_named(name: &str) -> Option<&'static Star> {
+    //! looks up a star by its catalogue name, e.g. "Sirius".
+    STARS.iter().find(|star| star.name == name)
+}
+
+pub fn visible_from(
+    observer: &SolarVec3D,
+    bodies: &[Body],
+) -> Result<ArrayVec<&'static Star, N_STARS>, ArithmeticError> {
+    //! every catalogued star not hidden by any of the bodies, as seen from observer.
+    let mut visible = ArrayVec::new();
+    for star in STARS.iter() {
+        let mut hidden = false;
+        for body in bodies {
+            hidden |= star.is_occluded_by(observer, body)?;
+        }
+        if !hidden {
+            visible.push(star); // at most N_STARS pushes.
+        }
+    }
+    Ok(visible)
+}
+
+pub const N_STARS: usize = 38;
+// directions are converted from J2000 right ascension and declination, rotated into the ecliptic by the obliquity
+// 23.4392911 degrees. A static rather than a const, so lookups can hand out references that live forever.
+pub static STARS: [Star; N_STARS] = [
+    Star::new(0, "Alpheratz", 0.873267121, 0.222733893, 0.433352221),
+    Star::new(1, "Diphda", 0.933976668, 0.042143611, -0.354840104),
+    Star::new(2, "Navi", 0.474231552, 0.456857653, 0.752585889),
+    Star::new(3, "Achernar", 0.492724224, -0.129160342, -0.860546597),
+    Star::new(4, "Polaris", 0.010126183, 0.404991106, 0.914264549),
+    Star::new(5, "Acamar", 0.543324866, 0.233682383, -0.806343992),
+    Star::new(6, "Menkar", 0.698254761, 0.681880188, -0.217898364),
+    Star::new(7, "Mirfak", 0.404979581, 0.764260431, 0.501893946),
+    Star::new(8, "Aldebaran", 0.343906399, 0.934157427, -0.095280057),
+    Star::new(9, "Rigel", 0.195050048, 0.833544615, -0.516874118),
+    Star::new(10, "Capella", 0.130498076, 0.912139780, 0.388550221),
+    Star::new(11, "Canopus", -0.063221970, 0.236599131, -0.969548263),
+    Star::new(12, "Sirius", -0.187454053, 0.747302893, -0.637494599),
+    Star::new(13, "Procyon", -0.418110137, 0.865463105, -0.275966533),
+    Star::new(14, "Regor", -0.362957583, 0.232567915, -0.902315886),
+    Star::new(15, "Dnoces", -0.471128636, 0.731048207, 0.493564917),
+    Star::new(16, "Alphard", -0.777930751, 0.499824704, -0.380787357),
+    Star::new(17, "Regulus", -0.864501608, 0.502564575, 0.008112824),
+    Star::new(18, "Dubhe", -0.459111567, 0.455955050, 0.762444464),
+    Star::new(19, "Denebola", -0.966730038, 0.142449818, 0.212464311),
+    Star::new(20, "Gienah", -0.951229658, -0.180179469, -0.250394681),
+    Star::new(21, "Acrux", -0.449404032, -0.402802767, -0.797361867),
+    Star::new(22, "Alioth", -0.544292759, 0.209660209, 0.812273346),
+    Star::new(23, "Spica", -0.914079423, -0.403947460, -0.035850487),
+    Star::new(24, "Alkaid", -0.581459592, 0.031155248, 0.812978532),
+    Star::new(25, "Menkent", -0.685292148, -0.623758539, -0.375898865),
+    Star::new(26, "Arcturus", -0.783785555, -0.352801336, 0.511088466),
+    Star::new(27, "Alphecca", -0.529169968, -0.481438902, 0.698710046),
+    Star::new(28, "Antares", -0.344813772, -0.935283601, -0.079674637),
+    Star::new(29, "Atria", -0.109613743, -0.684027919, -0.721173095),
+    Star::new(30, "Rasalhague", -0.106536741, -0.803673879, 0.585455566),
+    Star::new(31, "Vega", 0.125094566, -0.456763819, 0.880748638),
+    Star::new(32, "Nunki", 0.214095073, -0.974958008, -0.060167946),
+    Star::new(33, "Altair", 0.459221343, -0.741329105, 0.489435304),
+    Star::new(34, "Dabih", 0.558085523, -0.825917897, 0.080001102),
+    Star::new(35, "Deneb", 0.455648954, -0.209294929, 0.865204984),
+    Star::new(36, "Enif", 0.817203196, -0.436619253, 0.376221430),
+    Star::new(37, "Fomalhaut", 0.837331368, -0.410927750, -0.360575324),
+];
+
+#[test]
+fn test_directions_are_unit() {
+    for star in STARS.iter() {
+        let length = star.direction.magnitude().to_f64();
+        assert!((length - 1.0).abs() < 1e-8, "{}: {length}", star.name);
+    }
+}
+
+#[test]
+fn test_lookup() {
+    let sirius = star_named("Sirius").unwrap();
+    assert_eq!(star(sirius.id), Some(sirius));
+    assert!(star_named("Betelgeuse").is_none());
+    assert!(star(N_STARS).is_none());
+    // Polaris sits near the celestial pole, one obliquity away from the ecliptic pole.
+    let polaris = star_named("Polaris").unwrap();
+    assert!((polaris.direction.2.to_f64() - 23.44f64.to_radians().cos()).abs() < 0.01);
+    for (i, star) in STARS.iter().enumerate() {
+        assert_eq!(star.id, i);
+    }
+}
+
+#[test]
+fn test_occlusion() {
+    let earth = &crate::planets::BODIES[3];
+    let sirius = star_named("Sirius").unwrap();
+    // 10,000km from Earth's centre, on the far side from Sirius: Earth is in the way.
+    let behind = earth.position.sub(
+        &sirius
+            .direction
+            .scale_from_unit(SolarFp::from_int(10_000_000)),
+    );
+    assert!(sirius.is_occluded_by(&behind, earth).unwrap());
+    // on the near side, Earth is behind the observer.
+    let in_front = earth.position.add(
+        &sirius
+            .direction
+            .scale_from_unit(SolarFp::from_int(10_000_000)),
+    );
+    assert!(!sirius.is_occluded_by(&in_front, earth).unwrap());
+    let hidden_from_behind = visible_from(&behind, std::slice::from_ref(earth)).unwrap();
+    assert!(!hidden_from_behind.contains(&sirius));
+    assert!(hidden_from_behind.len() > N_STARS / 2); // Earth covers well under half the sky from there.
+    assert_eq!(visible_from(&in_front, &[]).unwrap().len(), N_STARS);
+    assert!(visible_from(&earth.position, std::slice::from_ref(earth))
+        .unwrap()
+        .is_empty());
+}
@@ -0,0 +1,466 @@
+//! contains the main engine: a single throttleable engine firing along the body's forward (+x) axis. The flight
+//! controller commands a throttle setting or a cutoff; each step, the engine's actual output ramps towards the command
+//! through its start-up and shut-down transients, burning propellant in proportion to thrust (by its specific impulse)
+//! until it is cut off or runs dry. Settings below the minimum throttle are raised to it, as the engine can't burn
+//! stably any lower. The flight controller owns the engine and commands it as its burn changes phase (see burn.rs); the
+//! engine is stepped by whatever owns the truth, and its thrust applied to the _Rocket there, as its share of whatever
+//! else is pushing the rocket. Three faults are modelled: an ignition that doesn't light, a throttle valve that sticks
+//! where it is for the rest of the burn, and a thrust axis knocked off the body axis by an ignition.
+
+use std::fmt::Display;
+
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+use agc_utils::{
+    ArithmeticError, EulerAngles, Quaternion, SimTime, StepFp, StepVec3D, UnitFp, Vec3D,
+};
+
+use crate::hardware::rocket::_Rocket;
+use crate::hardware::sensors::_random_within;
+
+const _STANDARD_GRAVITY: StepFp = StepFp::from_f64_trusted(9.80665); // m/s^2; turns Isp into exhaust velocity.
+pub(crate) const _FULL_LOAD: StepFp = StepFp::from_f64_trusted(8_200.0); // kg; the descent stage's tanks, filled.
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct _EngineSpec {
+    /// The engine's fixed performance, and the vehicle it pushes. Size: 48B.
+    pub(crate) min_throttle: UnitFp, // fraction of full thrust; the lowest stable setting.
+    pub(crate) max_thrust: StepFp,       // N, at full throttle.
+    pub(crate) specific_impulse: StepFp, // s.
+    pub(crate) spool_up: SimTime,        // time for output to rise from nothing to full thrust.
+    pub(crate) spool_down: SimTime,      // time for output to fall from full thrust to nothing.
+    pub(crate) dry_mass: StepFp,         // kg; the whole vehicle, less propellant.
+}
+
+impl _EngineSpec {
+    // modelled on the Apollo lunar module's descent engine.
+    pub(crate) const _DEFAULT: Self = Self {
+        min_throttle: UnitFp::from_f64_trusted(0.1),
+        max_thrust: StepFp::from_f64_trusted(45_000.0),
+        specific_impulse: StepFp::from_f64_trusted(311.0),
+        spool_up: SimTime::from_secs(2),
+        spool_down: SimTime::from_secs(1),
+        dry_mass: StepFp::from_f64_trusted(6_500.0),
+    };
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct _EngineFailureRates {
+    /// How often the engine faults. Size: 32B.
+    pub(crate) no_ignition_probability: UnitFp, // per ignition attempt.
+    pub(crate) stuck_throttle_probability: UnitFp, // per throttle change while burning.
+    pub(crate) misalignment_probability: UnitFp,   // per ignition that lights.
+    pub(crate) max_misalignment: UnitFp, // rad, per axis; how far off a knocked thrust axis can be.
+}
+
+impl _EngineFailureRates {
+    pub(crate) const _DEFAULT: Self = Self {
+        no_ignition_probability: UnitFp::from_f64_trusted(1e-3),
+        stuck_throttle_probability: UnitFp::from_f64_trusted(1e-3),
+        misalignment_probability: UnitFp::from_f64_trusted(1e-3),
+        max_misalignment: UnitFp::from_f64_trusted(0.01),
+    };
+
+    pub(crate) const _NEVER_FAIL: Self = Self {
+        no_ignition_probability: UnitFp::from_f64_trusted(0.0),
+        stuck_throttle_probability: UnitFp::from_f64_trusted(0.0),
+        misalignment_probability: UnitFp::from_f64_trusted(0.0),
+        ..Self::_DEFAULT
+    };
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum _EngineError {
+    NoIgnition, // the engine didn't light; it stays off, and ignition can be tried again.
+    StuckThrottle { at: UnitFp }, // the throttle valve is stuck at this setting; only a cutoff still works.
+    Depleted,                     // no propellant left to burn.
+    Arithmetic(ArithmeticError),  // a step couldn't be computed; the engine is left as it was.
+}
+
+impl From<ArithmeticError> for _EngineError {
+    fn from(value: ArithmeticError) -> Self {
+        _EngineError::Arithmetic(value)
+    }
+}
+
+impl Display for _EngineError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            _EngineError::NoIgnition => write!(f, "Main engine failed to ignite"),
+            _EngineError::StuckThrottle { at } => {
+                write!(f, "Main engine throttle stuck at {:.3}", at.to_f64())
+            }
+            _EngineError::Depleted => write!(f, "Main engine propellant depleted"),
+            _EngineError::Arithmetic(error) => write!(f, "Main engine step failed: {error:?}"),
+        }
+    }
+}
+
+pub(crate) struct _MainEngine {
+    /// Size: 496B (mostly the rng); passed by reference.
+    spec: _EngineSpec,
+    rates: _EngineFailureRates,
+    rng: StdRng,              // seeded so that runs are reproducible.
+    commanded: UnitFp,        // the throttle setting asked for; zero when cut off.
+    output: UnitFp,           // the throttle actually burning; ramps towards commanded.
+    stuck: bool,              // whether the throttle valve is stuck for the rest of this burn.
+    misalignment: Quaternion, // rotation of the thrust axis off the body axis.
+    propellant: StepFp,       // kg remaining.
+    applied: StepVec3D, // reference frame, m/s^2; the share of the rocket's acceleration last applied.
+    last_step: SimTime,
+}
+
+impl _MainEngine {
+    pub(crate) fn _new(
+        spec: _EngineSpec,
+        rates: _EngineFailureRates,
+        propellant: StepFp,
+        seed: u64,
+        now: SimTime,
+    ) -> Self {
+        //! a cold engine, correctly aligned, with the given propellant loaded.
+        Self {
+            spec,
+            rates,
+            rng: StdRng::seed_from_u64(seed),
+            commanded: UnitFp::from_int(0),
+            output: UnitFp::from_int(0),
+            stuck: false,
+            misalignment: Quaternion::IDENTITY,
+            propellant,
+            applied: StepVec3D::new(),
+            last_step: now,
+        }
+    }
+
+    pub(crate) fn _propellant(&self) -> StepFp {
+        self.propellant
+    }
+
+    pub(crate) fn _output(&self) -> UnitFp {
+        //! the fraction of full thrust currently being produced.
+        self.output
+    }
+
+    pub(crate) fn _is_lit(&self) -> bool {
+        //! whether the engine is burning, or has been commanded to and is still spooling up.
+        self.commanded > UnitFp::from_int(0) || self.output > UnitFp::from_int(0)
+    }
+
+    pub(crate) fn _throttle(&mut self, setting: UnitFp) -> Result<UnitFp, _EngineError> {
+        //! commands a throttle setting, igniting the engine if it is off. Returns the setting taken, after limits.
+        //! A setting of zero or less is a cutoff.
+        if setting <= UnitFp::from_int(0) {
+            self._cutoff();
+            return Ok(UnitFp::from_int(0));
+        }
+        if self.propellant <= StepFp::from_int(0) {
+            return Err(_EngineError::Depleted);
+        }
+        if self.stuck {
+            return Err(_EngineError::StuckThrottle { at: self.commanded });
+        }
+        if self._is_lit() {
+            if self._roll() < self.rates.stuck_throttle_probability {
+                self.stuck = true;
+                return Err(_EngineError::StuckThrottle { at: self.commanded });
+            }
+        } else {
+            self._ignite()?;
+        }
+        self.commanded = setting.clamp(self.spec.min_throttle, UnitFp::from_int(1));
+        Ok(self.commanded)
+    }
+
+    pub(crate) fn _cutoff(&mut self) {
+        //! commands the engine off; it spools down from wherever it is. The cutoff valve is separate from the
+        //! throttle, so this works even with the throttle stuck, and frees it for the next burn.
+        self.commanded = UnitFp::from_int(0);
+        self.stuck = false;
+    }
+
+    pub(crate) fn _step(&mut self, now: SimTime) -> Result<(), _EngineError> {
+        //! advances the transients and propellant use to now. Running dry shuts the engine down at once; an engine
+        //! that is off burns nothing, so can't run dry.
+        let elapsed = now.elapsed_since(self.last_step);
+        let output = self._ramped(elapsed)?;
+        let seconds: StepFp = elapsed.as_fp().convert()?;
+        let mean_output = self.output.saturating_add(output).rshift(1); // (MR B.3) both within [0, 1].
+        let used = mean_output
+            .checked_scale_by_other(self._full_mass_flow()?)?
+            .checked_mul(seconds)?;
+        self.last_step = now;
+        if self._is_lit() && used >= self.propellant {
+            self.propellant = StepFp::from_int(0);
+            self.commanded = UnitFp::from_int(0);
+            self.output = UnitFp::from_int(0);
+            return Err(_EngineError::Depleted);
+        }
+        self.propellant = self.propellant.checked_sub(used)?;
+        self.output = output;
+        Ok(())
+    }
+
+    pub(crate) fn _acceleration(&self) -> Result<StepVec3D, ArithmeticError> {
+        //! the body-frame acceleration the engine's thrust currently gives the whole vehicle.
+        let thrust = self.output.checked_scale_by_other(self.spec.max_thrust)?;
+        let mass = self.spec.dry_mass.checked_add(self.propellant)?;
+        let force = self
+            .misalignment
+            .to_forward_vector()
+            .checked_scale_from_unit(thrust)?;
+        // (MR B.4) checked_div errors on a massless vehicle rather than dividing by zero.
+        Ok(Vec3D(
+            force.0.checked_div(mass)?,
+            force.1.checked_div(mass)?,
+            force.2.checked_div(mass)?,
+        ))
+    }
+
+    pub(crate) fn _apply(&mut self, rocket: &mut _Rocket) -> Result<(), ArithmeticError> {
+        //! brings the engine's share of the rocket's acceleration up to its current thrust, along its body axis as the
+        //! rocket is now oriented. Other sources' shares are left as they are.
+        let share = rocket._true_orientation().rotate(&self._acceleration()?)?;
+        rocket._add_acceleration(&share.checked_sub(&self.applied)?)?;
+        self.applied = share;
+        Ok(())
+    }
+
+    fn _ignite(&mut self) -> Result<(), _EngineError> {
+        //! attempts ignition from cold. A lit engine may have had its thrust axis knocked out of line.
+        if self._roll() < self.rates.no_ignition_probability {
+            return Err(_EngineError::NoIgnition);
+        }
+        if self._roll() < self.rates.misalignment_probability {
+            let bound = self.rates.max_misalignment;
+            self.misalignment = Quaternion::from_euler(EulerAngles {
+                roll: UnitFp::from_int(0), // a roll about the thrust axis doesn't move it.
+                pitch: _random_within(&mut self.rng, bound),
+                yaw: _random_within(&mut self.rng, bound),
+            });
+        }
+        Ok(())
+    }
+
+    fn _ramped(&self, elapsed: SimTime) -> Result<UnitFp, ArithmeticError> {
+        //! the output after ramping towards the commanded setting for elapsed, at the spool-up or spool-down rate.
+        if self.output < self.commanded {
+            let rise = _fraction_of(elapsed, self.spec.spool_up)?;
+            Ok(self.output.saturating_add(rise).min(self.commanded))
+        } else {
+            let fall = _fraction_of(elapsed, self.spec.spool_down)?;
+            Ok(self.output.saturating_sub(fall).max(self.commanded))
+        }
+    }
+
+    fn _full_mass_flow(&self) -> Result<StepFp, ArithmeticError> {
+        //! kg/s of propellant burnt at full thrust: thrust / (Isp * g0).
+        let exhaust_velocity = self.spec.specific_impulse.checked_mul(_STANDARD_GRAVITY)?;
+        self.spec.max_thrust.checked_div(exhaust_velocity) // (MR B.4) errors on a zero Isp.
+    }
+
+    fn _roll(&mut self) -> UnitFp {
+        //! uniformly distributed in [0, 1).
+        UnitFp::with_internal(self.rng.gen_range(0..UnitFp::from_int(1).internal()))
+    }
+}
+
+fn _fraction_of(elapsed: SimTime, duration: SimTime) -> Result<UnitFp, ArithmeticError> {
+    //! how much of duration has elapsed, capped at all of it. An instant duration has always fully elapsed.
+    if elapsed.has_reached(duration) {
+        return Ok(UnitFp::from_int(1));
+    }
+    // (MR B.4) duration is greater than elapsed, which is never negative, so is non-zero.
+    elapsed.as_fp().checked_div(duration.as_fp())?.convert()
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::float_arithmetic)] // this is test code.
+mod tests {
+    use super::*;
+    use agc_utils::{SolarVec3D, UnitVec3D};
+
+    const ALWAYS: UnitFp = UnitFp::from_f64_trusted(1.0);
+
+    fn engine(rates: _EngineFailureRates, propellant: f64) -> _MainEngine {
+        _MainEngine::_new(
+            _EngineSpec::_DEFAULT,
+            rates,
+            StepFp::from_f64(propellant).unwrap(),
+            24,
+            SimTime::ZERO,
+        )
+    }
+
+    fn close(value: UnitFp, expected: f64) -> bool {
+        (value.to_f64() - expected).abs() < 1e-6
+    }
+
+    #[test]
+    fn spools_up_and_down() {
+        let mut engine = engine(_EngineFailureRates::_NEVER_FAIL, 8_000.0);
+        assert!(!engine._is_lit());
+        assert_eq!(engine._throttle(ALWAYS), Ok(ALWAYS));
+        assert!(engine._is_lit());
+        engine._step(SimTime::from_secs(1)).unwrap();
+        assert!(close(engine._output(), 0.5), "{:?}", engine._output());
+        engine._step(SimTime::from_secs(3)).unwrap();
+        assert!(close(engine._output(), 1.0));
+        engine._cutoff();
+        engine._step(SimTime::from_f64(3.25).unwrap()).unwrap();
+        assert!(close(engine._output(), 0.75));
+        engine._step(SimTime::from_secs(10)).unwrap();
+        assert_eq!(engine._output(), UnitFp::from_int(0));
+        assert!(!engine._is_lit());
+    }
+
+    #[test]
+    fn throttle_is_limited() {
+        let mut engine = engine(_EngineFailureRates::_NEVER_FAIL, 8_000.0);
+        let low = UnitFp::from_f64(0.02).unwrap();
+        assert_eq!(
+            engine._throttle(low),
+            Ok(_EngineSpec::_DEFAULT.min_throttle)
+        );
+        assert_eq!(engine._throttle(UnitFp::from_int(3)), Ok(ALWAYS));
+        assert_eq!(
+            engine._throttle(UnitFp::from_int(-1)),
+            Ok(UnitFp::from_int(0))
+        );
+        assert!(!engine._is_lit());
+    }
+
+    #[test]
+    fn burns_propellant_by_specific_impulse() {
+        let mut engine = engine(_EngineFailureRates::_NEVER_FAIL, 8_000.0);
+        assert!(engine._throttle(ALWAYS).is_ok());
+        engine._step(SimTime::from_secs(2)).unwrap(); // through the transient.
+        let before = engine._propellant().to_f64();
+        engine._step(SimTime::from_secs(12)).unwrap();
+        // 45kN / (311s * 9.80665m/s^2) = 14.75kg/s.
+        let used = before - engine._propellant().to_f64();
+        assert!((used - 147.55).abs() < 0.1, "{used}");
+        // pushing 6500kg dry + what's left along the body x axis.
+        let acceleration = engine._acceleration().unwrap();
+        let expected = 45_000.0 / (6_500.0 + engine._propellant().to_f64());
+        assert!((acceleration.0.to_f64() - expected).abs() < 1e-6);
+        assert!(acceleration.1.to_f64().abs() < 1e-9 && acceleration.2.to_f64().abs() < 1e-9);
+    }
+
+    #[test]
+    fn running_dry_flames_out() {
+        let mut engine = engine(_EngineFailureRates::_NEVER_FAIL, 20.0);
+        assert!(engine._throttle(ALWAYS).is_ok());
+        engine._step(SimTime::from_secs(2)).unwrap();
+        assert_eq!(
+            engine._step(SimTime::from_secs(3)),
+            Err(_EngineError::Depleted)
+        );
+        assert_eq!(engine._propellant(), StepFp::from_int(0));
+        assert!(!engine._is_lit());
+        assert_eq!(engine._throttle(ALWAYS), Err(_EngineError::Depleted));
+        assert_eq!(engine._acceleration().unwrap(), StepVec3D::new());
+        // dry and off is simply off, not a fresh flameout each step.
+        assert_eq!(engine._step(SimTime::from_secs(4)), Ok(()));
+    }
+
+    #[test]
+    fn ignition_can_fail() {
+        let rates = _EngineFailureRates {
+            no_ignition_probability: ALWAYS,
+            .._EngineFailureRates::_NEVER_FAIL
+        };
+        let mut engine = engine(rates, 8_000.0);
+        assert_eq!(engine._throttle(ALWAYS), Err(_EngineError::NoIgnition));
+        engine._step(SimTime::from_secs(5)).unwrap();
+        assert!(!engine._is_lit());
+        assert_eq!(engine._propellant(), StepFp::from_int(8_000));
+    }
+
+    #[test]
+    fn stuck_throttle_holds_until_cutoff() {
+        let rates = _EngineFailureRates {
+            stuck_throttle_probability: ALWAYS,
+            .._EngineFailureRates::_NEVER_FAIL
+        };
+        let mut engine = engine(rates, 8_000.0);
+        let half = UnitFp::from_f64(0.5).unwrap();
+        assert_eq!(engine._throttle(half), Ok(half)); // ignition doesn't move the valve.
+        assert_eq!(
+            engine._throttle(ALWAYS),
+            Err(_EngineError::StuckThrottle { at: half })
+        );
+        assert_eq!(
+            engine._throttle(UnitFp::from_f64(0.2).unwrap()),
+            Err(_EngineError::StuckThrottle { at: half })
+        );
+        engine._step(SimTime::from_secs(5)).unwrap();
+        assert!(close(engine._output(), 0.5));
+        engine._cutoff();
+        engine._step(SimTime::from_secs(10)).unwrap();
+        assert!(!engine._is_lit());
+        assert_eq!(engine._throttle(half), Ok(half)); // a fresh burn has a free valve.
+    }
+
+    #[test]
+    fn misaligned_thrust_is_off_axis() {
+        let rates = _EngineFailureRates {
+            misalignment_probability: ALWAYS,
+            .._EngineFailureRates::_NEVER_FAIL
+        };
+        let mut engine = engine(rates, 8_000.0);
+        assert!(engine._throttle(ALWAYS).is_ok());
+        engine._step(SimTime::from_secs(2)).unwrap();
+        let acceleration = engine._acceleration().unwrap();
+        let x = UnitVec3D::from_floats(1.0, 0.0, 0.0).unwrap();
+        let off_axis = acceleration
+            .to_unit_vector()
+            .unwrap()
+            .angle_between(&x)
+            .unwrap();
+        assert!(off_axis > UnitFp::from_int(0));
+        assert!(off_axis.to_f64() < 0.015); // within 0.01 rad on each of two axes.
+    }
+
+    #[test]
+    fn thrust_follows_the_rocket_orientation() {
+        let mut engine = engine(_EngineFailureRates::_NEVER_FAIL, 8_000.0);
+        assert!(engine._throttle(ALWAYS).is_ok());
+        engine._step(SimTime::from_secs(2)).unwrap();
+        // yawed a quarter turn left, so the body x axis points along reference +y.
+        let orientation = Quaternion::from_euler(EulerAngles {
+            roll: UnitFp::from_int(0),
+            pitch: UnitFp::from_int(0),
+            yaw: UnitFp::FRAC_PI_2,
+        });
+        let mut rocket = _Rocket::_new(SolarVec3D::new(), StepVec3D::new(), orientation);
+        engine._apply(&mut rocket).unwrap();
+        let felt = rocket._true_specific_force().unwrap();
+        assert!(
+            felt.vector_to(&engine._acceleration().unwrap()).magnitude()
+                < StepFp::from_f64(1e-9).unwrap()
+        );
+    }
+
+    #[test]
+    fn thrust_adds_to_other_pushes() {
+        let mut engine = engine(_EngineFailureRates::_NEVER_FAIL, 8_000.0);
+        let push = StepVec3D::from_floats(0.0, 0.0, 1.0).unwrap();
+        let mut rocket = _Rocket::_new(SolarVec3D::new(), StepVec3D::new(), Quaternion::IDENTITY)
+            ._with_motion(StepVec3D::new(), push);
+        assert!(engine._throttle(ALWAYS).is_ok());
+        engine._step(SimTime::from_secs(2)).unwrap();
+        engine._apply(&mut rocket).unwrap();
+        let thrust = engine._acceleration().unwrap();
+        let tolerance = StepFp::from_f64(1e-9).unwrap();
+        let total = |rocket: &_Rocket| rocket._true_specific_force().unwrap();
+        assert!(total(&rocket).vector_to(&thrust.add(&push)).magnitude() < tolerance);
+        // applied again, only the change is added; and once cut off, only the other push is left.
+        engine._apply(&mut rocket).unwrap();
+        assert!(total(&rocket).vector_to(&thrust.add(&push)).magnitude() < tolerance);
+        engine._cutoff();
+        engine._step(SimTime::from_secs(5)).unwrap();
+        engine._apply(&mut rocket).unwrap();
+        assert!(total(&rocket).vector_to(&push).magnitude() < tolerance);
+    }
+}
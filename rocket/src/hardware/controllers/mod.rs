@@ -1 +1,2 @@
-
+pub mod engine; // main engine throttle, thrust and propellant
+pub mod reaction_wheels; // attitude control, and its momentum
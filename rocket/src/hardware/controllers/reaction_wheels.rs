@@ -0,0 +1,432 @@
+//! contains the reaction wheel assembly: one wheel per body axis, turning the rocket by spinning up against it. The
+//! flight controller commands a body-frame torque on the vehicle; each step, the wheels take up the opposite angular
+//! momentum, until a wheel reaches its momentum limit and can give no more torque that way. Wheel momentum can only
+//! be shed by firing thrusters against it (see _dump()); without them, unloading one way means turning the rocket the
+//! other. The wheels' electronics fail just as the sensors' do, and tachometers on each wheel report its momentum, so
+//! the assembly is driven through _SensorState's by a _FailureModel like any sensor. The controller drives the wheels
+//! directly rather than through a harness thread, so they carry their own failure model, rolled each _step():
+//!   Operational/Variant: the commanded torque is applied, with normal or 10x torque noise.
+//!   Garbage: the motors are driven with random torques, and the tachometers read garbage.
+//!   Frozen: the electronics hang, ignoring commands and holding the last torque; the tachometers go stale.
+//!   Rebooting: the motors are unpowered, so the wheels coast, giving no torque; the command is cleared.
+
+use std::fmt::Display;
+
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use tokio::sync::watch;
+
+use agc_utils::{ArithmeticError, SimClockReader, SimTime, StepFp, StepVec3D, Vec3D};
+
+use crate::hardware::rocket::_Rocket;
+use crate::hardware::sensors::{
+    _Sensor, _SensorReading,
+    _SensorState::{self, *},
+    _random_within,
+    failure::{_FailureModel, _FailureRates},
+};
+
+const _MAX_TORQUE: StepFp = StepFp::from_f64_trusted(1.0); // N m, per wheel.
+const _MOMENTUM_LIMIT: StepFp = StepFp::from_f64_trusted(50.0); // N m s, per wheel; at its top speed.
+const _TORQUE_NOISE: StepFp = StepFp::from_f64_trusted(0.001); // N m, per wheel; bound while Operational.
+const _TACHOMETER_NOISE: StepFp = StepFp::from_f64_trusted(0.01); // N m s, per wheel.
+const _VARIANT_NOISE_MULTIPLIER: StepFp = StepFp::from_f64_trusted(10.0);
+const _WHEEL_POLLING_PERIOD: SimTime = SimTime::from_secs(1);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum _WheelError {
+    Unresponsive { state: _SensorState }, // hung or rebooting; the command was not taken.
+}
+
+impl Display for _WheelError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            _WheelError::Unresponsive { state } => {
+                write!(
+                    f,
+                    "Reaction wheels unresponsive ({state:?}); command dropped"
+                )
+            }
+        }
+    }
+}
+
+pub(crate) struct _ReactionWheels {
+    /// Size: 880B (mostly the rngs); passed by reference.
+    state: _SensorState,
+    momentum: StepVec3D, // body frame, N m s; the wheels' stored angular momentum.
+    commanded: StepVec3D, // body frame, N m; the torque to put on the vehicle.
+    applying: StepVec3D, // body frame, N m; the torque the motors were last driven with.
+    last_reading: _SensorReading<StepVec3D>, // last tachometer reading of the wheels' momentum.
+    last_step: SimTime,
+    clock: SimClockReader,
+    rng: StdRng,             // seeded so that runs are reproducible.
+    failures: _FailureModel, // moves the electronics between states; seeded from rng.
+    polling_period: SimTime,
+    send_channel: watch::Sender<_SensorReading<StepVec3D>>,
+}
+
+impl _ReactionWheels {
+    pub(crate) fn _new(
+        clock: SimClockReader,
+        rates: _FailureRates,
+        seed: u64,
+    ) -> (Self, watch::Receiver<_SensorReading<StepVec3D>>) {
+        //! powers up the wheels at rest. Returns the receiving end of the tachometers' output channel.
+        let mut rng = StdRng::seed_from_u64(seed);
+        let reading = _SensorReading {
+            data: StepVec3D::new(),
+            time: clock.now(),
+        };
+        let (send_channel, receiver) = watch::channel(reading);
+        let wheels = Self {
+            state: Operational,
+            momentum: StepVec3D::new(),
+            commanded: StepVec3D::new(),
+            applying: StepVec3D::new(),
+            last_reading: reading,
+            last_step: clock.now(),
+            clock,
+            failures: _FailureModel::_new(rates, rng.gen()),
+            rng,
+            polling_period: _WHEEL_POLLING_PERIOD,
+            send_channel,
+        };
+        (wheels, receiver)
+    }
+
+    pub(crate) fn _momentum(&self) -> StepVec3D {
+        //! the wheels' true stored momentum; a thruster firing of the opposite impulse would dump it all.
+        self.momentum
+    }
+
+    pub(crate) fn _command_torque(&mut self, torque: &StepVec3D) -> Result<StepVec3D, _WheelError> {
+        //! commands a body-frame torque on the vehicle. Returns the torque taken, limited per axis to _MAX_TORQUE.
+        match self.state {
+            Operational | Variant | Garbage => {
+                self.commanded = Vec3D(_limit(torque.0), _limit(torque.1), _limit(torque.2));
+                Ok(self.commanded)
+            }
+            Frozen(_) | Rebooting(_) => Err(_WheelError::Unresponsive { state: self.state }),
+        }
+    }
+
+    pub(crate) fn _step(&mut self, now: SimTime) -> Result<StepVec3D, ArithmeticError> {
+        //! rolls the electronics' failures, then drives the wheels up to now. Returns the body-frame angular impulse
+        //! they gave the vehicle, which is less than commanded on any axis whose wheel reached its momentum limit.
+        let dt: StepFp = now.elapsed_since(self.last_step).as_fp().convert()?;
+        self.last_step = now;
+        // the model needs the wheels mutably while being part of them, so it's rolled on a copy that replaces it.
+        let mut failures = self.failures.clone();
+        failures._tick(self, now);
+        self.failures = failures;
+        self.applying = match self.state {
+            Operational => self._noisy_command(StepFp::from_int(1))?,
+            Variant => self._noisy_command(_VARIANT_NOISE_MULTIPLIER)?,
+            Garbage => _random_vec(&mut self.rng, _MAX_TORQUE),
+            Frozen(_) => self.applying,
+            Rebooting(_) => StepVec3D::new(),
+        };
+        // the wheels take up the opposite of the impulse given to the vehicle.
+        let unlimited = self
+            .momentum
+            .checked_sub(&self.applying.checked_scale(dt)?)?;
+        self._take_up(&unlimited)
+    }
+
+    pub(crate) fn _dump(
+        &mut self,
+        thruster_impulse: &StepVec3D,
+    ) -> Result<StepVec3D, ArithmeticError> {
+        //! holds the vehicle steady against a thruster firing, shedding wheel momentum into it. Returns the part of
+        //! the body-frame impulse the wheels couldn't hold against, which turns the vehicle; all of it unless the
+        //! electronics are working normally.
+        match self.state {
+            Operational | Variant => {
+                let unlimited = self.momentum.checked_add(thruster_impulse)?;
+                let held = self._take_up(&unlimited)?;
+                thruster_impulse.checked_add(&held)
+            }
+            Garbage | Frozen(_) | Rebooting(_) => Ok(*thruster_impulse),
+        }
+    }
+
+    fn _take_up(&mut self, unlimited: &StepVec3D) -> Result<StepVec3D, ArithmeticError> {
+        //! moves the wheels' momentum towards unlimited, stopping each at its limit. Returns the change in the
+        //! vehicle's momentum, which is equal and opposite to the wheels'.
+        let limited = Vec3D(
+            _saturate(unlimited.0),
+            _saturate(unlimited.1),
+            _saturate(unlimited.2),
+        );
+        let impulse = self.momentum.checked_sub(&limited)?;
+        self.momentum = limited;
+        Ok(impulse)
+    }
+
+    fn _noisy_command(&mut self, noise_multiplier: StepFp) -> Result<StepVec3D, ArithmeticError> {
+        //! the commanded torque, corrupted by the motors' torque noise.
+        let noise = _random_vec(&mut self.rng, _TORQUE_NOISE.checked_mul(noise_multiplier)?);
+        self.commanded.checked_add(&noise)
+    }
+}
+
+impl _Sensor for _ReactionWheels {
+    type Output = StepVec3D;
+
+    fn _poll(&mut self, _rocket: &_Rocket) {
+        //! reads the tachometers. The wheels' momentum is their own, so nothing is needed from the rocket.
+        let now = self.clock.now();
+        let noise_multiplier = match self.state {
+            Operational => StepFp::from_int(1),
+            Variant => _VARIANT_NOISE_MULTIPLIER,
+            Garbage => {
+                self.last_reading = _SensorReading {
+                    data: Vec3D(
+                        StepFp::with_internal(self.rng.gen()),
+                        StepFp::with_internal(self.rng.gen()),
+                        StepFp::with_internal(self.rng.gen()),
+                    ),
+                    time: now,
+                };
+                return;
+            }
+            Frozen(_) | Rebooting(_) => return, // hung or restarting: the held reading goes stale.
+        };
+        // (MR B.3) noise of at most 0.1 N m s on a momentum within the limit can't overflow.
+        let bound = _TACHOMETER_NOISE.saturating_mul(noise_multiplier);
+        let noise = _random_vec(&mut self.rng, bound);
+        self.last_reading = _SensorReading {
+            data: self.momentum.add(&noise),
+            time: now,
+        };
+    }
+
+    fn _update_drift(&mut self, _now: SimTime) {
+        //! wheel speed is counted from tachometer pulses, so doesn't drift.
+    }
+
+    fn _state(&self) -> _SensorState {
+        self.state
+    }
+
+    fn _set_state(&mut self, state: _SensorState) {
+        self.state = state;
+    }
+
+    fn _polling_period(&self) -> SimTime {
+        self.polling_period
+    }
+
+    fn _reboot(&mut self) {
+        //! the electronics come back with no torque commanded; the wheels keep whatever momentum they coasted with.
+        self.commanded = StepVec3D::new();
+        self.applying = StepVec3D::new();
+    }
+
+    fn _recalibrate(&mut self) {
+        //! nothing accumulates, so there's nothing to zero.
+    }
+
+    fn _last_reading(&self) -> _SensorReading<StepVec3D> {
+        self.last_reading
+    }
+
+    fn _output(&self) -> &watch::Sender<_SensorReading<StepVec3D>> {
+        &self.send_channel
+    }
+}
+
+fn _limit(torque: StepFp) -> StepFp {
+    //! a torque within what one wheel's motor can give.
+    torque.clamp(StepFp::from_int(0).saturating_sub(_MAX_TORQUE), _MAX_TORQUE)
+}
+
+fn _saturate(momentum: StepFp) -> StepFp {
+    //! a momentum within what one wheel can store.
+    momentum.clamp(
+        StepFp::from_int(0).saturating_sub(_MOMENTUM_LIMIT),
+        _MOMENTUM_LIMIT,
+    )
+}
+
+fn _random_vec(rng: &mut StdRng, bound: StepFp) -> StepVec3D {
+    //! a vector with each component independently uniform within [-bound, bound].
+    Vec3D(
+        _random_within(rng, bound),
+        _random_within(rng, bound),
+        _random_within(rng, bound),
+    )
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::float_arithmetic)] // this is test code.
+mod tests {
+    use super::*;
+    use agc_utils::{Quaternion, SimClock, SolarVec3D, UnitFp};
+
+    fn torque(z: f64) -> StepVec3D {
+        StepVec3D::from_floats(0.0, 0.0, z).unwrap()
+    }
+
+    fn wheels(clock: &SimClock) -> (_ReactionWheels, watch::Receiver<_SensorReading<StepVec3D>>) {
+        _ReactionWheels::_new(clock.reader(), _FailureRates::_NEVER_FAIL, 8)
+    }
+
+    fn close(value: StepFp, expected: f64, tolerance: f64) -> bool {
+        (value.to_f64() - expected).abs() <= tolerance
+    }
+
+    #[test]
+    fn torque_turns_the_rocket() {
+        let mut clock = SimClock::new();
+        let (mut wheels, _readings) = wheels(&clock);
+        let mut rocket = _Rocket::_new(SolarVec3D::new(), StepVec3D::new(), Quaternion::IDENTITY);
+        assert_eq!(wheels._command_torque(&torque(0.5)), Ok(torque(0.5)));
+        for _ in 0..10 {
+            clock.advance(SimTime::from_secs(1));
+            let impulse = wheels._step(clock.now()).unwrap();
+            rocket
+                ._turn(&impulse, &wheels._momentum(), SimTime::from_secs(1))
+                .unwrap();
+        }
+        // 5 N m s, give or take the torque noise, into the rocket and out of the wheel.
+        assert!(close(wheels._momentum().2, -5.0, 0.01));
+        assert!(close(rocket._true_angular_rate().2, 5.0 / 30_000.0, 1e-6));
+        assert!(rocket._true_orientation() != Quaternion::IDENTITY);
+    }
+
+    #[test]
+    fn torque_is_limited() {
+        let clock = SimClock::new();
+        let (mut wheels, _readings) = wheels(&clock);
+        let taken = wheels
+            ._command_torque(&StepVec3D::from_floats(3.0, -3.0, 0.25).unwrap())
+            .unwrap();
+        assert_eq!(
+            taken,
+            Vec3D(
+                _MAX_TORQUE,
+                StepFp::from_int(0).saturating_sub(_MAX_TORQUE),
+                StepFp::from_f64(0.25).unwrap()
+            )
+        );
+    }
+
+    #[test]
+    fn wheels_saturate() {
+        let mut clock = SimClock::new();
+        let (mut wheels, _readings) = wheels(&clock);
+        assert!(wheels._command_torque(&torque(1.0)).is_ok());
+        clock.advance(SimTime::from_secs(60));
+        let impulse = wheels._step(clock.now()).unwrap();
+        // only the 50 N m s the wheel could take reaches the vehicle.
+        assert_eq!(
+            wheels._momentum().2,
+            StepFp::from_int(0).saturating_sub(_MOMENTUM_LIMIT)
+        );
+        assert!(close(impulse.2, 50.0, 1e-9));
+        clock.advance(SimTime::from_secs(10));
+        assert!(close(wheels._step(clock.now()).unwrap().2, 0.0, 1e-9)); // saturated; no more torque.
+
+        // the other way is still free.
+        assert!(wheels._command_torque(&torque(-1.0)).is_ok());
+        clock.advance(SimTime::from_secs(10));
+        assert!(close(wheels._step(clock.now()).unwrap().2, -10.0, 0.02));
+    }
+
+    #[test]
+    fn dumping_needs_thrusters() {
+        let mut clock = SimClock::new();
+        let (mut wheels, _readings) = wheels(&clock);
+        assert!(wheels._command_torque(&torque(1.0)).is_ok());
+        clock.advance(SimTime::from_secs(60));
+        assert!(wheels._step(clock.now()).is_ok());
+        assert!(wheels._command_torque(&StepVec3D::new()).is_ok());
+        // thrusters fire to give the opposite of the stored momentum; the wheels hold the vehicle still against them.
+        let stored = wheels._momentum();
+        let firing = StepVec3D::new().checked_sub(&stored).unwrap();
+        let leftover = wheels._dump(&firing).unwrap();
+        assert_eq!(wheels._momentum(), StepVec3D::new());
+        assert_eq!(leftover, StepVec3D::new());
+        // a hung assembly can't hold against them, so the firing turns the vehicle instead.
+        wheels._set_state(Frozen(SimTime::from_secs(1_000)));
+        assert_eq!(wheels._dump(&firing).unwrap(), firing);
+        assert_eq!(wheels._momentum(), StepVec3D::new());
+    }
+
+    #[test]
+    fn faults_misdrive_the_wheels() {
+        let mut clock = SimClock::new();
+        let (mut wheels, _readings) = wheels(&clock);
+        assert!(wheels._command_torque(&torque(0.5)).is_ok());
+        // hung: commands are refused and the last torque keeps being applied.
+        clock.advance(SimTime::from_secs(1));
+        assert!(wheels._step(clock.now()).is_ok());
+        wheels._set_state(Frozen(SimTime::from_secs(100)));
+        assert!(matches!(
+            wheels._command_torque(&StepVec3D::new()),
+            Err(_WheelError::Unresponsive { .. })
+        ));
+        clock.advance(SimTime::from_secs(1));
+        assert!(close(wheels._step(clock.now()).unwrap().2, 0.5, 0.001));
+        // rebooting: the motors are off.
+        wheels._set_state(Rebooting(SimTime::from_secs(100)));
+        wheels._reboot();
+        clock.advance(SimTime::from_secs(1));
+        assert_eq!(wheels._step(clock.now()).unwrap(), StepVec3D::new());
+        // garbage: random, but still within what the motors can give. A burst set by hand would end on the next
+        // step's roll, so it's left to the model to start one.
+        let rates = _FailureRates {
+            garbage_probability: UnitFp::from_int(1),
+            .._FailureRates::_NEVER_FAIL
+        };
+        let (mut wheels, _readings) = _ReactionWheels::_new(clock.reader(), rates, 8);
+        clock.advance(SimTime::from_secs(1));
+        let impulse = wheels._step(clock.now()).unwrap();
+        assert_eq!(wheels._state(), Garbage);
+        for axis in [impulse.0, impulse.1, impulse.2] {
+            assert!(axis.abs() <= _MAX_TORQUE);
+        }
+        assert!(impulse != StepVec3D::new());
+    }
+
+    #[test]
+    fn tachometers_report_momentum() {
+        let mut clock = SimClock::new();
+        let (mut wheels, _readings) = wheels(&clock);
+        let rocket = _Rocket::_new(SolarVec3D::new(), StepVec3D::new(), Quaternion::IDENTITY);
+        assert!(wheels._command_torque(&torque(1.0)).is_ok());
+        clock.advance(SimTime::from_secs(20));
+        assert!(wheels._step(clock.now()).is_ok());
+        wheels._poll(&rocket);
+        let reading = wheels._last_reading();
+        assert_eq!(reading.time, clock.now());
+        assert!(close(reading.data.2, -20.0, 0.05));
+        wheels._set_state(Variant);
+        wheels._poll(&rocket);
+        assert!(close(wheels._last_reading().data.2, -20.0, 0.15));
+    }
+
+    #[test]
+    fn faults_arise_as_the_wheels_step() {
+        let mut clock = SimClock::new();
+        let rates = _FailureRates {
+            hang_probability: UnitFp::from_int(1),
+            .._FailureRates::_NEVER_FAIL
+        };
+        let (mut wheels, _readings) = _ReactionWheels::_new(clock.reader(), rates, 8);
+        assert!(wheels._command_torque(&torque(0.5)).is_ok());
+        clock.advance(SimTime::from_secs(1));
+        // hangs on its first step, holding the torque it was last driven with; none yet.
+        assert_eq!(wheels._step(clock.now()).unwrap(), StepVec3D::new());
+        assert_eq!(wheels._state(), Frozen(SimTime::from_secs(31)));
+        assert!(matches!(
+            wheels._command_torque(&StepVec3D::new()),
+            Err(_WheelError::Unresponsive { .. })
+        ));
+        // and recovers by itself once the hang is over.
+        clock.advance(SimTime::from_secs(30));
+        assert!(wheels._step(clock.now()).is_ok());
+        assert_eq!(wheels._state(), Operational);
+    }
+}
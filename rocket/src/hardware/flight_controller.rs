@@ -1,6 +1,313 @@
 //! Contains instantiation logic for the flight controller. This also manages the creation and thread spawning for other sensors, and their linking to the flight controller
 //!
 
+use std::time::Instant;
+
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use tokio::sync::{mpsc, watch};
+
+use agc_utils::{SimClockReader, SimTime, SolarFp, SolarVec3D};
+
+use crate::hardware::controllers::engine::{
+    _EngineFailureRates, _EngineSpec, _MainEngine, _FULL_LOAD,
+};
+use crate::hardware::ground::_MissionControl;
+use crate::hardware::rocket::_Rocket;
+use crate::hardware::sensors::{
+    _Sensor, _SensorReading,
+    altimeter::_AltimeterData,
+    command::{_CommandError, _SensorCommand, _command_channel, _send},
+    failure::{_FailureModel, _FailureRates},
+    harness::{_HarnessError, _SensorLinks, _SensorThread, _SHUTDOWN_TIMEOUT},
+    inertial_platform::{_InertialPlatformData, _InertialReading},
+    scheduler::{_JitterConfig, _PollScheduler},
+};
+use crate::hardware::sighting::_StarSighting;
+use crate::logic::burn::_Burn;
+use crate::logic::compute::{_ComputeBudget, _TOKEN_CAPACITY, _TOKEN_REGEN_PER_SECOND};
+use crate::logic::executive::_Executive;
+use crate::logic::navigation::{_NavFilter, _NavState};
+use crate::logic::restart::_RestartTable;
+use crate::logic::validation::_Validator;
+use crate::logic::watchdog::{_Watchdog, _WatchdogConfig};
+
 pub struct _FlightController {
-    //true_location:
+    pub(crate) altimeter: _SensorLink<SolarFp>,
+    pub(crate) inertial_platform: _SensorLink<_InertialReading>,
+    pub(crate) budget: _ComputeBudget,
+    pub(crate) executive: _Executive,
+    pub(crate) restart_table: _RestartTable, // survives restarts; see restart.rs.
+    pub(crate) nav: Option<_NavFilter>, // the believed state; None until there's a nav solution.
+    pub(crate) reference_body: SolarVec3D, // centre of the body the altimeter ranges to; refreshed by each uplink.
+    pub(crate) burn: Option<_Burn>,
+    pub(crate) engine: _MainEngine, // commanded by the burn in progress.
+    pub(crate) sighting: _StarSighting,
+    pub(crate) ground: _MissionControl,
+    pub(crate) clock: SimClockReader,
+    sensors: _SensorThreads,
+}
+
+pub(crate) struct _SensorLink<T> {
+    /// The controller's two ends of a sensor thread's channels, the checks its readings must pass, the watchdog on
+    /// its liveness, and the last reading that passed. Size: 136B + 2T.
+    pub(crate) readings: watch::Receiver<_SensorReading<T>>,
+    pub(crate) commands: mpsc::Sender<_SensorCommand>,
+    pub(crate) validator: _Validator<T>,
+    pub(crate) watchdog: _Watchdog,
+    pub(crate) latest: Option<_SensorReading<T>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum _SensorId {
+    Altimeter,
+    InertialPlatform,
+}
+
+struct _SensorThreads {
+    /// Every running sensor thread, plus the signal that stops them all. Fixed fields rather than a collection, so
+    /// everything stays on the stack (MR F.1).
+    shutdown: watch::Sender<bool>,
+    altimeter: _SensorThread,
+    inertial_platform: _SensorThread,
+}
+
+impl _FlightController {
+    pub fn _launch(
+        clock: SimClockReader,
+        truth: watch::Receiver<_Rocket>,
+        seed: u64,
+    ) -> Result<Self, _HarnessError> {
+        //! powers up every sensor on its own thread, wired to this controller. Seeds for each sensor, failure model and
+        //! scheduler are all drawn from the one seed, so a whole run is reproducible.
+        let mut seeds = StdRng::seed_from_u64(seed);
+        let (initial_nav, reference_body) = _initial_nav(&truth, clock.now());
+        let (shutdown, shutdown_receiver) = watch::channel(false);
+
+        let (altimeter, altimeter_readings) = _AltimeterData::_new(clock.clone(), seeds.gen());
+        let (altimeter, altimeter_link) = _spawn(
+            "altimeter",
+            altimeter,
+            altimeter_readings,
+            &mut seeds,
+            &clock,
+            &truth,
+            &shutdown_receiver,
+        )?;
+
+        let (platform, platform_readings) = _InertialPlatformData::_new(clock.clone(), seeds.gen());
+        // (MR B.5) if this spawn fails, dropping `shutdown` on return stops the altimeter thread already running.
+        let (inertial_platform, platform_link) = _spawn(
+            "inertial platform",
+            platform,
+            platform_readings,
+            &mut seeds,
+            &clock,
+            &truth,
+            &shutdown_receiver,
+        )?;
+        let (engine, sighting, ground) = _actuators_and_services(&mut seeds, truth, &clock);
+
+        Ok(Self {
+            altimeter: altimeter_link,
+            inertial_platform: platform_link,
+            budget: _ComputeBudget::_new(_TOKEN_CAPACITY, _TOKEN_REGEN_PER_SECOND, clock.now()),
+            executive: _Executive::_new(),
+            restart_table: _RestartTable::default(),
+            nav: Some(_NavFilter::_new(&initial_nav)),
+            reference_body,
+            burn: None,
+            engine,
+            sighting,
+            ground,
+            clock,
+            sensors: _SensorThreads {
+                shutdown,
+                altimeter,
+                inertial_platform,
+            },
+        })
+    }
+
+    pub(crate) fn _command(
+        &self,
+        sensor: _SensorId,
+        command: _SensorCommand,
+    ) -> Result<(), _CommandError> {
+        //! sends a command to a sensor without waiting; a hung sensor can't stall the controller (MR G.3).
+        let commands = match sensor {
+            _SensorId::Altimeter => &self.altimeter.commands,
+            _SensorId::InertialPlatform => &self.inertial_platform.commands,
+        };
+        _send(commands, command)
+    }
+
+    pub fn _shutdown(self) -> Result<(), _HarnessError> {
+        //! stops every sensor thread, waiting at most _SHUTDOWN_TIMEOUT in total. All threads are signalled and
+        //! waited on even if one fails; the first failure is returned.
+        let threads = self.sensors;
+        threads.shutdown.send_replace(true);
+        let deadline = Instant::now()
+            .checked_add(_SHUTDOWN_TIMEOUT)
+            .unwrap_or_else(Instant::now); // (MR B.1) only fails if the clock is near its limit; then don't wait.
+        let altimeter = threads.altimeter._join_by(deadline);
+        let inertial_platform = threads.inertial_platform._join_by(deadline);
+        altimeter.and(inertial_platform)
+    }
+}
+
+fn _initial_nav(truth: &watch::Receiver<_Rocket>, now: SimTime) -> (_NavState, SolarVec3D) {
+    //! the state vector and reference body loaded from the ground before launch; the only time the controller is
+    //! given the truth.
+    let rocket = truth.borrow();
+    let nav = _NavState {
+        position: rocket._true_position(),
+        velocity: rocket._true_velocity(),
+        attitude: rocket._true_orientation(),
+        at: now,
+    };
+    (nav, rocket._true_nearest_body())
+}
+
+fn _actuators_and_services(
+    seeds: &mut StdRng,
+    truth: watch::Receiver<_Rocket>,
+    clock: &SimClockReader,
+) -> (_MainEngine, _StarSighting, _MissionControl) {
+    //! the engine, and the crew's sightings and Mission Control that correct navigation; none has a thread of its own.
+    let sighting = _StarSighting::_new(truth.clone(), seeds.gen());
+    let ground = _MissionControl::_new(truth, seeds.gen());
+    let engine = _MainEngine::_new(
+        _EngineSpec::_DEFAULT,
+        _EngineFailureRates::_DEFAULT,
+        _FULL_LOAD,
+        seeds.gen(),
+        clock.now(),
+    );
+    (engine, sighting, ground)
+}
+
+fn _spawn<S: _Sensor + Send + 'static>(
+    name: &'static str,
+    sensor: S,
+    readings: watch::Receiver<_SensorReading<S::Output>>,
+    seeds: &mut StdRng,
+    clock: &SimClockReader,
+    truth: &watch::Receiver<_Rocket>,
+    shutdown: &watch::Receiver<bool>,
+) -> Result<(_SensorThread, _SensorLink<S::Output>), _HarnessError> {
+    //! starts a sensor on its own thread with default failure rates and jitter, starting from the current sim time.
+    //! Returns the thread and the controller's link to it.
+    let period = sensor._polling_period();
+    let scheduler = _PollScheduler::_new(period, _JitterConfig::_DEFAULT, seeds.gen(), clock.now());
+    let (commands, command_receiver) = _command_channel();
+    let links = _SensorLinks {
+        clock: clock.clone(),
+        truth: truth.clone(),
+        shutdown: shutdown.clone(),
+        commands: command_receiver,
+    };
+    let thread = _SensorThread::_spawn(
+        name,
+        sensor,
+        _FailureModel::_new(_FailureRates::_DEFAULT, seeds.gen()),
+        scheduler,
+        links,
+    )?;
+    let link = _SensorLink {
+        readings,
+        commands,
+        validator: _Validator::default(),
+        watchdog: _Watchdog::_new(period, _WatchdogConfig::_DEFAULT, clock.now()),
+        latest: None,
+    };
+    Ok((thread, link))
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::arithmetic_side_effects)] // this is test code.
+mod tests {
+    use super::*;
+    use crate::hardware::sensors::command::_COMMAND_CAPACITY;
+    use crate::hardware::sensors::harness::_wait_for_change;
+    use agc_utils::{Quaternion, SimClock, SimTime, SolarVec3D, StepVec3D};
+    use std::time::Duration;
+
+    fn truth() -> _Rocket {
+        _Rocket::_new(
+            SolarVec3D::from_floats(10_000.0, 0.0, 0.0).unwrap(),
+            StepVec3D::new(),
+            Quaternion::IDENTITY,
+        )
+    }
+
+    #[test]
+    fn every_sensor_reports_to_the_controller() {
+        let mut clock = SimClock::new();
+        let (_truth_sender, truth_receiver) = watch::channel(truth());
+        let mut controller = _FlightController::_launch(clock.reader(), truth_receiver, 7).unwrap();
+        clock.advance(SimTime::from_secs(2));
+        assert!(_wait_for_change(&mut controller.altimeter.readings));
+        assert!(_wait_for_change(&mut controller.inertial_platform.readings));
+        controller._shutdown().unwrap();
+    }
+
+    #[test]
+    fn shutdown_stops_every_sensor() {
+        let clock = SimClock::new();
+        let (_truth_sender, truth_receiver) = watch::channel(truth());
+        let controller = _FlightController::_launch(clock.reader(), truth_receiver, 7).unwrap();
+        let altimeter = controller.altimeter.commands.clone();
+        let inertial_platform = controller.inertial_platform.commands.clone();
+        // within _SHUTDOWN_TIMEOUT, or this would be an error.
+        controller._shutdown().unwrap();
+        assert_eq!(
+            _send(&altimeter, _SensorCommand::PowerOn),
+            Err(_CommandError::Disconnected {
+                command: _SensorCommand::PowerOn
+            })
+        );
+        assert!(_send(&inertial_platform, _SensorCommand::PowerOn).is_err());
+    }
+
+    #[test]
+    fn commands_never_block_the_controller() {
+        let clock = SimClock::new();
+        let (_truth_sender, truth_receiver) = watch::channel(truth());
+        let mut controller = _FlightController::_launch(clock.reader(), truth_receiver, 7).unwrap();
+        // hung sensors: their queues are never drained.
+        let (commands, _hung_altimeter) = _command_channel();
+        controller.altimeter.commands = commands;
+        let (commands, _hung_inertial_platform) = _command_channel();
+        controller.inertial_platform.commands = commands;
+        for sensor in [_SensorId::Altimeter, _SensorId::InertialPlatform] {
+            for _ in 0.._COMMAND_CAPACITY {
+                controller._command(sensor, _SensorCommand::Reboot).unwrap();
+            }
+            assert_eq!(
+                controller._command(sensor, _SensorCommand::Recalibrate),
+                Err(_CommandError::Full {
+                    command: _SensorCommand::Recalibrate
+                })
+            );
+        }
+        controller._shutdown().unwrap();
+    }
+
+    #[test]
+    fn commands_to_stopped_sensors_are_reported() {
+        let clock = SimClock::new();
+        let (truth_sender, truth_receiver) = watch::channel(truth());
+        let controller = _FlightController::_launch(clock.reader(), truth_receiver, 7).unwrap();
+        drop(truth_sender); // the simulation ends, so the sensor threads exit.
+        let deadline = Instant::now() + Duration::from_secs(1);
+        let mut result = Ok(());
+        while Instant::now() < deadline && result.is_ok() {
+            result = controller._command(_SensorId::Altimeter, _SensorCommand::PowerOn);
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        assert!(matches!(
+            result,
+            Err(_CommandError::Disconnected { .. } | _CommandError::Full { .. })
+        ));
+    }
 }
@@ -0,0 +1,242 @@
+//! contains Mission Control (constraint 5b). On request, the rocket signals Earth: the signal takes the one-way light
+//! time to arrive, the ground computes for a minute from its tracking data, and the state vector it sends back takes
+//! the light time again to return. Contacts are limited to one a day, and only one can be in flight at a time.
+//! Like the crew, the ground isn't onboard hardware, so there's no thread; just a request the controller makes and
+//! later collects. Mission Control propagates its solution to when the uplink will arrive, so the state vector is
+//! taken from the truth at that moment, with the tracking network's errors added. The uplink also names the centre of
+//! the body beneath the rocket, for the altimeter to range against; the ground knows it exactly from its ephemeris.
+
+use std::fmt::Display;
+
+use rand::{rngs::StdRng, SeedableRng};
+use tokio::sync::watch;
+
+use agc_utils::{FixedPoint, SimTime, SolarFp, SolarVec3D, StepFp, StepVec3D, Vec3D};
+
+use crate::hardware::quota::_DailyQuota;
+use crate::hardware::rocket::_Rocket;
+use crate::hardware::sensors::_random_within;
+
+pub(crate) const _PROCESSING_TIME: SimTime = SimTime::from_secs(60);
+pub(crate) const _CONTACTS_PER_DAY: u8 = 1;
+const _SPEED_OF_LIGHT: SolarFp = SolarFp::from_f64_trusted(299_792_458.0); // m/s.
+const _POSITION_ERROR_BOUNDS: SolarFp = SolarFp::from_f64_trusted(100.0); // m, per axis.
+const _VELOCITY_ERROR_BOUNDS: StepFp = StepFp::from_f64_trusted(0.01); // m/s, per axis.
+
+// the variances of errors uniform within the bounds above: bound^2 / 3.
+pub(crate) const _GROUND_POSITION_VARIANCE: FixedPoint<20> =
+    FixedPoint::<20>::from_f64_trusted(3_333.0); // m^2.
+pub(crate) const _GROUND_VELOCITY_VARIANCE: FixedPoint<20> =
+    FixedPoint::<20>::from_f64_trusted(3.3e-5); // m^2/s^2.
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct _StateVector {
+    /// Where Mission Control tracked the rocket to be, and how it's moving. Size: 80B.
+    pub(crate) position: SolarVec3D,
+    pub(crate) velocity: StepVec3D,
+    pub(crate) reference_body: SolarVec3D, // centre of the body beneath the rocket, from the ground's ephemeris.
+    pub(crate) at: SimTime,                // when the state vector applies; the time it arrives.
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum _ContactError {
+    Busy { until: SimTime },           // a contact is already in flight.
+    QuotaExhausted { until: SimTime }, // today's contact has been used; the next is available from `until`.
+    NoSignal { at: SimTime }, // no signal path can be computed, e.g. the Earth isn't known; the quota isn't spent.
+    Garbled { at: SimTime },  // the uplink arrived, but couldn't be turned into a state vector.
+}
+
+impl Display for _ContactError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            _ContactError::Busy { until } => {
+                write!(f, "Mission Control contact already in flight until {until}")
+            }
+            _ContactError::QuotaExhausted { until } => {
+                write!(
+                    f,
+                    "No Mission Control contact left today; next available at {until}"
+                )
+            }
+            _ContactError::NoSignal { at } => write!(f, "No signal path to Earth at {at}"),
+            _ContactError::Garbled { at } => {
+                write!(f, "Mission Control uplink at {at} was garbled")
+            }
+        }
+    }
+}
+
+pub(crate) struct _MissionControl {
+    /// Size: 368B (mostly the rng); passed by reference.
+    quota: _DailyQuota,
+    due: Option<SimTime>, // when the state vector from the contact in flight arrives.
+    truth: watch::Receiver<_Rocket>,
+    rng: StdRng, // seeded so that runs are reproducible.
+}
+
+impl _MissionControl {
+    pub(crate) fn _new(truth: watch::Receiver<_Rocket>, seed: u64) -> Self {
+        //! a ground station ready for contact, with a full day's quota.
+        Self {
+            quota: _DailyQuota::_new(_CONTACTS_PER_DAY),
+            due: None,
+            truth,
+            rng: StdRng::seed_from_u64(seed),
+        }
+    }
+
+    pub(crate) fn _remaining(&self, now: SimTime) -> u8 {
+        //! contacts left today.
+        self.quota._remaining(now)
+    }
+
+    pub(crate) fn _is_ready(&self, now: SimTime) -> bool {
+        //! whether a state vector has arrived and is waiting to be collected.
+        self.due.is_some_and(|due| now.has_reached(due))
+    }
+
+    pub(crate) fn _request(&mut self, now: SimTime) -> Result<SimTime, _ContactError> {
+        //! signals Earth for a state vector. Returns when it will arrive.
+        if let Some(until) = self.due {
+            return Err(_ContactError::Busy { until });
+        }
+        let light_time = self
+            ._light_time()
+            .ok_or(_ContactError::NoSignal { at: now })?;
+        self.quota
+            ._try_use(now)
+            .map_err(|until| _ContactError::QuotaExhausted { until })?;
+        // (MR B.3) a saturated arrival is a contact never answered.
+        let due = now
+            .saturating_add(light_time)
+            .saturating_add(_PROCESSING_TIME)
+            .saturating_add(light_time);
+        self.due = Some(due);
+        Ok(due)
+    }
+
+    pub(crate) fn _collect(&mut self, now: SimTime) -> Option<Result<_StateVector, _ContactError>> {
+        //! takes the state vector from a completed contact, freeing the link for the next. None if none has arrived.
+        let due = self.due.filter(|due| now.has_reached(*due))?;
+        self.due = None;
+        Some(self._state_vector(due))
+    }
+
+    fn _light_time(&self) -> Option<SimTime> {
+        //! how long a signal takes to cross between the rocket and the Earth, as they are now. None if unknowable.
+        let distance = self.truth.borrow()._true_distance_to_earth()?;
+        let seconds = distance.checked_div(_SPEED_OF_LIGHT).ok()?; // (MR B.4) a non-zero constant.
+        Some(SimTime::from_fp(seconds.convert().ok()?))
+    }
+
+    fn _state_vector(&mut self, at: SimTime) -> Result<_StateVector, _ContactError> {
+        //! the rocket's true position and velocity, corrupted by the tracking network's errors.
+        let rocket = self.truth.borrow();
+        let position_error = Vec3D(
+            _random_within(&mut self.rng, _POSITION_ERROR_BOUNDS),
+            _random_within(&mut self.rng, _POSITION_ERROR_BOUNDS),
+            _random_within(&mut self.rng, _POSITION_ERROR_BOUNDS),
+        );
+        let velocity_error = Vec3D(
+            _random_within(&mut self.rng, _VELOCITY_ERROR_BOUNDS),
+            _random_within(&mut self.rng, _VELOCITY_ERROR_BOUNDS),
+            _random_within(&mut self.rng, _VELOCITY_ERROR_BOUNDS),
+        );
+        // (MR B.5) a state at the edge of the representable system can't take the error; the uplink is wasted.
+        let garbled = |_| _ContactError::Garbled { at };
+        Ok(_StateVector {
+            position: rocket
+                ._true_position()
+                .checked_add(&position_error)
+                .map_err(garbled)?,
+            velocity: rocket
+                ._true_velocity()
+                .checked_add(&velocity_error)
+                .map_err(garbled)?,
+            reference_body: rocket._true_nearest_body(),
+            at,
+        })
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::float_arithmetic)] // this is test code.
+mod tests {
+    use super::*;
+    use agc_utils::Quaternion;
+
+    // exactly ten light-seconds out from the Earth.
+    const EARTH: SolarVec3D = SolarVec3D::from_floats_trusted(1e11, 0.0, 0.0);
+    const ROCKET: SolarVec3D = SolarVec3D::from_floats_trusted(1e11 + 2_997_924_580.0, 0.0, 0.0);
+
+    fn ground(position: SolarVec3D) -> (_MissionControl, watch::Sender<_Rocket>) {
+        let rocket = _Rocket::_new(
+            position,
+            StepVec3D::from_floats(0.0, 1_000.0, 0.0).unwrap(),
+            Quaternion::IDENTITY,
+        )
+        ._with_earth(EARTH);
+        let (sender, truth) = watch::channel(rocket);
+        (_MissionControl::_new(truth, 5), sender)
+    }
+
+    #[test]
+    fn round_trip_is_two_light_times_and_a_minute() {
+        let (mut ground, _truth) = ground(ROCKET);
+        let now = SimTime::from_secs(100);
+        let due = ground._request(now).unwrap();
+        assert!((due.to_f64() - 180.0).abs() < 0.05, "{due}");
+        assert_eq!(
+            ground._request(now),
+            Err(_ContactError::Busy { until: due })
+        );
+        assert!(!ground._is_ready(now));
+        assert_eq!(ground._collect(now), None);
+        assert!(ground._is_ready(due));
+        let update = ground._collect(due).unwrap().unwrap();
+        assert_eq!(update.at, due);
+        assert_eq!(ground._collect(due), None); // collected once only.
+    }
+
+    #[test]
+    fn state_vector_is_close_to_truth() {
+        let (mut ground, _truth) = ground(ROCKET);
+        let due = ground._request(SimTime::ZERO).unwrap();
+        let update = ground._collect(due).unwrap().unwrap();
+        let position_error = update.position.vector_to(&ROCKET);
+        for component in [position_error.0, position_error.1, position_error.2] {
+            assert!(component.to_f64().abs() <= 100.0);
+        }
+        let velocity_error =
+            update.velocity.0.to_f64().abs() + (update.velocity.1.to_f64() - 1_000.0).abs();
+        assert!(velocity_error <= 0.02);
+        assert!(update.position != ROCKET); // tracking isn't perfect.
+    }
+
+    #[test]
+    fn one_contact_a_day() {
+        let (mut ground, _truth) = ground(ROCKET);
+        let due = ground._request(SimTime::from_secs(10)).unwrap();
+        assert!(ground._collect(due).is_some());
+        assert_eq!(ground._remaining(due), 0);
+        assert_eq!(
+            ground._request(due),
+            Err(_ContactError::QuotaExhausted {
+                until: SimTime::from_secs(86_400)
+            })
+        );
+        assert!(ground._request(SimTime::from_secs(86_400)).is_ok());
+    }
+
+    #[test]
+    fn no_signal_path_spends_nothing() {
+        let edge = SolarFp::with_internal(i64::MAX);
+        let (mut ground, _truth) = ground(Vec3D(edge, edge, edge));
+        assert_eq!(
+            ground._request(SimTime::ZERO),
+            Err(_ContactError::NoSignal { at: SimTime::ZERO })
+        );
+        assert_eq!(ground._remaining(SimTime::ZERO), _CONTACTS_PER_DAY);
+        assert!(!ground._is_ready(SimTime::from_secs(1_000_000)));
+    }
+}
@@ -3,5 +3,8 @@
 
 pub mod controllers;
 pub mod flight_controller;
+pub mod ground; // Mission Control contact
+pub mod quota; // daily limits on drift corrections
 pub mod rocket;
 pub mod sensors;
+pub mod sighting; // crew star sightings
@@ -0,0 +1,93 @@
+//! contains the daily quota shared by the drift-correcting services (constraint 5): a fixed number of uses per sim day.
+//! Days are counted from launch, and the count resets at the start of each one; unused uses don't carry over.
+
+use agc_utils::{SimTime, TimeFp};
+
+const _DAY: SimTime = SimTime::from_secs(86_400);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct _DailyQuota {
+    /// Size: 16B.
+    limit: u8,
+    used: u8,
+    day: i64, // the day `used` counts for.
+}
+
+impl _DailyQuota {
+    pub(crate) const fn _new(limit: u8) -> Self {
+        Self {
+            limit,
+            used: 0,
+            day: 0,
+        }
+    }
+
+    pub(crate) fn _remaining(&self, now: SimTime) -> u8 {
+        //! uses left on the day containing now.
+        if _day_of(now) == self.day {
+            self.limit.saturating_sub(self.used) // (MR B.3) used never exceeds limit; 0 regardless.
+        } else {
+            self.limit
+        }
+    }
+
+    pub(crate) fn _try_use(&mut self, now: SimTime) -> Result<(), SimTime> {
+        //! takes one use from the day containing now. Errors, with the start of the next day, if there are none left.
+        let day = _day_of(now);
+        if day != self.day {
+            self.day = day;
+            self.used = 0;
+        }
+        if self.used >= self.limit {
+            return Err(_start_of(day.saturating_add(1))); // (MR B.3) the day count can't reach i64::MAX.
+        }
+        self.used = self.used.saturating_add(1); // (MR B.3) below limit, so can't saturate.
+        Ok(())
+    }
+}
+
+fn _day_of(now: SimTime) -> i64 {
+    //! the whole days since launch.
+    // (MR B.4) _DAY is a non-zero constant; checked_div only fails on i64::MIN / -1, which can't occur.
+    now.as_fp()
+        .internal()
+        .checked_div(_DAY.as_fp().internal())
+        .unwrap_or(0)
+}
+
+fn _start_of(day: i64) -> SimTime {
+    //! the time a day begins; saturating at the end of representable time.
+    let start = day.saturating_mul(_DAY.as_fp().internal()); // (MR B.3) a saturated start is just "never".
+    SimTime::from_fp(TimeFp::with_internal(start))
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)] // this is test code.
+mod tests {
+    use super::*;
+
+    #[test]
+    fn limits_uses_per_day() {
+        let mut quota = _DailyQuota::_new(2);
+        let now = SimTime::from_secs(100);
+        assert_eq!(quota._remaining(now), 2);
+        quota._try_use(now).unwrap();
+        quota._try_use(now).unwrap();
+        assert_eq!(quota._remaining(now), 0);
+        assert_eq!(quota._try_use(now), Err(_DAY));
+    }
+
+    #[test]
+    fn resets_each_day() {
+        let mut quota = _DailyQuota::_new(1);
+        quota._try_use(SimTime::from_secs(86_399)).unwrap();
+        assert!(quota._try_use(SimTime::from_secs(86_399)).is_err());
+        assert_eq!(quota._remaining(_DAY), 1);
+        quota._try_use(_DAY).unwrap();
+        assert_eq!(
+            quota._try_use(SimTime::from_secs(3 * 86_400 - 1)),
+            Ok(()) // unused days don't matter either way.
+        );
+        assert_eq!(_DailyQuota::_new(0)._try_use(SimTime::ZERO), Err(_DAY));
+    }
+}
@@ -2,10 +2,374 @@
 //! As such, these instruments will need to query Rocket for information from time to time; e.g. the altimeter needs to know the true distance to the surface in order to produce an unknown one.
 //! Instruments must never store values acquired directly from Rocket without processing them to add their own inaccuracy first (this would be cheating!)
 
-use agc_utils::{Quaternion, SolarVec3D, StepVec3D};
+use tokio::sync::watch;
 
+use agc_physics::orbit::SimulationError;
+use agc_physics::planets::{Body, BODIES, N_BODIES};
+use agc_physics::stars::visible_from;
+use agc_physics::System;
+use agc_utils::{
+    ArithmeticError, Quaternion, QuaternionError, SimTime, SolarFp, SolarVec3D, StepFp, StepVec3D,
+    UnitFp, Vec3D,
+};
+
+// principal moments of inertia about the body axes, kg m^2; roughly those of a loaded lunar module.
+const _DEFAULT_INERTIA: StepVec3D = StepVec3D::from_floats_trusted(20_000.0, 30_000.0, 30_000.0);
+
+#[derive(Debug, Clone)]
 pub struct _Rocket {
     position: SolarVec3D,
     velocity: StepVec3D,
     orientation: Quaternion,
+    angular_velocity: StepVec3D,               // body frame, rad/s.
+    non_gravitational_acceleration: StepVec3D, // reference frame, m/s^2. Thrust etc; gravity is excluded as no accelerometer can sense it.
+    nearest_body: SolarVec3D, // centre of the body currently beneath the rocket; what the altimeter ranges against.
+    earth: Option<SolarVec3D>, // centre of the Earth, where Mission Control is; None if it isn't among the bodies.
+    bodies: [Body; N_BODIES], // the bodies as the simulation last placed them; what hides the stars.
+    tracked_bodies: usize,    // how many of them the simulation places; the rest aren't there.
+    inertia: StepVec3D,       // principal moments of inertia about the body axes, kg m^2.
+}
+
+impl _Rocket {
+    pub fn _new(position: SolarVec3D, velocity: StepVec3D, orientation: Quaternion) -> Self {
+        //! creates a rocket at the given state, not rotating and with no engines firing. The bodies, the Earth and the
+        //! nearest among them, start where they were at epoch, until the simulation steps.
+        Self {
+            position,
+            velocity,
+            orientation,
+            angular_velocity: StepVec3D::new(),
+            non_gravitational_acceleration: StepVec3D::new(),
+            nearest_body: _nearest_in(&BODIES, &position).unwrap_or_default(),
+            earth: _earth_in(&BODIES),
+            bodies: BODIES,
+            tracked_bodies: N_BODIES,
+            inertia: _DEFAULT_INERTIA,
+        }
+    }
+
+    pub fn _with_motion(
+        mut self,
+        angular_velocity: StepVec3D,
+        non_gravitational_acceleration: StepVec3D,
+    ) -> Self {
+        //! sets the rocket's rotation rate (body frame) and non-gravitational acceleration (reference frame).
+        self.angular_velocity = angular_velocity;
+        self.non_gravitational_acceleration = non_gravitational_acceleration;
+        self
+    }
+
+    pub fn _with_nearest_body(mut self, nearest_body: SolarVec3D) -> Self {
+        //! sets the centre of the body the rocket is currently closest to.
+        self.nearest_body = nearest_body;
+        self
+    }
+
+    pub fn _with_earth(mut self, earth: SolarVec3D) -> Self {
+        //! sets the Earth's current position; see _track_bodies() for taking it from the simulated bodies.
+        self.earth = Some(earth);
+        self
+    }
+
+    pub fn _track_bodies(&mut self, bodies: &[Body]) {
+        //! updates where the rocket sees the bodies as being, and which is now nearest; called each time the
+        //! simulation steps.
+        self.earth = _earth_in(bodies);
+        if let Some(nearest) = _nearest_in(bodies, &self.position) {
+            self.nearest_body = nearest;
+        }
+        self.tracked_bodies = 0;
+        for (tracked, body) in self.bodies.iter_mut().zip(bodies) {
+            tracked.clone_from(body);
+            self.tracked_bodies = self.tracked_bodies.saturating_add(1); // (MR B.3) at most N_BODIES.
+        }
+    }
+
+    pub fn _add_acceleration(&mut self, change: &StepVec3D) -> Result<(), ArithmeticError> {
+        //! adds to the non-gravitational acceleration (reference frame). Each source of thrust adds its own share, and
+        //! takes it back out as that changes, so sources firing together add up.
+        self.non_gravitational_acceleration =
+            self.non_gravitational_acceleration.checked_add(change)?;
+        Ok(())
+    }
+
+    pub fn _with_inertia(mut self, inertia: StepVec3D) -> Self {
+        //! sets the principal moments of inertia about the body axes.
+        self.inertia = inertia;
+        self
+    }
+
+    pub fn _turn(
+        &mut self,
+        impulse: &StepVec3D,
+        stored: &StepVec3D,
+        elapsed: SimTime,
+    ) -> Result<(), QuaternionError> {
+        //! rigid-body attitude dynamics: applies a body-frame angular impulse spread over elapsed, along with the
+        //! gyroscopic coupling of Euler's equations, then turns the rocket at its new rate for elapsed. The momentum
+        //! stored in spinning parts such as the reaction wheels, in the body frame, turns with the body and couples
+        //! in too: the coupling is w x (Iw + h).
+        let dt: StepFp = elapsed.as_fp().convert()?;
+        let momentum = _per_axis(&self.inertia, &self.angular_velocity, StepFp::checked_mul)?
+            .checked_add(stored)?;
+        let coupling = self.angular_velocity.cross(&momentum)?.checked_scale(dt)?;
+        let change = impulse.checked_sub(&coupling)?;
+        // (MR B.4) checked_div errors on a zero moment of inertia rather than dividing by it.
+        let spin_up = _per_axis(&change, &self.inertia, StepFp::checked_div)?;
+        self.angular_velocity = self.angular_velocity.checked_add(&spin_up)?;
+        let Ok(axis) = self.angular_velocity.to_unit_vector() else {
+            return Ok(()); // not rotating.
+        };
+        let angle: UnitFp = self
+            .angular_velocity
+            .checked_magnitude()?
+            .checked_mul(dt)?
+            .convert()?;
+        let turn = Quaternion::from_axis_angle(axis, angle)?;
+        self.orientation = self.orientation.mult(&turn).normalised()?;
+        Ok(())
+    }
+
+    pub fn _true_position(&self) -> SolarVec3D {
+        //! where the rocket actually is; e.g. for the altimeter to measure against.
+        self.position
+    }
+
+    pub fn _true_velocity(&self) -> StepVec3D {
+        self.velocity
+    }
+
+    pub fn _true_orientation(&self) -> Quaternion {
+        self.orientation
+    }
+
+    pub fn _true_nearest_body(&self) -> SolarVec3D {
+        //! the centre of the nearest body; known to the ground from its ephemeris, rather than sensed.
+        self.nearest_body
+    }
+
+    pub fn _true_distance_to_earth(&self) -> Option<SolarFp> {
+        //! straight-line distance from the rocket to the centre of the Earth; what a signal has to cross. None if the
+        //! Earth isn't known, or the distance is too great to represent.
+        let earth = self.earth?;
+        self.position.vector_to(&earth).checked_magnitude().ok()
+    }
+
+    pub fn _true_distance_to_nearest_body(&self) -> Option<SolarFp> {
+        //! straight-line distance from the rocket to the centre of the nearest body. None if it's too great to
+        //! represent.
+        self.nearest_body
+            .checked_sub(&self.position)
+            .and_then(|offset| offset.checked_magnitude())
+            .ok()
+    }
+
+    pub fn _true_specific_force(&self) -> Result<StepVec3D, ArithmeticError> {
+        //! the non-gravitational acceleration as felt in the body frame; what a perfect accelerometer would read.
+        self.orientation
+            .conjugated()
+            .rotate(&self.non_gravitational_acceleration)
+    }
+
+    pub fn _true_angular_rate(&self) -> StepVec3D {
+        //! the body-frame rotation rate; what a perfect gyroscope would read.
+        self.angular_velocity
+    }
+
+    pub fn _visible_stars(&self) -> Result<usize, ArithmeticError> {
+        //! how many catalogued stars can be seen from the rocket, past the bodies; none from inside one.
+        let bodies = self
+            .bodies
+            .get(..self.tracked_bodies)
+            .unwrap_or(&self.bodies);
+        Ok(visible_from(&self.position, bodies)?.len())
+    }
+}
+
+pub fn _step_simulation(
+    system: &mut System,
+    truth: &watch::Sender<_Rocket>,
+    time: SolarFp,
+) -> Result<(), SimulationError> {
+    //! advances the solar system by time, then brings the rocket's view of the bodies up to date with it.
+    system.advance_time_multistep(time, None)?;
+    truth.send_modify(|rocket| rocket._track_bodies(&system.bodies));
+    Ok(())
+}
+
+fn _earth_in(bodies: &[Body]) -> Option<SolarVec3D> {
+    //! the Earth's position among the simulated bodies, as in System.bodies.
+    bodies
+        .iter()
+        .find(|body| body.name == "Earth")
+        .map(|body| body.position)
+}
+
+fn _nearest_in(bodies: &[Body], position: &SolarVec3D) -> Option<SolarVec3D> {
+    //! the centre of the body whose surface is closest to position. Bodies too far off to measure are passed over.
+    bodies
+        .iter()
+        .filter_map(|body| {
+            let distance = body
+                .position
+                .checked_sub(position)
+                .and_then(|offset| offset.checked_magnitude())
+                .ok()?;
+            Some((distance.saturating_sub(body.radius), body.position)) // (MR B.3) inside a body is nearest of all.
+        })
+        .min_by_key(|(altitude, _)| *altitude)
+        .map(|(_, centre)| centre)
+}
+
+fn _per_axis(
+    a: &StepVec3D,
+    b: &StepVec3D,
+    operation: fn(StepFp, StepFp) -> Result<StepFp, ArithmeticError>,
+) -> Result<StepVec3D, ArithmeticError> {
+    //! combines two vectors component by component; e.g. a diagonal inertia tensor with a rate.
+    Ok(Vec3D(
+        operation(a.0, b.0)?,
+        operation(a.1, b.1)?,
+        operation(a.2, b.2)?,
+    ))
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::float_arithmetic)] // this is test code.
+mod tests {
+    use super::*;
+    use agc_utils::{EulerAngles, UnitFp};
+
+    #[test]
+    fn truth_passes_through() {
+        let position = SolarVec3D::from_floats(1e9, 0.0, 0.0).unwrap();
+        let velocity = StepVec3D::from_floats(0.0, 3e4, 0.0).unwrap();
+        let rocket = _Rocket::_new(position, velocity, Quaternion::IDENTITY);
+        assert_eq!(rocket._true_position(), position);
+        assert_eq!(rocket._true_velocity(), velocity);
+        assert_eq!(rocket._true_angular_rate(), StepVec3D::new());
+        assert_eq!(rocket._true_specific_force().unwrap(), StepVec3D::new());
+    }
+
+    #[test]
+    fn torque_spins_up_and_turns() {
+        let mut rocket = _Rocket::_new(SolarVec3D::new(), StepVec3D::new(), Quaternion::IDENTITY);
+        // 1N m about z for 100s, in 1s steps, on 30,000kg m^2: 1/300 rad/s, having turned 1/6 rad.
+        let impulse = StepVec3D::from_floats(0.0, 0.0, 1.0).unwrap();
+        for _ in 0..100 {
+            rocket
+                ._turn(&impulse, &StepVec3D::new(), SimTime::from_secs(1))
+                .unwrap();
+        }
+        let rate = rocket._true_angular_rate();
+        assert!((rate.2.to_f64() - 1.0 / 300.0).abs() < 1e-9, "{rate:?}");
+        assert!(rate.0.to_f64().abs() < 1e-12 && rate.1.to_f64().abs() < 1e-12);
+        let turned = rocket
+            ._true_orientation()
+            .angle_to(&Quaternion::IDENTITY)
+            .to_f64();
+        assert!((turned - 1.0 / 6.0).abs() < 2e-3, "{turned}");
+    }
+
+    #[test]
+    fn gyroscopic_coupling_and_bad_inertia() {
+        // spinning about a principal axis is steady; tilted off one, the rate precesses.
+        let spin = StepVec3D::from_floats(0.1, 0.0, 0.0).unwrap();
+        let mut steady = _Rocket::_new(SolarVec3D::new(), StepVec3D::new(), Quaternion::IDENTITY)
+            ._with_motion(spin, StepVec3D::new());
+        steady
+            ._turn(&StepVec3D::new(), &StepVec3D::new(), SimTime::from_secs(1))
+            .unwrap();
+        assert_eq!(steady._true_angular_rate(), spin);
+        let tilted = StepVec3D::from_floats(0.1, 0.1, 0.0).unwrap();
+        let mut wobbling = steady.clone()._with_motion(tilted, StepVec3D::new());
+        wobbling
+            ._turn(&StepVec3D::new(), &StepVec3D::new(), SimTime::from_secs(1))
+            .unwrap();
+        assert!(wobbling._true_angular_rate().2 != StepFp::from_int(0));
+        // stored momentum turns with the body: spinning about x, with the wheels' along z, the rate tips towards y.
+        let stored = StepVec3D::from_floats(0.0, 0.0, -300.0).unwrap();
+        let mut carrying = steady.clone();
+        carrying
+            ._turn(&StepVec3D::new(), &stored, SimTime::from_secs(1))
+            .unwrap();
+        let rate = carrying._true_angular_rate();
+        // w x h = (0.1, 0, 0) x (0, 0, -300) = (0, 30, 0), taken away.
+        let expected = -30.0 / _DEFAULT_INERTIA.1.to_f64();
+        assert!((rate.1.to_f64() - expected).abs() < 1e-9, "{rate:?}");
+        assert!(rate.2.to_f64().abs() < 1e-12);
+        let mut massless = steady._with_inertia(StepVec3D::new());
+        assert!(massless
+            ._turn(&spin, &StepVec3D::new(), SimTime::from_secs(1))
+            .is_err());
+    }
+
+    #[test]
+    fn specific_force_is_in_body_frame() {
+        // yawed a quarter turn left, so thrust along reference +x is felt along body -y.
+        let orientation = Quaternion::from_euler(EulerAngles {
+            roll: UnitFp::from_int(0),
+            pitch: UnitFp::from_int(0),
+            yaw: UnitFp::FRAC_PI_2,
+        });
+        let rocket = _Rocket::_new(SolarVec3D::new(), StepVec3D::new(), orientation)._with_motion(
+            StepVec3D::new(),
+            StepVec3D::from_floats(2.0, 0.0, 0.0).unwrap(),
+        );
+        let felt = rocket._true_specific_force().unwrap();
+        let expected = StepVec3D::from_floats(0.0, -2.0, 0.0).unwrap();
+        assert!(felt.vector_to(&expected).magnitude() < agc_utils::StepFp::from_f64_trusted(1e-9));
+    }
+
+    #[test]
+    fn earth_follows_the_simulation() {
+        let mut system = System::create();
+        let epoch = _earth_in(&system.bodies).unwrap();
+        assert!(epoch.magnitude().to_f64() > 1.4e11 && epoch.magnitude().to_f64() < 1.6e11);
+        let rocket = _Rocket::_new(epoch, StepVec3D::new(), Quaternion::IDENTITY);
+        assert_eq!(rocket._true_distance_to_earth(), Some(SolarFp::from_int(0)));
+        let (truth, rocket) = watch::channel(rocket);
+
+        // a day on, the Earth has moved ~2.6 million km along its orbit, and the rocket knows it.
+        _step_simulation(&mut system, &truth, SolarFp::from_int(86_400)).unwrap();
+        let distance = rocket.borrow()._true_distance_to_earth().unwrap().to_f64();
+        assert!((2.4e9..2.8e9).contains(&distance), "{distance}");
+        let earth = _earth_in(&system.bodies).unwrap();
+        assert_eq!(
+            rocket.borrow()._true_distance_to_earth(),
+            Some(epoch.vector_to(&earth).magnitude())
+        );
+
+        // without an Earth among the bodies, there's no distance to it rather than one to the Sun.
+        truth.send_modify(|rocket| rocket._track_bodies(&system.bodies[..3])); // Sol, Mercury and Venus only.
+        assert_eq!(rocket.borrow()._true_distance_to_earth(), None);
+    }
+
+    #[test]
+    fn nearest_body_follows_the_simulation() {
+        let mut system = System::create();
+        let earth = _earth_in(&system.bodies).unwrap();
+        // 1000km up, above the Earth at epoch.
+        let altitude = SolarVec3D::from_floats(7.371e6, 0.0, 0.0).unwrap();
+        let position = earth.checked_add(&altitude).unwrap();
+        let rocket = _Rocket::_new(position, StepVec3D::new(), Quaternion::IDENTITY);
+        assert_eq!(rocket._true_nearest_body(), earth);
+        let (truth, rocket) = watch::channel(rocket);
+
+        // an hour on, the Earth has moved ~100,000km, still far nearer than the Moon or anything else.
+        _step_simulation(&mut system, &truth, SolarFp::from_int(3_600)).unwrap();
+        let moved = _earth_in(&system.bodies).unwrap();
+        assert_ne!(moved, earth);
+        assert_eq!(rocket.borrow()._true_nearest_body(), moved);
+
+        // a rocket far out of the plane of the planets ranges against the Sun.
+        let mut far = _Rocket::_new(
+            SolarVec3D::from_floats(0.0, 0.0, 1e12).unwrap(),
+            StepVec3D::new(),
+            Quaternion::IDENTITY,
+        );
+        far._track_bodies(&system.bodies);
+        let sun = system.bodies.first().unwrap().position;
+        assert_eq!(far._true_nearest_body(), sun);
+    }
 }
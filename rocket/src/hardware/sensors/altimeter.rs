@@ -1,70 +1,166 @@
 //! contains the struct definition for the Altimeter. This sensor works between 40km and gives the distance to the surface.
 
-use std::time::Instant;
-
-use rand::Rng;
+use rand::{rngs::StdRng, Rng, SeedableRng};
 use tokio::sync::watch;
 
-use agc_physics::planets::Body;
-use agc_utils::{SolarFp, SolarVec3D, UnitFp};
-//use agc_utils::Vec3D;
+use agc_utils::{SimClockReader, SimTime, SolarFp, UnitFp};
 
 use super::{
-    _SensorReading,
+    _Sensor, _SensorReading,
     _SensorState::{self, *},
+    _random_within,
 };
+use crate::hardware::rocket::_Rocket;
 
 const _ALTIMETER_DRIFT_BOUNDS: UnitFp = UnitFp::from_f64_trusted(0.000375); // m/s; want ~10-100m/year so must be tiny.
+const _ALTIMETER_VARIANCE_BOUNDS: UnitFp = UnitFp::from_f64_trusted(0.001); // fraction of true distance; 0.1%.
+const _ALTIMETER_MAX_RANGE: SolarFp = SolarFp::from_f64_trusted(40_000.0); // m.
+const _ALTIMETER_POLLING_PERIOD: SimTime = SimTime::from_secs(1);
 
 pub struct _AltimeterData {
     state: _SensorState,
     variance: UnitFp,                      // Operational deviation from true values.
     last_reading: _SensorReading<SolarFp>, // last reading collected by the device.
     drift: SolarFp,                        // constantly growing deviation from real values
-    drift_rate: SolarFp, // rate at which drift increases (per second). Randomised between +/-ALTIMETER_DRIFT_BOUNDS on startup/reboot
-    polling_delay: f64,
+    drift_rate: UnitFp, // rate at which drift increases (per second). Randomised between +/-ALTIMETER_DRIFT_BOUNDS on startup/reboot
+    drift_epoch: SimTime, // sim time at which drift was last reset (startup/reboot).
+    clock: SimClockReader, // shared handle on simulation time; used to stamp readings and grow drift.
+    rng: StdRng,           // seeded so that runs are reproducible.
+    polling_period: SimTime,
     max_range: SolarFp,
-    send_channel: watch::Sender<_SensorReading<f64>>, //
+    send_channel: watch::Sender<_SensorReading<SolarFp>>,
 }
 
 impl _AltimeterData {
-    pub fn _poll(&mut self, location: SolarVec3D, target: &Body) {
+    pub fn _new(
+        clock: SimClockReader,
+        seed: u64,
+    ) -> (Self, watch::Receiver<_SensorReading<SolarFp>>) {
+        //! powers up an altimeter with a randomised variance and drift rate. Returns the receiving end of its output channel.
+        let mut rng = StdRng::seed_from_u64(seed);
+        let now = clock.now();
+        let last_reading = _SensorReading {
+            data: SolarFp::from_int(0),
+            time: now,
+        };
+        let (send_channel, receiver) = watch::channel(last_reading);
+        let altimeter = Self {
+            state: Operational,
+            variance: _random_within(&mut rng, _ALTIMETER_VARIANCE_BOUNDS),
+            last_reading,
+            drift: SolarFp::from_int(0),
+            drift_rate: _random_within(&mut rng, _ALTIMETER_DRIFT_BOUNDS),
+            drift_epoch: now,
+            clock,
+            rng,
+            polling_period: _ALTIMETER_POLLING_PERIOD,
+            max_range: _ALTIMETER_MAX_RANGE,
+            send_channel,
+        };
+        (altimeter, receiver)
+    }
+}
+
+fn _no_return(now: SimTime) -> _SensorReading<SolarFp> {
+    //! beyond range there's no echo to time. The altimeter still reports, so it's seen to be alive, but with a
+    //! saturated distance that the controller rejects as out of range.
+    _SensorReading {
+        data: SolarFp::with_internal(i64::MAX),
+        time: now,
+    }
+}
+
+impl _Sensor for _AltimeterData {
+    type Output = SolarFp;
+
+    fn _poll(&mut self, rocket: &_Rocket) {
         //! internal polling of data. Error type is just log/debug str as within the scope of the program, sensors need to fail silently.
         //! note that this does not send any data anywhere, it just updates the internally held value.
+        let now = self.clock.now();
+        self._update_drift(now);
         match self.state {
             Operational => {
-                let true_distance = location.vector_to(&target.position).magnitude();
-                if true_distance < self.max_range {
+                let in_range = rocket
+                    ._true_distance_to_nearest_body()
+                    .filter(|distance| *distance < self.max_range);
+                if let Some(true_distance) = in_range {
                     self.last_reading = _SensorReading {
-
-                        #[allow(clippy::arithmetic_side_effects)] // it's complaining about the addition. We know for a fact that variance is in bounds.
-                        data: (UnitFp::from_int(1) + self.variance).scale_by_other(true_distance)
-                            + self.drift,
-                        time: Instant::now(), // TODO create wrapper type for Simulation time.
+                        data: UnitFp::from_int(1)
+                            .saturating_add(self.variance) // (MR B.3) variance is a small fraction; can't approach the UnitFp limit of 8.
+                            .scale_by_other(true_distance)
+                            .saturating_add(self.drift), // (MR B.3) a saturated reading is out of range and will be rejected by the controller.
+                        time: now,
                     }
+                } else {
+                    self.last_reading = _no_return(now);
                 }
             }
             Variant => {
-                let true_distance = location.vector_to(&target.position).magnitude();
-                if true_distance < self.max_range {
+                let in_range = rocket
+                    ._true_distance_to_nearest_body()
+                    .filter(|distance| *distance < self.max_range);
+                if let Some(true_distance) = in_range {
                     self.last_reading = _SensorReading {
-
-                        #[allow(clippy::arithmetic_side_effects)] // it's complaining about the addition. We know for a fact that variance is in bounds.
-                        data: (UnitFp::from_int(1) + self.variance * UnitFp::from_int(5))
-                            .scale_by_other(true_distance) + self.drift,
-                        time: Instant::now(), // TODO create wrapper type for Simulation time.
+                        data: UnitFp::from_int(1)
+                            .saturating_add(self.variance.saturating_mul(UnitFp::from_int(5))) // (MR B.3) 5x a small fraction stays well inside the UnitFp limit of 8.
+                            .scale_by_other(true_distance)
+                            .saturating_add(self.drift), // (MR B.3) a saturated reading is out of range and will be rejected by the controller.
+                        time: now,
                     }
+                } else {
+                    self.last_reading = _no_return(now);
                 }
             }
             Garbage => {
                 self.last_reading = _SensorReading {
-                    data: SolarFp::with_internal(rand::thread_rng().gen()), // garbage data
-                    time: Instant::now(),
+                    data: SolarFp::with_internal(self.rng.gen()), // garbage data
+                    time: now,
                 }
             }
             Frozen(_) | Rebooting(_) => {
-                unreachable!("The altimeter will never poll itself while frozen or rebooting.")
+                // hung or restarting: no new reading is taken, so the held one goes stale.
             }
         }
     }
+
+    fn _update_drift(&mut self, now: SimTime) {
+        //! recomputes the accumulated drift from the time elapsed since the drift epoch.
+        //! Drift is computed from total elapsed time rather than summed per poll, so that frequent polling doesn't lose
+        //! sub-precision increments to truncation.
+        let elapsed = now.elapsed_since(self.drift_epoch).as_fp();
+        self.drift = self.drift_rate.scale_by_other(elapsed).as_solar_fp();
+    }
+
+    fn _state(&self) -> _SensorState {
+        self.state
+    }
+
+    fn _set_state(&mut self, state: _SensorState) {
+        self.state = state;
+    }
+
+    fn _polling_period(&self) -> SimTime {
+        self.polling_period
+    }
+
+    fn _reboot(&mut self) {
+        //! the drift rate is a power-up property, so it is re-drawn and drift starts again from zero.
+        self.drift_rate = _random_within(&mut self.rng, _ALTIMETER_DRIFT_BOUNDS);
+        self.drift_epoch = self.clock.now();
+        self.drift = SolarFp::from_int(0);
+    }
+
+    fn _recalibrate(&mut self) {
+        //! drift is measured from its epoch, so moving the epoch to now zeroes it.
+        self.drift_epoch = self.clock.now();
+        self.drift = SolarFp::from_int(0);
+    }
+
+    fn _last_reading(&self) -> _SensorReading<SolarFp> {
+        self.last_reading
+    }
+
+    fn _output(&self) -> &watch::Sender<_SensorReading<SolarFp>> {
+        &self.send_channel
+    }
 }
@@ -0,0 +1,92 @@
+//! contains the commands the flight controller can send to a sensor thread, and the bounded channel that carries them.
+//!
+//! (MR G.3) Deadlock freedom. Each sensor thread has exactly two links with the flight controller:
+//!   1. controller -> sensor: this command channel. The controller only ever uses try_send(), which returns at once
+//!      with _CommandError::Full if the sensor hasn't drained its queue; it never waits for the sensor.
+//!   2. sensor -> controller: the sensor's watch channel. send_replace() overwrites the held value and never waits.
+//!
+//! The sensor thread drains commands with try_recv(), which also never waits. As neither side ever blocks on the
+//! other, no cycle of waits can form, so deadlock is impossible; the only waits anywhere are the harness's own
+//! bounded sleeps and shutdown join (MR G.2). A hung (Frozen) sensor still has its commands drained, as they are
+//! handled by the thread harness - the sensor's power/reset line - rather than by the sensor itself.
+
+use std::fmt::Display;
+
+use tokio::sync::mpsc::{self, error::TrySendError};
+
+// capacity of each command queue. The controller sends at most a handful of commands per sensor per poll period and
+// the sensor thread drains the whole queue every loop, so a full queue means the thread itself has stopped.
+pub(crate) const _COMMAND_CAPACITY: usize = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum _SensorCommand {
+    Reboot,      // restart the sensor; clears any hang, and re-randomises its power-up errors.
+    Recalibrate, // zero accumulated drift against a known reference. Ignored by hung or rebooting sensors.
+    PowerOff,    // stop polling and publishing entirely.
+    PowerOn,     // power back up; the sensor goes through a full reboot first.
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum _CommandError {
+    Full { command: _SensorCommand }, // the sensor's queue is full; it isn't draining, so is likely dead.
+    Disconnected { command: _SensorCommand }, // the sensor thread has exited.
+}
+
+impl Display for _CommandError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            _CommandError::Full { command } => {
+                write!(f, "Command {command:?} dropped: sensor queue full")
+            }
+            _CommandError::Disconnected { command } => {
+                write!(f, "Command {command:?} dropped: sensor thread has exited")
+            }
+        }
+    }
+}
+
+pub(crate) fn _command_channel() -> (mpsc::Sender<_SensorCommand>, mpsc::Receiver<_SensorCommand>) {
+    mpsc::channel(_COMMAND_CAPACITY)
+}
+
+pub(crate) fn _send(
+    commands: &mpsc::Sender<_SensorCommand>,
+    command: _SensorCommand,
+) -> Result<(), _CommandError> {
+    //! queues a command without waiting (see the MR G.3 note at the top of this file).
+    commands.try_send(command).map_err(|error| match error {
+        TrySendError::Full(command) => _CommandError::Full { command },
+        TrySendError::Closed(command) => _CommandError::Disconnected { command },
+    })
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)] // this is test code.
+mod tests {
+    use super::*;
+    use _SensorCommand::*;
+
+    #[test]
+    fn full_queue_returns_immediately() {
+        let (sender, _receiver) = _command_channel();
+        for _ in 0.._COMMAND_CAPACITY {
+            _send(&sender, Reboot).unwrap();
+        }
+        assert_eq!(
+            _send(&sender, PowerOff),
+            Err(_CommandError::Full { command: PowerOff })
+        );
+    }
+
+    #[test]
+    fn exited_sensor_is_reported() {
+        let (sender, receiver) = _command_channel();
+        drop(receiver);
+        assert_eq!(
+            _send(&sender, Recalibrate),
+            Err(_CommandError::Disconnected {
+                command: Recalibrate
+            })
+        );
+    }
+}
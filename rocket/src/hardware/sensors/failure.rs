@@ -0,0 +1,350 @@
+//! contains the failure model which moves a sensor between its _SensorState's. Each simulated tick a healthy sensor
+//! may hang, start throwing garbage, or enter a high-variance episode; each of these ends by itself after a time
+//! (or number of ticks) set by _FailureRates. Reboots are requested by the FlightController and complete after a
+//! fixed duration, at which point the sensor re-randomises its power-up errors.
+
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+use agc_utils::{SimTime, UnitFp};
+
+use super::{_Sensor, _SensorState::*};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct _FailureRates {
+    /// How often, and for how long, a sensor misbehaves. Probabilities are per tick, and are checked in the order
+    /// hang, garbage, variance; if they sum past 1 the later ones are starved.
+    pub(crate) hang_probability: UnitFp,
+    pub(crate) hang_duration: SimTime, // a hung sensor recovers by itself after this long, unless rebooted first.
+    pub(crate) garbage_probability: UnitFp,
+    pub(crate) max_garbage_burst: u32, // ticks; each burst lasts uniformly between 1 and this.
+    pub(crate) variance_probability: UnitFp,
+    pub(crate) variance_duration: SimTime,
+    pub(crate) reboot_duration: SimTime,
+}
+
+impl _FailureRates {
+    pub(crate) const _DEFAULT: Self = Self {
+        hang_probability: UnitFp::from_f64_trusted(1e-6),
+        hang_duration: SimTime::from_secs(30),
+        garbage_probability: UnitFp::from_f64_trusted(1e-5),
+        max_garbage_burst: 20,
+        variance_probability: UnitFp::from_f64_trusted(1e-4),
+        variance_duration: SimTime::from_secs(60),
+        reboot_duration: SimTime::from_secs(10),
+    };
+
+    pub(crate) const _NEVER_FAIL: Self = Self {
+        hang_probability: UnitFp::from_f64_trusted(0.0),
+        garbage_probability: UnitFp::from_f64_trusted(0.0),
+        variance_probability: UnitFp::from_f64_trusted(0.0),
+        ..Self::_DEFAULT
+    };
+}
+
+#[derive(Clone)]
+pub(crate) struct _FailureModel {
+    rates: _FailureRates,
+    rng: StdRng,            // seeded so that runs are reproducible.
+    variance_end: SimTime,  // when the current Variant episode (if any) finishes.
+    garbage_remaining: u32, // ticks left in the current Garbage burst (if any).
+}
+
+impl _FailureModel {
+    pub(crate) fn _new(rates: _FailureRates, seed: u64) -> Self {
+        Self {
+            rates,
+            rng: StdRng::seed_from_u64(seed),
+            variance_end: SimTime::ZERO,
+            garbage_remaining: 0,
+        }
+    }
+
+    pub(crate) fn _tick(&mut self, sensor: &mut impl _Sensor, now: SimTime) {
+        //! advances the sensor's state by one tick. Call once per poll, before polling.
+        match sensor._state() {
+            Operational => self._roll_failure(sensor, now),
+            Variant => {
+                if now.has_reached(self.variance_end) {
+                    sensor._set_state(Operational);
+                } else {
+                    self._roll_failure(sensor, now);
+                }
+            }
+            Garbage => {
+                self.garbage_remaining = self.garbage_remaining.saturating_sub(1); // (MR B.3) floors at 0, which ends the burst.
+                if self.garbage_remaining == 0 {
+                    sensor._set_state(Operational);
+                }
+            }
+            Frozen(deadline) => {
+                if now.has_reached(deadline) {
+                    sensor._set_state(Operational);
+                }
+            }
+            Rebooting(deadline) => {
+                if now.has_reached(deadline) {
+                    sensor._reboot();
+                    sensor._set_state(Operational);
+                }
+            }
+        }
+    }
+
+    pub(crate) fn _begin_reboot(&mut self, sensor: &mut impl _Sensor, now: SimTime) {
+        //! starts a reboot, whatever state the sensor is in. It comes back Operational after the reboot duration.
+        sensor._set_state(Rebooting(now.saturating_add(self.rates.reboot_duration)));
+    }
+
+    fn _roll_failure(&mut self, sensor: &mut impl _Sensor, now: SimTime) {
+        //! a single roll decides which, if any, failure starts this tick.
+        let roll = self._roll();
+        let hang = self.rates.hang_probability;
+        let garbage = hang.saturating_add(self.rates.garbage_probability);
+        let variance = garbage.saturating_add(self.rates.variance_probability);
+        if roll < hang {
+            sensor._set_state(Frozen(now.saturating_add(self.rates.hang_duration)));
+        } else if roll < garbage {
+            self.garbage_remaining = self.rng.gen_range(1..=self.rates.max_garbage_burst.max(1));
+            sensor._set_state(Garbage);
+        } else if roll < variance && sensor._state() == Operational {
+            self.variance_end = now.saturating_add(self.rates.variance_duration);
+            sensor._set_state(Variant);
+        }
+    }
+
+    fn _roll(&mut self) -> UnitFp {
+        //! uniformly distributed in [0, 1).
+        UnitFp::with_internal(self.rng.gen_range(0..UnitFp::from_int(1).internal()))
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::arithmetic_side_effects)] // this is test code.
+mod tests {
+    use super::*;
+    use crate::hardware::rocket::_Rocket;
+    use crate::hardware::sensors::{_SensorReading, _SensorState};
+    use tokio::sync::watch;
+
+    const SEED: u64 = 1202;
+    const ALWAYS: UnitFp = UnitFp::from_f64_trusted(1.0);
+
+    struct TestSensor {
+        state: _SensorState,
+        reboots: u32,
+        output: watch::Sender<_SensorReading<u8>>,
+    }
+
+    impl TestSensor {
+        fn new() -> Self {
+            Self {
+                state: Operational,
+                reboots: 0,
+                output: watch::channel(_SensorReading {
+                    data: 0,
+                    time: SimTime::ZERO,
+                })
+                .0,
+            }
+        }
+    }
+
+    impl _Sensor for TestSensor {
+        type Output = u8;
+        fn _poll(&mut self, _rocket: &_Rocket) {}
+        fn _state(&self) -> _SensorState {
+            self.state
+        }
+        fn _set_state(&mut self, state: _SensorState) {
+            self.state = state;
+        }
+        fn _polling_period(&self) -> SimTime {
+            SimTime::from_secs(1)
+        }
+        fn _update_drift(&mut self, _now: SimTime) {}
+        fn _reboot(&mut self) {
+            self.reboots += 1;
+        }
+        fn _recalibrate(&mut self) {}
+        fn _last_reading(&self) -> _SensorReading<u8> {
+            *self.output.borrow()
+        }
+        fn _output(&self) -> &watch::Sender<_SensorReading<u8>> {
+            &self.output
+        }
+    }
+
+    fn secs(secs: i64) -> SimTime {
+        SimTime::from_secs(secs)
+    }
+
+    #[test]
+    fn never_fail_stays_operational() {
+        let mut model = _FailureModel::_new(_FailureRates::_NEVER_FAIL, SEED);
+        let mut sensor = TestSensor::new();
+        for tick in 0..10_000 {
+            model._tick(&mut sensor, secs(tick));
+            assert_eq!(sensor.state, Operational);
+        }
+    }
+
+    #[test]
+    fn hang_recovers_at_deadline() {
+        let rates = _FailureRates {
+            hang_probability: ALWAYS,
+            .._FailureRates::_NEVER_FAIL
+        };
+        let mut model = _FailureModel::_new(rates, SEED);
+        let mut sensor = TestSensor::new();
+        model._tick(&mut sensor, secs(0));
+        assert_eq!(sensor.state, Frozen(secs(30)));
+        model._tick(&mut sensor, secs(29));
+        assert_eq!(sensor.state, Frozen(secs(30)));
+        model._tick(&mut sensor, secs(30));
+        assert_eq!(sensor.state, Operational);
+        assert_eq!(sensor.reboots, 0);
+    }
+
+    #[test]
+    fn garbage_burst_is_bounded() {
+        let rates = _FailureRates {
+            garbage_probability: ALWAYS,
+            max_garbage_burst: 5,
+            .._FailureRates::_NEVER_FAIL
+        };
+        let mut model = _FailureModel::_new(rates, SEED);
+        let mut sensor = TestSensor::new();
+        for burst in 0..20 {
+            model._tick(&mut sensor, secs(burst));
+            assert_eq!(sensor.state, Garbage);
+            let mut length = 0;
+            while sensor.state == Garbage {
+                length += 1;
+                model._tick(&mut sensor, secs(burst));
+            }
+            assert_eq!(sensor.state, Operational);
+            assert!((1..=5).contains(&length), "{length}");
+        }
+    }
+
+    #[test]
+    fn variance_episode_ends_after_duration() {
+        let rates = _FailureRates {
+            variance_probability: ALWAYS,
+            .._FailureRates::_NEVER_FAIL
+        };
+        let mut model = _FailureModel::_new(rates, SEED);
+        let mut sensor = TestSensor::new();
+        model._tick(&mut sensor, secs(0));
+        assert_eq!(sensor.state, Variant);
+        model._tick(&mut sensor, secs(59));
+        assert_eq!(sensor.state, Variant);
+        model._tick(&mut sensor, secs(60));
+        assert_eq!(sensor.state, Operational);
+    }
+
+    #[test]
+    fn variant_sensor_can_still_hang() {
+        let rates = _FailureRates {
+            hang_probability: ALWAYS,
+            .._FailureRates::_NEVER_FAIL
+        };
+        let mut model = _FailureModel::_new(rates, SEED);
+        let mut sensor = TestSensor::new();
+        model.variance_end = secs(60);
+        sensor.state = Variant;
+        model._tick(&mut sensor, secs(1));
+        assert_eq!(sensor.state, Frozen(secs(31)));
+    }
+
+    #[test]
+    fn variant_sensor_can_turn_to_garbage() {
+        let rates = _FailureRates {
+            garbage_probability: ALWAYS,
+            max_garbage_burst: 1,
+            .._FailureRates::_NEVER_FAIL
+        };
+        let mut model = _FailureModel::_new(rates, SEED);
+        let mut sensor = TestSensor::new();
+        model.variance_end = secs(60);
+        sensor.state = Variant;
+        model._tick(&mut sensor, secs(1));
+        assert_eq!(sensor.state, Garbage);
+        // the burst ends the variance episode too.
+        model._tick(&mut sensor, secs(2));
+        assert_eq!(sensor.state, Operational);
+    }
+
+    #[test]
+    fn reboot_rerandomises_and_recovers() {
+        let mut model = _FailureModel::_new(_FailureRates::_NEVER_FAIL, SEED);
+        let mut sensor = TestSensor::new();
+        sensor.state = Frozen(secs(1000));
+        model._begin_reboot(&mut sensor, secs(5));
+        assert_eq!(sensor.state, Rebooting(secs(15)));
+        model._tick(&mut sensor, secs(14));
+        assert_eq!((sensor.state, sensor.reboots), (Rebooting(secs(15)), 0));
+        model._tick(&mut sensor, secs(15));
+        assert_eq!((sensor.state, sensor.reboots), (Operational, 1));
+    }
+
+    #[test]
+    fn failed_states_do_not_roll_new_failures() {
+        let rates = _FailureRates {
+            hang_probability: ALWAYS,
+            .._FailureRates::_NEVER_FAIL
+        };
+        let mut model = _FailureModel::_new(rates, SEED);
+        let mut sensor = TestSensor::new();
+        sensor.state = Rebooting(secs(10));
+        model._tick(&mut sensor, secs(1));
+        assert_eq!(sensor.state, Rebooting(secs(10)));
+        model.garbage_remaining = 3;
+        sensor.state = Garbage;
+        model._tick(&mut sensor, secs(1));
+        assert_eq!(sensor.state, Garbage);
+    }
+
+    #[test]
+    fn probabilities_are_respected() {
+        let rates = _FailureRates {
+            hang_probability: UnitFp::from_f64_trusted(0.1),
+            garbage_probability: UnitFp::from_f64_trusted(0.2),
+            .._FailureRates::_DEFAULT
+        };
+        let mut model = _FailureModel::_new(rates, SEED);
+        let (mut hangs, mut garbage) = (0, 0);
+        for _ in 0..10_000 {
+            let mut sensor = TestSensor::new();
+            model._tick(&mut sensor, secs(0));
+            match sensor.state {
+                Frozen(_) => hangs += 1,
+                Garbage => garbage += 1,
+                _ => {}
+            }
+        }
+        assert!((900..1100).contains(&hangs), "{hangs}");
+        assert!((1850..2150).contains(&garbage), "{garbage}");
+    }
+
+    #[test]
+    fn seeded_models_are_reproducible() {
+        let rates = _FailureRates {
+            hang_probability: UnitFp::from_f64_trusted(0.01),
+            garbage_probability: UnitFp::from_f64_trusted(0.05),
+            variance_probability: UnitFp::from_f64_trusted(0.05),
+            .._FailureRates::_DEFAULT
+        };
+        let history = || {
+            let mut model = _FailureModel::_new(rates, SEED);
+            let mut sensor = TestSensor::new();
+            (0..1000)
+                .map(|tick| {
+                    model._tick(&mut sensor, secs(tick));
+                    sensor.state
+                })
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(history(), history());
+    }
+}
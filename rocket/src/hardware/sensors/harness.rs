@@ -0,0 +1,460 @@
+//! contains the thread harness which runs each sensor on its own thread, as constraint 1 and MR G.1 require.
+//! Each thread watches the rocket's ground truth and the sim clock; whenever its poll scheduler says it is due, it
+//! advances its failure model, polls, and publishes. Threads exit when told to shut down, or when the ground truth
+//! channel closes (i.e. the simulation has ended), and every wait on either side is bounded per MR G.2.
+
+use std::fmt::Display;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use tokio::sync::{
+    mpsc::{self, error::TryRecvError},
+    watch,
+};
+
+use agc_utils::{SimClockReader, SimTime};
+
+use super::{
+    _Sensor,
+    _SensorState::*,
+    command::{_SensorCommand, _COMMAND_CAPACITY},
+    failure::_FailureModel,
+    scheduler::_PollScheduler,
+};
+use crate::hardware::rocket::_Rocket;
+
+// (MR G.2) how long an idle sensor thread sleeps before rechecking the sim clock. The shortest sensor period is 0.1s of
+// sim time, so 1ms of real time keeps polls prompt at up to ~100x real time, without busy-waiting.
+const _IDLE_SLEEP: Duration = Duration::from_millis(1);
+
+// (MR G.2) how often a waiting shutdown rechecks its threads. Matches _IDLE_SLEEP, since that's the longest a healthy
+// thread can go without noticing the shutdown signal.
+const _JOIN_POLL: Duration = Duration::from_millis(1);
+
+// (MR G.2) default bound on waiting for every sensor thread to exit. A healthy thread notices shutdown within one poll
+// plus one _IDLE_SLEEP (well under 10ms); a second is ample headroom on a loaded machine, and anything longer is hung.
+pub(crate) const _SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(1);
+
+#[derive(Debug)]
+pub(crate) enum _HarnessError {
+    Spawn {
+        sensor: &'static str,
+        error: std::io::Error,
+    }, // the OS refused to start the thread.
+    ShutdownTimeout {
+        sensor: &'static str,
+    }, // the thread didn't exit by the deadline; it has been detached.
+    Panicked {
+        sensor: &'static str,
+    }, // should be impossible under MR B.1, but is reported rather than propagated.
+}
+
+impl Display for _HarnessError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            _HarnessError::Spawn { sensor, error } => {
+                write!(f, "Could not spawn {sensor} thread: {error}")
+            }
+            _HarnessError::ShutdownTimeout { sensor } => {
+                write!(f, "{sensor} thread did not shut down in time")
+            }
+            _HarnessError::Panicked { sensor } => write!(f, "{sensor} thread panicked"),
+        }
+    }
+}
+
+pub(crate) struct _SensorLinks {
+    /// Everything a sensor thread needs from the rest of the rocket.
+    pub(crate) clock: SimClockReader,
+    pub(crate) truth: watch::Receiver<_Rocket>,
+    pub(crate) shutdown: watch::Receiver<bool>,
+    pub(crate) commands: mpsc::Receiver<_SensorCommand>, // see command.rs for the MR G.3 deadlock-freedom proof.
+}
+
+pub(crate) struct _SensorThread {
+    /// A running sensor thread. Size: 40B.
+    name: &'static str,
+    handle: JoinHandle<()>,
+}
+
+impl _SensorThread {
+    pub(crate) fn _spawn<S>(
+        name: &'static str,
+        sensor: S,
+        failures: _FailureModel,
+        scheduler: _PollScheduler,
+        links: _SensorLinks,
+    ) -> Result<Self, _HarnessError>
+    where
+        S: _Sensor + Send + 'static,
+    {
+        //! starts the sensor running on its own named thread.
+        let handle = thread::Builder::new()
+            .name(name.to_string())
+            .spawn(move || _run(sensor, failures, scheduler, links))
+            .map_err(|error| _HarnessError::Spawn {
+                sensor: name,
+                error,
+            })?;
+        Ok(Self { name, handle })
+    }
+
+    pub(crate) fn _join_by(self, deadline: Instant) -> Result<(), _HarnessError> {
+        //! waits for the thread to exit, giving up at the deadline. A thread that misses it is detached, not waited on.
+        // (MR D.3) bounded by the deadline; each pass sleeps _JOIN_POLL.
+        while !self.handle.is_finished() {
+            let now = Instant::now();
+            if now >= deadline {
+                return Err(_HarnessError::ShutdownTimeout { sensor: self.name });
+            }
+            thread::sleep(_JOIN_POLL.min(deadline.saturating_duration_since(now)));
+        }
+        self.handle
+            .join()
+            .map_err(|_| _HarnessError::Panicked { sensor: self.name })
+    }
+}
+
+fn _run<S: _Sensor>(
+    mut sensor: S,
+    mut failures: _FailureModel,
+    mut scheduler: _PollScheduler,
+    mut links: _SensorLinks,
+) {
+    //! the sensor thread's mainloop. Runs until shutdown is signalled or either watch channel's sender is dropped.
+    let mut powered = true;
+    while _should_run(&links) {
+        let now = links.clock.now();
+        powered = _handle_commands(
+            &mut sensor,
+            &mut failures,
+            &mut links.commands,
+            powered,
+            now,
+        );
+        if scheduler._is_due(now) {
+            if powered {
+                _poll_and_publish(&mut sensor, &mut failures, &mut links.truth, now);
+            }
+            scheduler._advance(); // a powered-off sensor still skips through its schedule, so it doesn't catch up later.
+        } else {
+            thread::sleep(_IDLE_SLEEP);
+        }
+    }
+}
+
+fn _should_run(links: &_SensorLinks) -> bool {
+    //! false once shutdown is requested, or the simulation (truth) or flight controller (shutdown) has gone away.
+    links.truth.has_changed().is_ok()
+        && links.shutdown.has_changed().is_ok()
+        && !*links.shutdown.borrow()
+}
+
+fn _poll_and_publish<S: _Sensor>(
+    sensor: &mut S,
+    failures: &mut _FailureModel,
+    truth: &mut watch::Receiver<_Rocket>,
+    now: SimTime,
+) {
+    //! a single scheduled poll: advance the failure model, poll, and publish any new reading.
+    failures._tick(sensor, now);
+    let previous = sensor._last_reading().time;
+    sensor._poll(&truth.borrow_and_update());
+    if sensor._last_reading().time != previous {
+        sensor._publish(); // hung sensors don't publish at all, rather than repeating a stale reading.
+    }
+}
+
+fn _handle_commands<S: _Sensor>(
+    sensor: &mut S,
+    failures: &mut _FailureModel,
+    commands: &mut mpsc::Receiver<_SensorCommand>,
+    powered: bool,
+    now: SimTime,
+) -> bool {
+    //! applies every queued command without waiting, returning whether the sensor is now powered.
+    let mut powered = powered;
+    // (MR D.3) bounded by the queue's capacity; anything sent meanwhile is picked up next loop.
+    for _ in 0.._COMMAND_CAPACITY {
+        match commands.try_recv() {
+            Ok(command) => powered = _apply_command(sensor, failures, command, powered, now),
+            Err(TryRecvError::Empty | TryRecvError::Disconnected) => break,
+        }
+    }
+    powered
+}
+
+fn _apply_command<S: _Sensor>(
+    sensor: &mut S,
+    failures: &mut _FailureModel,
+    command: _SensorCommand,
+    powered: bool,
+    now: SimTime,
+) -> bool {
+    //! applies a single command, returning whether the sensor is now powered.
+    match command {
+        _SensorCommand::Reboot => failures._begin_reboot(sensor, now),
+        _SensorCommand::Recalibrate => match sensor._state() {
+            Operational | Variant | Garbage => sensor._recalibrate(),
+            Frozen(_) | Rebooting(_) => {} // the sensor isn't running to take the reference measurement.
+        },
+        _SensorCommand::PowerOff => return false,
+        _SensorCommand::PowerOn => {
+            if !powered {
+                failures._begin_reboot(sensor, now);
+            }
+            return true;
+        }
+    }
+    powered
+}
+
+#[cfg(test)]
+#[allow(clippy::arithmetic_side_effects)] // this is test code.
+pub(crate) fn _wait_for_change<T>(receiver: &mut watch::Receiver<T>) -> bool {
+    //! (MR G.2) test helper; a healthy sensor thread publishes within a few ms, so 1s means it isn't running.
+    let deadline = Instant::now() + Duration::from_secs(1);
+    while Instant::now() < deadline {
+        if receiver.has_changed().unwrap_or(false) {
+            return true;
+        }
+        thread::sleep(_JOIN_POLL);
+    }
+    false
+}
+
+#[cfg(test)]
+#[allow(clippy::arithmetic_side_effects)] // this is test code.
+pub(crate) fn _wait_for_reading<T>(
+    receiver: &watch::Receiver<super::_SensorReading<T>>,
+    since: SimTime,
+) -> bool {
+    //! (MR G.2) test helper; waits for a reading stamped at or after since, as published once a sensor has caught up
+    //! with the clock. Doesn't mark anything as seen.
+    let deadline = Instant::now() + Duration::from_secs(1);
+    while Instant::now() < deadline {
+        if receiver.borrow().time >= since {
+            return true;
+        }
+        thread::sleep(_JOIN_POLL);
+    }
+    false
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)] // this is test code.
+mod tests {
+    use super::*;
+    use crate::hardware::sensors::_SensorReading;
+    use crate::hardware::sensors::altimeter::_AltimeterData;
+    use crate::hardware::sensors::command::{_command_channel, _send};
+    use crate::hardware::sensors::failure::_FailureRates;
+    use crate::hardware::sensors::scheduler::_JitterConfig;
+    use agc_utils::{Quaternion, SimClock, SolarFp, SolarVec3D, StepVec3D, UnitFp};
+
+    fn truth() -> _Rocket {
+        _Rocket::_new(
+            SolarVec3D::from_floats(10_000.0, 0.0, 0.0).unwrap(),
+            StepVec3D::new(),
+            Quaternion::IDENTITY,
+        )
+    }
+
+    fn spawn_altimeter(
+        clock: &SimClock,
+        truth: watch::Receiver<_Rocket>,
+        shutdown: watch::Receiver<bool>,
+    ) -> (_SensorThread, watch::Receiver<_SensorReading<SolarFp>>) {
+        let (thread, readings, _commands) =
+            spawn_with_failures(clock, truth, shutdown, _FailureRates::_NEVER_FAIL);
+        (thread, readings)
+    }
+
+    fn spawn_with_failures(
+        clock: &SimClock,
+        truth: watch::Receiver<_Rocket>,
+        shutdown: watch::Receiver<bool>,
+        rates: _FailureRates,
+    ) -> (
+        _SensorThread,
+        watch::Receiver<_SensorReading<SolarFp>>,
+        mpsc::Sender<_SensorCommand>,
+    ) {
+        let (altimeter, readings) = _AltimeterData::_new(clock.reader(), 1);
+        let period = altimeter._polling_period();
+        let (commands, command_receiver) = _command_channel();
+        let links = _SensorLinks {
+            clock: clock.reader(),
+            truth,
+            shutdown,
+            commands: command_receiver,
+        };
+        let thread = _SensorThread::_spawn(
+            "altimeter",
+            altimeter,
+            _FailureModel::_new(rates, 2),
+            _PollScheduler::_new(period, _JitterConfig::_DEFAULT, 3, clock.now()),
+            links,
+        )
+        .unwrap();
+        (thread, readings, commands)
+    }
+
+    #[test]
+    fn polls_as_sim_time_advances() {
+        let mut clock = SimClock::new();
+        let (_truth_sender, truth_receiver) = watch::channel(truth());
+        let (shutdown, shutdown_receiver) = watch::channel(false);
+        let (thread, mut readings) = spawn_altimeter(&clock, truth_receiver, shutdown_receiver);
+
+        // not yet due: the first poll is ~1s of sim time away.
+        thread::sleep(Duration::from_millis(20));
+        assert!(!readings.has_changed().unwrap());
+
+        clock.advance(SimTime::from_secs(2));
+        assert!(_wait_for_change(&mut readings));
+        assert!(readings.borrow_and_update().time >= SimTime::from_secs(1));
+
+        shutdown.send_replace(true);
+        thread._join_by(Instant::now() + _SHUTDOWN_TIMEOUT).unwrap();
+    }
+
+    #[test]
+    fn exits_when_simulation_ends() {
+        let clock = SimClock::new();
+        let (truth_sender, truth_receiver) = watch::channel(truth());
+        let (_shutdown, shutdown_receiver) = watch::channel(false);
+        let (thread, _readings) = spawn_altimeter(&clock, truth_receiver, shutdown_receiver);
+        drop(truth_sender);
+        thread._join_by(Instant::now() + _SHUTDOWN_TIMEOUT).unwrap();
+    }
+
+    #[test]
+    fn exits_when_controller_goes_away() {
+        let clock = SimClock::new();
+        let (_truth_sender, truth_receiver) = watch::channel(truth());
+        let (shutdown, shutdown_receiver) = watch::channel(false);
+        let (thread, _readings) = spawn_altimeter(&clock, truth_receiver, shutdown_receiver);
+        drop(shutdown);
+        thread._join_by(Instant::now() + _SHUTDOWN_TIMEOUT).unwrap();
+    }
+
+    #[test]
+    fn join_times_out_on_hung_thread() {
+        let hung = _SensorThread {
+            name: "hung",
+            handle: thread::spawn(|| thread::sleep(Duration::from_millis(200))),
+        };
+        let result = hung._join_by(Instant::now() + Duration::from_millis(10));
+        assert!(matches!(
+            result,
+            Err(_HarnessError::ShutdownTimeout { sensor: "hung" })
+        ));
+    }
+
+    #[test]
+    fn panics_are_reported() {
+        #[allow(clippy::panic)] // deliberately simulating a broken sensor.
+        let panicking = _SensorThread {
+            name: "broken",
+            handle: thread::spawn(|| panic!("sensor fault")),
+        };
+        let result = panicking._join_by(Instant::now() + _SHUTDOWN_TIMEOUT);
+        assert!(matches!(
+            result,
+            Err(_HarnessError::Panicked { sensor: "broken" })
+        ));
+    }
+
+    fn advance_until_published(
+        clock: &mut SimClock,
+        readings: &mut watch::Receiver<_SensorReading<SolarFp>>,
+        max_secs: u32,
+    ) -> bool {
+        //! steps sim time a second at a time, giving the thread a few ms of real time to respond to each step.
+        for _ in 0..max_secs {
+            clock.advance(SimTime::from_secs(1));
+            thread::sleep(Duration::from_millis(5));
+            if readings.has_changed().unwrap() {
+                return true;
+            }
+        }
+        false
+    }
+
+    #[test]
+    fn frozen_sensor_can_be_rebooted() {
+        let mut clock = SimClock::new();
+        let (_truth_sender, truth_receiver) = watch::channel(truth());
+        let (shutdown, shutdown_receiver) = watch::channel(false);
+        let rates = _FailureRates {
+            hang_probability: UnitFp::from_int(1),
+            hang_duration: SimTime::from_secs(1_000_000),
+            .._FailureRates::_NEVER_FAIL
+        };
+        let (thread, mut readings, commands) =
+            spawn_with_failures(&clock, truth_receiver, shutdown_receiver, rates);
+
+        // hangs on its first poll, and never publishes.
+        assert!(!advance_until_published(&mut clock, &mut readings, 20));
+
+        let start = Instant::now();
+        _send(&commands, _SensorCommand::Reboot).unwrap();
+        assert!(start.elapsed() < Duration::from_millis(10)); // the controller isn't held up.
+
+        // back after the 10s reboot.
+        assert!(advance_until_published(&mut clock, &mut readings, 30));
+
+        shutdown.send_replace(true);
+        thread._join_by(Instant::now() + _SHUTDOWN_TIMEOUT).unwrap();
+    }
+
+    #[test]
+    fn powered_off_sensor_is_silent_until_powered_on() {
+        let mut clock = SimClock::new();
+        let (_truth_sender, truth_receiver) = watch::channel(truth());
+        let (shutdown, shutdown_receiver) = watch::channel(false);
+        let (thread, mut readings, commands) = spawn_with_failures(
+            &clock,
+            truth_receiver,
+            shutdown_receiver,
+            _FailureRates::_NEVER_FAIL,
+        );
+        assert!(advance_until_published(&mut clock, &mut readings, 5));
+        readings.mark_unchanged();
+
+        _send(&commands, _SensorCommand::PowerOff).unwrap();
+        thread::sleep(Duration::from_millis(20));
+        assert!(!advance_until_published(&mut clock, &mut readings, 20));
+
+        _send(&commands, _SensorCommand::PowerOn).unwrap();
+        assert!(advance_until_published(&mut clock, &mut readings, 30));
+        assert!(readings.borrow().time > SimTime::from_secs(20));
+
+        shutdown.send_replace(true);
+        thread._join_by(Instant::now() + _SHUTDOWN_TIMEOUT).unwrap();
+    }
+
+    #[test]
+    fn commands_change_power_and_state() {
+        let clock = SimClock::new();
+        let (mut altimeter, _readings) = _AltimeterData::_new(clock.reader(), 1);
+        let mut failures = _FailureModel::_new(_FailureRates::_NEVER_FAIL, 2);
+        let now = SimTime::from_secs(5);
+        let mut apply = |altimeter: &mut _AltimeterData, command, powered| {
+            _apply_command(altimeter, &mut failures, command, powered, now)
+        };
+
+        assert!(!apply(&mut altimeter, _SensorCommand::PowerOff, true));
+        assert!(apply(&mut altimeter, _SensorCommand::PowerOn, true));
+        assert_eq!(altimeter._state(), Operational); // already on: no reboot.
+        assert!(apply(&mut altimeter, _SensorCommand::PowerOn, false));
+        assert_eq!(altimeter._state(), Rebooting(SimTime::from_secs(15)));
+
+        altimeter._set_state(Frozen(SimTime::from_secs(100)));
+        assert!(apply(&mut altimeter, _SensorCommand::Recalibrate, true));
+        assert_eq!(altimeter._state(), Frozen(SimTime::from_secs(100)));
+        assert!(apply(&mut altimeter, _SensorCommand::Reboot, true));
+        assert_eq!(altimeter._state(), Rebooting(SimTime::from_secs(15)));
+    }
+}
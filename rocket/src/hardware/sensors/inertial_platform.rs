@@ -1,6 +1,431 @@
 //! contains the struct definitin for the InertialPlatform; a combined accelerometer/gyroscope.
+//! The accelerometer reads specific force (non-gravitational acceleration) and the gyroscope reads angular rate, both along
+//! the body axes. Each is subject to a fixed bias, per-axis scale-factor error and axis misalignment (randomised on
+//! startup), a random-walk drift that grows with sqrt(time), and white noise on every reading.
+
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use tokio::sync::watch;
+
+use agc_utils::{
+    ArithmeticError, EulerAngles, FixedPoint, Quaternion, SimClockReader, SimTime, StepFp,
+    StepVec3D, TimeFp, UnitFp, UnitVec3D, Vec3D,
+};
+
+use super::{
+    _Sensor, _SensorReading,
+    _SensorState::{self, *},
+    _random_within,
+};
+use crate::hardware::rocket::_Rocket;
+
+// error bounds, representative of a 1960s gimballed platform.
+const _ACCEL_BIAS_BOUNDS: StepFp = StepFp::from_f64_trusted(5e-4); // m/s^2; ~50 micro-g.
+const _ACCEL_RANDOM_WALK: StepFp = StepFp::from_f64_trusted(1e-5); // m/s^2 per sqrt(s).
+const _ACCEL_NOISE: StepFp = StepFp::from_f64_trusted(1e-4); // m/s^2, per reading.
+const _GYRO_BIAS_BOUNDS: StepFp = StepFp::from_f64_trusted(5e-8); // rad/s; ~0.01 deg/hr.
+const _GYRO_RANDOM_WALK: StepFp = StepFp::from_f64_trusted(1e-9); // rad/s per sqrt(s).
+const _GYRO_NOISE: StepFp = StepFp::from_f64_trusted(1e-7); // rad/s, per reading.
+const _SCALE_FACTOR_BOUNDS: UnitFp = UnitFp::from_f64_trusted(1e-4); // 100 ppm.
+const _MISALIGNMENT_BOUNDS: UnitFp = UnitFp::from_f64_trusted(1e-4); // rad, about each axis.
+const _POLLING_PERIOD: SimTime = SimTime::from_fp(TimeFp::from_f64_trusted(0.1)); // 10Hz; dead-reckoning needs frequent samples.
+const _VARIANT_NOISE_MULTIPLIER: StepFp = StepFp::from_f64_trusted(5.0); // matches the altimeter's Variant behaviour.
+
+/// a single sample from the platform. Both vectors are in the body frame.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct _InertialReading {
+    pub(crate) acceleration: StepVec3D, // m/s^2
+    pub(crate) angular_rate: StepVec3D, // rad/s
+}
+
+impl _InertialReading {
+    fn _zero() -> Self {
+        Self {
+            acceleration: StepVec3D::new(),
+            angular_rate: StepVec3D::new(),
+        }
+    }
+}
+
+/// the error model of one triad of instruments (3 accelerometers or 3 gyroscopes).
+struct _AxisErrors {
+    bias: StepVec3D,          // fixed offset per axis.
+    scale_factor: UnitVec3D,  // fractional gain error per axis.
+    misalignment: Quaternion, // small rotation between the true body axes and the instrument axes.
+    walk: StepVec3D,          // accumulated random-walk drift.
+    walk_coefficient: StepFp, // random-walk growth per sqrt(second).
+    noise: StepFp,            // white noise bound per reading.
+}
+
+impl _AxisErrors {
+    fn _randomised(
+        rng: &mut StdRng,
+        bias_bound: StepFp,
+        walk_coefficient: StepFp,
+        noise: StepFp,
+    ) -> Self {
+        //! draws a fresh set of fixed errors, as happens on startup/reboot. Drift starts from zero.
+        Self {
+            bias: _random_vec(rng, bias_bound),
+            scale_factor: _random_vec(rng, _SCALE_FACTOR_BOUNDS),
+            misalignment: Quaternion::from_euler(EulerAngles {
+                roll: _random_within(rng, _MISALIGNMENT_BOUNDS),
+                pitch: _random_within(rng, _MISALIGNMENT_BOUNDS),
+                yaw: _random_within(rng, _MISALIGNMENT_BOUNDS),
+            }),
+            walk: StepVec3D::new(),
+            walk_coefficient,
+            noise,
+        }
+    }
+
+    fn _accelerometer(rng: &mut StdRng) -> Self {
+        Self::_randomised(rng, _ACCEL_BIAS_BOUNDS, _ACCEL_RANDOM_WALK, _ACCEL_NOISE)
+    }
+
+    fn _gyroscope(rng: &mut StdRng) -> Self {
+        Self::_randomised(rng, _GYRO_BIAS_BOUNDS, _GYRO_RANDOM_WALK, _GYRO_NOISE)
+    }
+
+    fn _apply(
+        &self,
+        truth: &StepVec3D,
+        noise_multiplier: StepFp,
+        rng: &mut StdRng,
+    ) -> Result<StepVec3D, ArithmeticError> {
+        //! corrupts a true body-frame vector with every error term to produce what the instruments would read.
+        let misaligned = self.misalignment.rotate(truth)?;
+        let one = UnitFp::from_int(1);
+        let scaled = Vec3D(
+            one.checked_add(self.scale_factor.0)?
+                .checked_scale_by_other(misaligned.0)?,
+            one.checked_add(self.scale_factor.1)?
+                .checked_scale_by_other(misaligned.1)?,
+            one.checked_add(self.scale_factor.2)?
+                .checked_scale_by_other(misaligned.2)?,
+        );
+        let noise = _random_vec(rng, self.noise.checked_mul(noise_multiplier)?);
+        scaled
+            .checked_add(&self.bias)?
+            .checked_add(&self.walk)?
+            .checked_add(&noise)
+    }
+
+    fn _random_walk(&mut self, elapsed: SimTime, rng: &mut StdRng) -> Result<(), ArithmeticError> {
+        //! advances the drift by one random step, sized by sqrt(elapsed) as befits a random walk.
+        let root_elapsed: StepFp = elapsed.as_fp().sqrt()?.convert()?;
+        let step = _random_vec(rng, self.walk_coefficient.checked_mul(root_elapsed)?);
+        self.walk = self.walk.checked_add(&step)?;
+        Ok(())
+    }
+}
+
+fn _random_vec<const N: u8>(rng: &mut StdRng, bound: FixedPoint<N>) -> Vec3D<N> {
+    //! a vector with each component independently uniform within [-bound, bound].
+    Vec3D(
+        _random_within(rng, bound),
+        _random_within(rng, bound),
+        _random_within(rng, bound),
+    )
+}
 
-use crate::hardware::sensors::_SensorState;
 pub struct _InertialPlatformData {
     state: _SensorState,
+    accelerometer: _AxisErrors,
+    gyroscope: _AxisErrors,
+    last_reading: _SensorReading<_InertialReading>, // last reading collected by the device.
+    last_poll: SimTime,                             // used to size each random-walk step.
+    clock: SimClockReader,
+    polling_period: SimTime,
+    rng: StdRng, // seeded so that runs are reproducible.
+    send_channel: watch::Sender<_SensorReading<_InertialReading>>,
+}
+
+impl _InertialPlatformData {
+    pub fn _new(
+        clock: SimClockReader,
+        seed: u64,
+    ) -> (Self, watch::Receiver<_SensorReading<_InertialReading>>) {
+        //! powers up a platform with freshly randomised errors. Returns the receiving end of its output channel.
+        let mut rng = StdRng::seed_from_u64(seed);
+        let now = clock.now();
+        let last_reading = _SensorReading {
+            data: _InertialReading::_zero(),
+            time: now,
+        };
+        let (send_channel, receiver) = watch::channel(last_reading);
+        let platform = Self {
+            state: Operational,
+            accelerometer: _AxisErrors::_accelerometer(&mut rng),
+            gyroscope: _AxisErrors::_gyroscope(&mut rng),
+            last_reading,
+            last_poll: now,
+            clock,
+            polling_period: _POLLING_PERIOD,
+            rng,
+            send_channel,
+        };
+        (platform, receiver)
+    }
+
+    fn _measure(&mut self, rocket: &_Rocket, now: SimTime, noise_multiplier: StepFp) {
+        //! takes a reading from the rocket's true motion, corrupted by the platform's errors.
+        let acceleration = rocket._true_specific_force().and_then(|truth| {
+            self.accelerometer
+                ._apply(&truth, noise_multiplier, &mut self.rng)
+        });
+        let angular_rate = self.gyroscope._apply(
+            &rocket._true_angular_rate(),
+            noise_multiplier,
+            &mut self.rng,
+        );
+        // (MR B.5) on overflow the instrument is beyond its range; like a pegged needle, it produces no new reading.
+        if let (Ok(acceleration), Ok(angular_rate)) = (acceleration, angular_rate) {
+            self.last_reading = _SensorReading {
+                data: _InertialReading {
+                    acceleration,
+                    angular_rate,
+                },
+                time: now,
+            }
+        }
+    }
+}
+
+impl _Sensor for _InertialPlatformData {
+    type Output = _InertialReading;
+
+    fn _poll(&mut self, rocket: &_Rocket) {
+        let now = self.clock.now();
+        self._update_drift(now);
+        match self.state {
+            Operational => self._measure(rocket, now, StepFp::from_int(1)),
+            Variant => self._measure(rocket, now, _VARIANT_NOISE_MULTIPLIER),
+            Garbage => {
+                self.last_reading = _SensorReading {
+                    data: _InertialReading {
+                        acceleration: _garbage_vec(&mut self.rng),
+                        angular_rate: _garbage_vec(&mut self.rng),
+                    },
+                    time: now,
+                }
+            }
+            Frozen(_) | Rebooting(_) => {
+                // hung or restarting: no new reading is taken, so the held one goes stale.
+            }
+        }
+    }
+
+    fn _update_drift(&mut self, now: SimTime) {
+        //! grows the random-walk drift of both triads by the time since the last poll. Drift continues while hung.
+        let elapsed = now.elapsed_since(self.last_poll);
+        self.last_poll = now;
+        // (MR B.5) an overflowing step is discarded, leaving drift where it was; only reachable after ~10^20 years.
+        let _ = self.accelerometer._random_walk(elapsed, &mut self.rng);
+        let _ = self.gyroscope._random_walk(elapsed, &mut self.rng);
+    }
+
+    fn _state(&self) -> _SensorState {
+        self.state
+    }
+
+    fn _set_state(&mut self, state: _SensorState) {
+        self.state = state;
+    }
+
+    fn _polling_period(&self) -> SimTime {
+        self.polling_period
+    }
+
+    fn _reboot(&mut self) {
+        //! bias, scale factor and misalignment are all re-established on power-up, and drift restarts from zero.
+        self.accelerometer = _AxisErrors::_accelerometer(&mut self.rng);
+        self.gyroscope = _AxisErrors::_gyroscope(&mut self.rng);
+        self.last_poll = self.clock.now();
+    }
+
+    fn _recalibrate(&mut self) {
+        //! the random walk is the only accumulated error; bias, scale factor and misalignment are fixed until reboot.
+        self.accelerometer.walk = StepVec3D::new();
+        self.gyroscope.walk = StepVec3D::new();
+    }
+
+    fn _last_reading(&self) -> _SensorReading<_InertialReading> {
+        self.last_reading
+    }
+
+    fn _output(&self) -> &watch::Sender<_SensorReading<_InertialReading>> {
+        &self.send_channel
+    }
+}
+
+fn _garbage_vec(rng: &mut StdRng) -> StepVec3D {
+    //! truly random but correctly typed data.
+    Vec3D(
+        StepFp::with_internal(rng.gen()),
+        StepFp::with_internal(rng.gen()),
+        StepFp::with_internal(rng.gen()),
+    )
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::float_arithmetic)] // this is test code.
+mod tests {
+    use super::*;
+    use agc_utils::{SimClock, SolarVec3D};
+
+    const SEED: u64 = 1969;
+
+    fn test_rocket() -> _Rocket {
+        //! a rocket burning at 2 m/s^2 along the reference z axis and rolling slowly.
+        _Rocket::_new(SolarVec3D::new(), StepVec3D::new(), Quaternion::IDENTITY)._with_motion(
+            StepVec3D::from_floats(0.01, 0.0, 0.0).unwrap(),
+            StepVec3D::from_floats(0.0, 0.0, 2.0).unwrap(),
+        )
+    }
+
+    fn max_error(measured: &StepVec3D, truth: &StepVec3D) -> f64 {
+        let difference = measured.sub(truth);
+        [difference.0, difference.1, difference.2]
+            .iter()
+            .map(|component| component.to_f64().abs())
+            .fold(0.0, f64::max)
+    }
+
+    #[test]
+    fn operational_reading_within_error_budget() {
+        let clock = SimClock::new();
+        let (mut platform, _receiver) = _InertialPlatformData::_new(clock.reader(), SEED);
+        let rocket = test_rocket();
+        platform._poll(&rocket);
+        let reading = platform.last_reading.data;
+        // scale factor + misalignment (~2 * 1e-4 * 2) + bias + noise.
+        let accel_error = max_error(
+            &reading.acceleration,
+            &rocket._true_specific_force().unwrap(),
+        );
+        assert!(accel_error > 0.0 && accel_error < 1.5e-3, "{accel_error}");
+        let gyro_error = max_error(&reading.angular_rate, &rocket._true_angular_rate());
+        assert!(gyro_error > 0.0 && gyro_error < 3e-6, "{gyro_error}");
+    }
+
+    #[test]
+    fn variant_readings_are_noisier() {
+        let clock = SimClock::new();
+        let rocket = test_rocket();
+        let truth = rocket._true_specific_force().unwrap();
+        let worst_error = |state: _SensorState| {
+            let (mut platform, _receiver) = _InertialPlatformData::_new(clock.reader(), SEED);
+            platform.state = state;
+            (0..200)
+                .map(|_| {
+                    platform._poll(&rocket);
+                    max_error(&platform.last_reading.data.acceleration, &truth)
+                })
+                .fold(0.0, f64::max)
+        };
+        let operational = worst_error(Operational);
+        let variant = worst_error(Variant);
+        // five times the noise bound now dominates the budget.
+        assert!(
+            variant > operational && variant < 2e-3,
+            "{operational} {variant}"
+        );
+    }
+
+    #[test]
+    fn readings_are_stamped_with_sim_time() {
+        let mut clock = SimClock::new();
+        let (mut platform, _receiver) = _InertialPlatformData::_new(clock.reader(), SEED);
+        clock.advance(SimTime::from_secs(30));
+        platform._poll(&test_rocket());
+        assert_eq!(platform.last_reading.time, SimTime::from_secs(30));
+    }
+
+    #[test]
+    fn drift_grows_over_time() {
+        let mut clock = SimClock::new();
+        let (mut platform, _receiver) = _InertialPlatformData::_new(clock.reader(), SEED);
+        let rocket = test_rocket();
+        for _ in 0..100 {
+            clock.advance(SimTime::from_secs(3600));
+            platform._poll(&rocket);
+        }
+        // 100 steps of up to 1e-5 * sqrt(3600) each: non-zero, and bounded by the sum of step bounds.
+        let walk = platform.accelerometer.walk.magnitude().to_f64();
+        assert!(walk > 0.0 && walk < 100.0 * 6e-4 * 3f64.sqrt(), "{walk}");
+        assert!(platform.gyroscope.walk.magnitude().to_f64() > 0.0);
+    }
+
+    #[test]
+    fn garbage_is_correctly_typed_nonsense() {
+        let mut clock = SimClock::new();
+        let (mut platform, _receiver) = _InertialPlatformData::_new(clock.reader(), SEED);
+        platform.state = Garbage;
+        clock.advance(SimTime::from_secs(1));
+        let rocket = test_rocket();
+        platform._poll(&rocket);
+        let reading = platform.last_reading;
+        assert_eq!(reading.time, SimTime::from_secs(1));
+        let error = max_error(
+            &reading.data.acceleration,
+            &rocket._true_specific_force().unwrap(),
+        );
+        assert!(error > 1.0, "{error}");
+    }
+
+    #[test]
+    fn frozen_and_rebooting_hold_stale_reading() {
+        let mut clock = SimClock::new();
+        let (mut platform, _receiver) = _InertialPlatformData::_new(clock.reader(), SEED);
+        let rocket = test_rocket();
+        platform._poll(&rocket);
+        let held = platform.last_reading;
+
+        for state in [
+            Frozen(SimTime::from_secs(60)),
+            Rebooting(SimTime::from_secs(60)),
+        ] {
+            platform.state = state;
+            clock.advance(SimTime::from_secs(10));
+            platform._poll(&rocket);
+            assert_eq!(platform.last_reading, held);
+        }
+    }
+
+    #[test]
+    fn publishes_to_flight_controller() {
+        let mut clock = SimClock::new();
+        let (mut platform, mut receiver) = _InertialPlatformData::_new(clock.reader(), SEED);
+        clock.advance(SimTime::from_secs(5));
+        platform._poll(&test_rocket());
+        assert!(!receiver.has_changed().unwrap());
+        platform._publish();
+        assert!(receiver.has_changed().unwrap());
+        assert_eq!(*receiver.borrow_and_update(), platform.last_reading);
+    }
+
+    #[test]
+    fn seeded_platforms_are_reproducible() {
+        let clock = SimClock::new();
+        let (mut first, _r1) = _InertialPlatformData::_new(clock.reader(), SEED);
+        let (mut second, _r2) = _InertialPlatformData::_new(clock.reader(), SEED);
+        let rocket = test_rocket();
+        first._poll(&rocket);
+        second._poll(&rocket);
+        assert_eq!(first.last_reading, second.last_reading);
+    }
+
+    #[test]
+    fn recalibration_clears_drift_but_not_bias() {
+        let mut clock = SimClock::new();
+        let (mut platform, _receiver) = _InertialPlatformData::_new(clock.reader(), SEED);
+        clock.advance(SimTime::from_secs(3600));
+        platform._poll(&test_rocket());
+        let bias = platform.accelerometer.bias;
+        assert_ne!(platform.accelerometer.walk, StepVec3D::new());
+        platform._recalibrate();
+        assert_eq!(platform.accelerometer.walk, StepVec3D::new());
+        assert_eq!(platform.gyroscope.walk, StepVec3D::new());
+        assert_eq!(platform.accelerometer.bias, bias);
+    }
 }
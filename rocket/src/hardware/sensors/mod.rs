@@ -1,25 +1,209 @@
-use std::time::Instant;
+use agc_utils::{FixedPoint, SimTime, UnitFp};
+use rand::Rng;
+use tokio::sync::watch;
+
+use crate::hardware::rocket::_Rocket;
 
 pub mod altimeter;
+pub mod command; // flight controller -> sensor commands
+pub mod failure; // drives sensors between _SensorState's
+pub mod harness; // one thread per sensor
 pub mod inertial_platform; // gyroscope + accelerometer
+pub mod scheduler; // jittered polling times
+
+use _SensorState::*;
+
+pub(crate) trait _Sensor {
+    /// Common interface for every instrument on the rocket. New sensors implement this rather than being listed
+    /// centrally, so they can be driven (and tested) generically.
+    type Output: Copy; // the data type carried by each reading.
+
+    /// takes a reading from the rocket's ground truth, corrupted by this sensor's errors. Sensors fail silently, and
+    /// this only updates the internally held reading; see _publish().
+    fn _poll(&mut self, rocket: &_Rocket);
+
+    fn _state(&self) -> _SensorState;
+    fn _set_state(&mut self, state: _SensorState);
+
+    /// the simulated time between consecutive polls.
+    fn _polling_period(&self) -> SimTime;
+
+    /// grows this sensor's slowly accumulating error up to the given time.
+    fn _update_drift(&mut self, now: SimTime);
+
+    /// zeroes accumulated drift, as if measured against a known reference. Power-up errors are left alone.
+    fn _recalibrate(&mut self);
 
-enum _Sensor {
-    /// enum wrapper to enable iteration and single-array storage of different sensors.
-    Altimeter(altimeter::_AltimeterData),
-    InertialPlatform(inertial_platform::_InertialPlatformData),
+    /// called as a reboot completes. Re-randomises any errors that are fixed at power-up, and resets drift.
+    fn _reboot(&mut self);
+
+    fn _last_reading(&self) -> _SensorReading<Self::Output>;
+    fn _output(&self) -> &watch::Sender<_SensorReading<Self::Output>>;
+
+    fn _publish(&self) {
+        //! pushes the held reading out to the flight controller. Overwrites any reading it hasn't yet looked at.
+        self._output().send_replace(self._last_reading());
+    }
+
+    fn _subscribe(&self) -> watch::Receiver<_SensorReading<Self::Output>> {
+        //! another receiving end of this sensor's output channel.
+        self._output().subscribe()
+    }
 }
 
-enum _SensorState {
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum _SensorState {
     /// All sensors are capable of falling into any of these states.
     Operational, // subject to minimal variance, working as expected. Operational variance is defined during instantiation of the hardware.
-    Variant,        // subject to 10x variance compared normal, otherwise all working
-    Garbage,        // throws out technically parseable data with truly random values.
-    Frozen(f64),    // simulates hanging sensor. Carried number is unfreeze time.
-    Rebooting(f64), // triggered by FlightController. Reverts to Operational after time.
+    Variant,            // subject to 10x variance compared normal, otherwise all working
+    Garbage,            // throws out technically parseable data with truly random values.
+    Frozen(SimTime),    // simulates hanging sensor. Carried time is the unfreeze deadline.
+    Rebooting(SimTime), // triggered by FlightController. Reverts to Operational at the carried deadline.
 }
 
-struct _SensorReading<T> {
+impl _SensorState {
+    fn _deadline(&self) -> Option<SimTime> {
+        //! the time at which a hung or rebooting sensor returns to Operational, if it is in such a state.
+        match self {
+            Frozen(deadline) | Rebooting(deadline) => Some(*deadline),
+            Operational | Variant | Garbage => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct _SensorReading<T> {
     /// Represents a single reading from a sensor. Contains the reading data (type: T) and the time it was harvested.
-    data: T,
-    time: Instant,
+    pub(crate) data: T,
+    pub(crate) time: SimTime,
+}
+
+fn _random_fraction(rng: &mut impl Rng) -> UnitFp {
+    //! uniformly distributed in [-1, 1]. Used to randomise error terms within their bounds.
+    let one = UnitFp::from_int(1).internal();
+    UnitFp::with_internal(rng.gen_range(one.saturating_neg()..=one)) // (MR B.3) negating 2^60 can't saturate.
+}
+
+pub(crate) fn _random_within<const N: u8>(
+    rng: &mut impl Rng,
+    bound: FixedPoint<N>,
+) -> FixedPoint<N> {
+    //! uniformly distributed in [-bound, bound].
+    _random_fraction(rng)
+        .checked_scale_by_other(bound)
+        .unwrap_or(bound) // (MR B.1) unreachable fallback; |fraction| <= 1 so the result can't exceed bound.
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)] // this is test code.
+mod tests {
+    use super::*;
+    use agc_utils::{Quaternion, SimClock, SolarFp, SolarVec3D, StepVec3D, Vec3D};
+
+    fn test_rocket() -> _Rocket {
+        //! 10km above the nearest body, under a gentle burn.
+        _Rocket::_new(
+            SolarVec3D::from_floats(10_000.0, 0.0, 0.0).unwrap(),
+            StepVec3D::new(),
+            Quaternion::IDENTITY,
+        )
+        ._with_motion(
+            StepVec3D::new(),
+            StepVec3D::from_floats(1.0, 0.0, 0.0).unwrap(),
+        )
+    }
+
+    fn check_sensor<S: _Sensor>(mut sensor: S, clock: &mut SimClock)
+    where
+        S::Output: PartialEq + std::fmt::Debug,
+    {
+        //! behaviour every sensor must share, whatever it measures.
+        let rocket = test_rocket();
+        let mut receiver = sensor._subscribe();
+        assert!(sensor._polling_period() > SimTime::ZERO);
+
+        // readings are stamped with sim time, and only reach the controller once published.
+        clock.advance(sensor._polling_period());
+        sensor._poll(&rocket);
+        let reading = sensor._last_reading();
+        assert_eq!(reading.time, clock.now());
+        assert!(!receiver.has_changed().unwrap());
+        sensor._publish();
+        assert_eq!(*receiver.borrow_and_update(), reading);
+
+        // hung and rebooting sensors hold a stale reading.
+        for state in [
+            Frozen(SimTime::from_secs(60)),
+            Rebooting(SimTime::from_secs(60)),
+        ] {
+            sensor._set_state(state);
+            assert_eq!(sensor._state(), state);
+            clock.advance(sensor._polling_period());
+            sensor._poll(&rocket);
+            assert_eq!(sensor._last_reading(), reading);
+        }
+
+        // after a reboot, the sensor reads again.
+        sensor._reboot();
+        sensor._set_state(Operational);
+        clock.advance(sensor._polling_period());
+        sensor._poll(&rocket);
+        assert_eq!(sensor._last_reading().time, clock.now());
+    }
+
+    #[test]
+    fn altimeter_is_a_sensor() {
+        let mut clock = SimClock::new();
+        let (altimeter, _receiver) = altimeter::_AltimeterData::_new(clock.reader(), 1);
+        check_sensor(altimeter, &mut clock);
+    }
+
+    #[test]
+    fn altimeter_out_of_all_range_reports_no_return() {
+        // so far from the nearest body that the distance can't be represented; still no panic on the sensor thread.
+        let mut clock = SimClock::new();
+        let (mut altimeter, _receiver) = altimeter::_AltimeterData::_new(clock.reader(), 1);
+        let edge = SolarFp::with_internal(i64::MAX);
+        let rocket = _Rocket::_new(
+            Vec3D(edge, edge, edge),
+            StepVec3D::new(),
+            Quaternion::IDENTITY,
+        );
+        assert_eq!(rocket._true_distance_to_nearest_body(), None);
+        clock.advance(altimeter._polling_period());
+        altimeter._poll(&rocket);
+        assert_eq!(altimeter._last_reading().data, edge);
+        assert_eq!(altimeter._last_reading().time, clock.now());
+    }
+
+    #[test]
+    fn inertial_platform_is_a_sensor() {
+        let mut clock = SimClock::new();
+        let (platform, _receiver) =
+            inertial_platform::_InertialPlatformData::_new(clock.reader(), 1);
+        check_sensor(platform, &mut clock);
+    }
+
+    #[test]
+    fn reaction_wheels_are_a_sensor() {
+        // their tachometers are read, and their electronics fail, like any other instrument's.
+        let mut clock = SimClock::new();
+        let (wheels, _receiver) =
+            crate::hardware::controllers::reaction_wheels::_ReactionWheels::_new(
+                clock.reader(),
+                super::failure::_FailureRates::_NEVER_FAIL,
+                1,
+            );
+        check_sensor(wheels, &mut clock);
+    }
+
+    #[test]
+    fn only_hung_or_rebooting_states_have_deadlines() {
+        let deadline = SimTime::from_secs(60);
+        assert_eq!(Frozen(deadline)._deadline(), Some(deadline));
+        assert_eq!(Rebooting(deadline)._deadline(), Some(deadline));
+        for state in [Operational, Variant, Garbage] {
+            assert_eq!(state._deadline(), None);
+        }
+    }
 }
@@ -0,0 +1,191 @@
+//! contains the poll scheduler which decides when each sensor next reports. Per constraint 3d, feedback arrives at the
+//! sensor's spec'd polling period varied by +-5%, with occasional bursts of greater variance. All times are simulated,
+//! and the scheduler is seeded, so that a run's timing is reproducible and its distribution can be tested.
+
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+use agc_utils::{SimTime, TimeFp, UnitFp};
+
+use super::_random_within;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct _JitterConfig {
+    /// How far each poll interval may stray from the nominal period, as a fraction of it.
+    pub(crate) jitter: UnitFp,
+    pub(crate) burst_probability: UnitFp, // per interval chance that a burst starts.
+    pub(crate) burst_jitter: UnitFp,      // replaces jitter for the duration of a burst.
+    pub(crate) max_burst_length: u32, // intervals; each burst lasts uniformly between 1 and this.
+}
+
+impl _JitterConfig {
+    pub(crate) const _DEFAULT: Self = Self {
+        jitter: UnitFp::from_f64_trusted(0.05),
+        burst_probability: UnitFp::from_f64_trusted(0.001),
+        burst_jitter: UnitFp::from_f64_trusted(0.5),
+        max_burst_length: 10,
+    };
+}
+
+// the shortest interval ever scheduled; stops a large burst jitter scheduling a poll at or before the previous one.
+const _MIN_INTERVAL: SimTime = SimTime::from_fp(TimeFp::from_f64_trusted(0.001));
+
+pub(crate) struct _PollScheduler {
+    period: SimTime, // nominal, spec'd polling period.
+    config: _JitterConfig,
+    rng: StdRng,          // seeded so that runs are reproducible.
+    next_poll: SimTime,   // when the sensor is next due.
+    burst_remaining: u32, // intervals left in the current burst (if any).
+}
+
+impl _PollScheduler {
+    pub(crate) fn _new(period: SimTime, config: _JitterConfig, seed: u64, start: SimTime) -> Self {
+        //! a scheduler whose first poll falls one (jittered) interval after start.
+        let mut scheduler = Self {
+            period,
+            config,
+            rng: StdRng::seed_from_u64(seed),
+            next_poll: start,
+            burst_remaining: 0,
+        };
+        scheduler._advance();
+        scheduler
+    }
+
+    pub(crate) fn _next_poll(&self) -> SimTime {
+        self.next_poll
+    }
+
+    pub(crate) fn _is_due(&self, now: SimTime) -> bool {
+        now.has_reached(self.next_poll)
+    }
+
+    pub(crate) fn _advance(&mut self) {
+        //! schedules the poll after the current one. Intervals chain from the previous scheduled
+        //! poll rather than from when the sensor actually got round to it, so a late poll doesn't push back the rest.
+        let interval = self._interval();
+        self.next_poll = self.next_poll.saturating_add(interval); // (MR B.3) saturation is ~280k years away.
+    }
+
+    fn _interval(&mut self) -> SimTime {
+        //! a single interval; the nominal period varied by the current jitter bound.
+        let bound = self._current_jitter();
+        let variation = UnitFp::from_int(1).saturating_add(_random_within(&mut self.rng, bound)); // (MR B.3) bound is configured as a fraction, far from the UnitFp limit of 8.
+        let interval = variation
+            .checked_scale_by_other(self.period.as_fp())
+            .map(SimTime::from_fp)
+            .unwrap_or(self.period); // (MR B.1) only overflows for periods within a factor of 2 of TimeFp's limit; fall back to nominal.
+        interval.max(_MIN_INTERVAL)
+    }
+
+    fn _current_jitter(&mut self) -> UnitFp {
+        //! the jitter bound for the next interval; starts, continues or ends a burst as needed.
+        if self.burst_remaining == 0 {
+            let roll = UnitFp::with_internal(self.rng.gen_range(0..UnitFp::from_int(1).internal()));
+            if roll < self.config.burst_probability {
+                self.burst_remaining = self.rng.gen_range(1..=self.config.max_burst_length.max(1));
+            }
+        }
+        if self.burst_remaining == 0 {
+            self.config.jitter
+        } else {
+            self.burst_remaining = self.burst_remaining.saturating_sub(1); // (MR B.3) non-zero, checked above.
+            self.config.burst_jitter
+        }
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::float_arithmetic, clippy::as_conversions)] // this is test code.
+mod tests {
+    use super::*;
+
+    const SEED: u64 = 1201;
+    const PERIOD: SimTime = SimTime::from_secs(2);
+    const NO_BURSTS: _JitterConfig = _JitterConfig {
+        burst_probability: UnitFp::from_f64_trusted(0.0),
+        .._JitterConfig::_DEFAULT
+    };
+
+    fn intervals(config: _JitterConfig, count: usize) -> Vec<f64> {
+        let mut scheduler = _PollScheduler::_new(PERIOD, config, SEED, SimTime::ZERO);
+        let mut last = scheduler._next_poll();
+        (0..count)
+            .map(|_| {
+                scheduler._advance();
+                let next = scheduler._next_poll();
+                let interval = next.elapsed_since(last).to_f64();
+                last = next;
+                interval
+            })
+            .collect()
+    }
+
+    #[test]
+    fn jitter_within_five_percent() {
+        let intervals = intervals(NO_BURSTS, 10_000);
+        assert!(intervals.iter().all(|i| (1.9..=2.1).contains(i)));
+        let mean = intervals.iter().sum::<f64>() / intervals.len() as f64;
+        assert!((mean - 2.0).abs() < 0.005, "{mean}");
+        // the spread should actually use the allowed range, not just sit on the nominal period.
+        assert!(intervals.iter().any(|i| *i < 1.91) && intervals.iter().any(|i| *i > 2.09));
+    }
+
+    #[test]
+    fn bursts_exceed_normal_jitter() {
+        let config = _JitterConfig {
+            burst_probability: UnitFp::from_f64_trusted(0.01),
+            .._JitterConfig::_DEFAULT
+        };
+        let intervals = intervals(config, 10_000);
+        let outliers = intervals
+            .iter()
+            .filter(|i| !(1.9..=2.1).contains(*i))
+            .count();
+        // ~100 bursts of ~5.5 intervals, of which ~90% land outside +-5%.
+        assert!((200..1000).contains(&outliers), "{outliers}");
+        assert!(intervals.iter().all(|i| (1.0..=3.0).contains(i)));
+    }
+
+    #[test]
+    fn burst_lengths_are_bounded() {
+        let config = _JitterConfig {
+            burst_probability: UnitFp::from_f64_trusted(1.0),
+            max_burst_length: 3,
+            .._JitterConfig::_DEFAULT
+        };
+        let mut scheduler = _PollScheduler::_new(PERIOD, config, SEED, SimTime::ZERO);
+        for _ in 0..100 {
+            assert!(scheduler.burst_remaining < 3);
+            scheduler._advance();
+        }
+    }
+
+    #[test]
+    fn intervals_never_collapse() {
+        let config = _JitterConfig {
+            jitter: UnitFp::from_f64_trusted(1.0),
+            .._JitterConfig::_DEFAULT
+        };
+        let intervals = intervals(config, 10_000);
+        assert!(intervals.iter().all(|i| *i > 0.0009)); // 0.001 isn't exact at TimeFp scale.
+    }
+
+    #[test]
+    fn due_exactly_at_scheduled_time() {
+        let start = SimTime::from_secs(100);
+        let scheduler = _PollScheduler::_new(PERIOD, NO_BURSTS, SEED, start);
+        let next = scheduler._next_poll();
+        assert!(next > start);
+        assert!(!scheduler._is_due(next.saturating_sub(SimTime::from_fp(TimeFp::with_internal(1)))));
+        assert!(scheduler._is_due(next));
+    }
+
+    #[test]
+    fn seeded_schedules_are_reproducible() {
+        let config = _JitterConfig {
+            burst_probability: UnitFp::from_f64_trusted(0.05),
+            .._JitterConfig::_DEFAULT
+        };
+        assert_eq!(intervals(config, 1000), intervals(config, 1000));
+    }
+}
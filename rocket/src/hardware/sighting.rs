@@ -0,0 +1,250 @@
+//! contains the crew's star sightings (constraint 5a). On request, a crew member spends 15 minutes marking stars
+//! through the sextant, then reduces the marks to a fix of the rocket's position and attitude. It's the one source of
+//! navigation the crew, rather than an instrument, provides: there's no thread, just a job the controller asks for and
+//! later collects. Sightings are limited to 8 a day, and only one can be in progress at a time.
+//! A fix is taken from the truth at the moment the sighting completes, with the sextant's errors added, provided the
+//! crew can see enough stars past the Sun, planets and moons to mark.
+
+use std::fmt::Display;
+
+use rand::{rngs::StdRng, SeedableRng};
+use tokio::sync::watch;
+
+use agc_utils::{EulerAngles, FixedPoint, Quaternion, SimTime, SolarFp, SolarVec3D, UnitFp, Vec3D};
+
+use crate::hardware::quota::_DailyQuota;
+use crate::hardware::rocket::_Rocket;
+use crate::hardware::sensors::_random_within;
+
+pub(crate) const _SIGHTING_DURATION: SimTime = SimTime::from_secs(15 * 60);
+pub(crate) const _SIGHTINGS_PER_DAY: u8 = 8;
+const _POSITION_ERROR_BOUNDS: SolarFp = SolarFp::from_f64_trusted(500.0); // m, per axis.
+const _ATTITUDE_ERROR_BOUNDS: UnitFp = UnitFp::from_f64_trusted(1e-4); // rad, per axis; ~20 arcseconds.
+                                                                       // two stars fix an attitude; with only one, the rocket could be rolled any way about it.
+const _MIN_VISIBLE_STARS: usize = 2;
+
+// the variance of an error uniform within +/-500m: 500^2 / 3.
+pub(crate) const _SIGHTING_POSITION_VARIANCE: FixedPoint<20> =
+    FixedPoint::<20>::from_f64_trusted(83_333.0); // m^2.
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct _Fix {
+    /// Where the crew found the rocket to be, and which way it faced. Size: 64B.
+    pub(crate) position: SolarVec3D,
+    pub(crate) attitude: Quaternion,
+    pub(crate) at: SimTime, // when the marks were taken.
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum _SightingError {
+    Busy { until: SimTime },           // a sighting is already in progress.
+    QuotaExhausted { until: SimTime }, // no sightings left today; more are available from `until`.
+    Spoiled { at: SimTime }, // the marks couldn't be reduced to a fix; the sighting still counts against the quota.
+    NoStarsVisible { at: SimTime }, // too few stars could be seen past the bodies to mark; it still counts, as above.
+}
+
+impl Display for _SightingError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            _SightingError::Busy { until } => {
+                write!(f, "Crew busy with a star sighting until {until}")
+            }
+            _SightingError::QuotaExhausted { until } => {
+                write!(f, "No star sightings left today; next available at {until}")
+            }
+            _SightingError::Spoiled { at } => write!(f, "Star sighting at {at} was spoiled"),
+            _SightingError::NoStarsVisible { at } => {
+                write!(f, "Too few stars visible for the star sighting at {at}")
+            }
+        }
+    }
+}
+
+pub(crate) struct _StarSighting {
+    /// Size: 368B (mostly the rng); passed by reference.
+    quota: _DailyQuota,
+    due: Option<SimTime>, // when the sighting in progress completes.
+    truth: watch::Receiver<_Rocket>,
+    rng: StdRng, // seeded so that runs are reproducible.
+}
+
+impl _StarSighting {
+    pub(crate) fn _new(truth: watch::Receiver<_Rocket>, seed: u64) -> Self {
+        //! a crew ready to take sightings, with a full day's quota.
+        Self {
+            quota: _DailyQuota::_new(_SIGHTINGS_PER_DAY),
+            due: None,
+            truth,
+            rng: StdRng::seed_from_u64(seed),
+        }
+    }
+
+    pub(crate) fn _remaining(&self, now: SimTime) -> u8 {
+        //! sightings left today.
+        self.quota._remaining(now)
+    }
+
+    pub(crate) fn _is_ready(&self, now: SimTime) -> bool {
+        //! whether a sighting has completed and is waiting to be collected.
+        self.due.is_some_and(|due| now.has_reached(due))
+    }
+
+    pub(crate) fn _request(&mut self, now: SimTime) -> Result<SimTime, _SightingError> {
+        //! starts a sighting. Returns when its fix will be ready.
+        if let Some(until) = self.due {
+            return Err(_SightingError::Busy { until });
+        }
+        self.quota
+            ._try_use(now)
+            .map_err(|until| _SightingError::QuotaExhausted { until })?;
+        let due = now.saturating_add(_SIGHTING_DURATION); // (MR B.3) a saturated due time is a sighting never done.
+        self.due = Some(due);
+        Ok(due)
+    }
+
+    pub(crate) fn _collect(&mut self, now: SimTime) -> Option<Result<_Fix, _SightingError>> {
+        //! takes the fix from a completed sighting, freeing the crew for the next. None if none has completed.
+        let due = self.due.filter(|due| now.has_reached(*due))?;
+        self.due = None;
+        Some(self._fix(due))
+    }
+
+    fn _fix(&mut self, at: SimTime) -> Result<_Fix, _SightingError> {
+        //! the rocket's true position and attitude, corrupted by the sextant's errors.
+        let rocket = self.truth.borrow();
+        // (MR B.5) a position too far out to test the stars from can't be reduced to a fix either.
+        let visible = rocket
+            ._visible_stars()
+            .map_err(|_| _SightingError::Spoiled { at })?;
+        if visible < _MIN_VISIBLE_STARS {
+            return Err(_SightingError::NoStarsVisible { at });
+        }
+        let position_error = Vec3D(
+            _random_within(&mut self.rng, _POSITION_ERROR_BOUNDS),
+            _random_within(&mut self.rng, _POSITION_ERROR_BOUNDS),
+            _random_within(&mut self.rng, _POSITION_ERROR_BOUNDS),
+        );
+        let attitude_error = Quaternion::from_euler(EulerAngles {
+            roll: _random_within(&mut self.rng, _ATTITUDE_ERROR_BOUNDS),
+            pitch: _random_within(&mut self.rng, _ATTITUDE_ERROR_BOUNDS),
+            yaw: _random_within(&mut self.rng, _ATTITUDE_ERROR_BOUNDS),
+        });
+        // (MR B.5) a position at the edge of the representable system can't take the error; the marks are wasted.
+        let position = rocket
+            ._true_position()
+            .checked_add(&position_error)
+            .map_err(|_| _SightingError::Spoiled { at })?;
+        Ok(_Fix {
+            position,
+            attitude: rocket._true_orientation().mult(&attitude_error),
+            at,
+        })
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::float_arithmetic)] // this is test code.
+mod tests {
+    use super::*;
+    use agc_physics::planets::BODIES;
+    use agc_utils::StepVec3D;
+
+    fn crew(position: SolarVec3D) -> (_StarSighting, watch::Sender<_Rocket>) {
+        let (sender, truth) = watch::channel(_Rocket::_new(
+            position,
+            StepVec3D::new(),
+            Quaternion::IDENTITY,
+        ));
+        (_StarSighting::_new(truth, 3), sender)
+    }
+
+    #[test]
+    fn takes_fifteen_minutes() {
+        let (mut sighting, _truth) = crew(SolarVec3D::from_floats(1e9, 0.0, 0.0).unwrap());
+        let now = SimTime::from_secs(60);
+        let due = sighting._request(now).unwrap();
+        assert_eq!(due, SimTime::from_secs(960));
+        assert_eq!(
+            sighting._request(now),
+            Err(_SightingError::Busy { until: due })
+        );
+        assert!(!sighting._is_ready(SimTime::from_secs(959)));
+        assert_eq!(sighting._collect(SimTime::from_secs(959)), None);
+        assert!(sighting._is_ready(due));
+        let fix = sighting._collect(due).unwrap().unwrap();
+        assert_eq!(fix.at, due);
+        assert_eq!(sighting._collect(due), None); // collected once only.
+        assert!(sighting._request(due).is_ok());
+    }
+
+    #[test]
+    fn fix_is_close_to_truth() {
+        let truth = SolarVec3D::from_floats(1e9, -2e8, 3e7).unwrap();
+        let (mut sighting, _truth) = crew(truth);
+        for hour in 0..8 {
+            let now = SimTime::from_secs(hour * 3_600);
+            assert!(sighting._request(now).is_ok());
+            let fix = sighting
+                ._collect(now.saturating_add(_SIGHTING_DURATION))
+                .unwrap()
+                .unwrap();
+            let error = fix.position.vector_to(&truth);
+            for component in [error.0, error.1, error.2] {
+                assert!(component.to_f64().abs() <= 500.0);
+            }
+            assert!(fix.position != truth); // the sextant isn't perfect.
+            assert!(fix.attitude.angle_to(&Quaternion::IDENTITY).to_f64() < 2e-4);
+        }
+    }
+
+    #[test]
+    fn quota_is_eight_a_day() {
+        let (mut sighting, _truth) = crew(SolarVec3D::new());
+        for i in 0..i64::from(_SIGHTINGS_PER_DAY) {
+            let now = SimTime::from_secs(i * 1_000);
+            assert!(sighting._request(now).is_ok());
+            sighting._collect(now.saturating_add(_SIGHTING_DURATION));
+        }
+        let late = SimTime::from_secs(80_000);
+        assert_eq!(sighting._remaining(late), 0);
+        assert_eq!(
+            sighting._request(late),
+            Err(_SightingError::QuotaExhausted {
+                until: SimTime::from_secs(86_400)
+            })
+        );
+        assert!(sighting._request(SimTime::from_secs(86_400)).is_ok());
+    }
+
+    #[test]
+    fn no_stars_from_inside_a_body() {
+        let bodies = BODIES;
+        let earth = bodies.iter().find(|body| body.name == "Earth").unwrap();
+        let (mut sighting, _truth) = crew(earth.position);
+        assert!(sighting._request(SimTime::ZERO).is_ok());
+        assert_eq!(
+            sighting._collect(_SIGHTING_DURATION),
+            Some(Err(_SightingError::NoStarsVisible {
+                at: _SIGHTING_DURATION
+            }))
+        );
+        assert_eq!(sighting._remaining(SimTime::ZERO), _SIGHTINGS_PER_DAY - 1);
+    }
+
+    #[test]
+    fn unrepresentable_fix_is_spoiled() {
+        let spoiled = |internal| {
+            let edge = SolarFp::with_internal(internal);
+            let (mut sighting, _truth) = crew(Vec3D(edge, edge, edge));
+            assert!(sighting._request(SimTime::ZERO).is_ok());
+            let result = sighting._collect(_SIGHTING_DURATION);
+            assert_eq!(sighting._remaining(SimTime::ZERO), _SIGHTINGS_PER_DAY - 1);
+            result
+                == Some(Err(_SightingError::Spoiled {
+                    at: _SIGHTING_DURATION,
+                }))
+        };
+        // the same seed gives the same errors; whichever way they point, one edge can't take them.
+        assert!(spoiled(i64::MAX) || spoiled(i64::MIN));
+    }
+}
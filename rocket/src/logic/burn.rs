@@ -0,0 +1,381 @@
+//! contains the burn program: fires the main engine at a set time for a set duration. It's restart-protected; each
+//! phase is checkpointed as it's entered, and re-checkpointed with fresh nav each step while thrusting, so a restart
+//! mid-burn resumes thrusting and cuts off at the originally planned time rather than re-igniting or burning long.
+//! Each step while thrusting, an engine that isn't lit is commanded to full throttle, so a failed ignition is retried;
+//! a lit one is left alone, as every throttle change risks sticking the valve. Cutoff shuts it down.
+
+use agc_utils::{SimTime, UnitFp};
+
+use crate::hardware::controllers::engine::_EngineError;
+use crate::hardware::flight_controller::_FlightController;
+use crate::logic::navigation::{_NavFilter, _NavState};
+use crate::logic::restart::{_Checkpoint, _Program, _RestartError, _RestartTable};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum _BurnPhase {
+    AwaitingIgnition,
+    Thrusting,
+    Cutoff, // engine shut down; the burn is done.
+}
+
+impl _BurnPhase {
+    const fn _to_phase(self) -> u8 {
+        //! the phase number recorded in the restart table.
+        match self {
+            _BurnPhase::AwaitingIgnition => 0,
+            _BurnPhase::Thrusting => 1,
+            _BurnPhase::Cutoff => 2,
+        }
+    }
+
+    const fn _from_phase(phase: u8) -> Option<Self> {
+        //! the inverse of _to_phase; None for a phase number no burn records.
+        match phase {
+            0 => Some(_BurnPhase::AwaitingIgnition),
+            1 => Some(_BurnPhase::Thrusting),
+            2 => Some(_BurnPhase::Cutoff),
+            3..=u8::MAX => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum _BurnError {
+    Restart(_RestartError), // the burn's restart group was lost, so it can't be checkpointed.
+    Engine(_EngineError), // the engine didn't take the command; a failed ignition is retried next step.
+}
+
+impl From<_RestartError> for _BurnError {
+    fn from(value: _RestartError) -> Self {
+        _BurnError::Restart(value)
+    }
+}
+
+impl From<_EngineError> for _BurnError {
+    fn from(value: _EngineError) -> Self {
+        _BurnError::Engine(value)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct _Burn {
+    /// A burn in progress. Size: 32B.
+    group: usize, // restart group holding this burn's checkpoints.
+    ignition: SimTime,
+    duration: SimTime,
+    phase: _BurnPhase,
+}
+
+impl _Burn {
+    pub(crate) fn _start(
+        table: &mut _RestartTable,
+        ignition: SimTime,
+        duration: SimTime,
+        nav: &_NavState,
+    ) -> Result<Self, _RestartError> {
+        //! plans a burn, registering it for restart protection.
+        let phase = _BurnPhase::AwaitingIgnition;
+        let program = _Program::Burn { ignition, duration };
+        let group = table._register(program, phase._to_phase(), nav)?;
+        Ok(Self {
+            group,
+            ignition,
+            duration,
+            phase,
+        })
+    }
+
+    pub(crate) fn _resume(group: usize, checkpoint: &_Checkpoint) -> Option<Self> {
+        //! rebuilds a burn from its checkpoint after a restart. None if the checkpoint isn't a valid burn's.
+        let _Program::Burn { ignition, duration } = checkpoint.program;
+        Some(Self {
+            group,
+            ignition,
+            duration,
+            phase: _BurnPhase::_from_phase(checkpoint.phase)?,
+        })
+    }
+
+    pub(crate) fn _phase(&self) -> _BurnPhase {
+        self.phase
+    }
+
+    pub(crate) fn _cutoff_time(&self) -> SimTime {
+        self.ignition.saturating_add(self.duration) // (MR B.3) a saturated cutoff is just a burn that never ends.
+    }
+
+    pub(crate) fn _step(
+        &mut self,
+        now: SimTime,
+        nav: Option<&_NavState>,
+        table: &mut _RestartTable,
+    ) -> Result<_BurnPhase, _RestartError> {
+        //! moves the burn on to whichever phase is due, and checkpoints it, with nav if there's a solution. Returns the
+        //! phase now in. A burn first stepped after its cutoff time goes straight to Cutoff, never igniting.
+        self.phase = match self.phase {
+            _BurnPhase::AwaitingIgnition | _BurnPhase::Thrusting
+                if now.has_reached(self._cutoff_time()) =>
+            {
+                _BurnPhase::Cutoff
+            }
+            _BurnPhase::AwaitingIgnition if now.has_reached(self.ignition) => _BurnPhase::Thrusting,
+            _BurnPhase::AwaitingIgnition | _BurnPhase::Thrusting | _BurnPhase::Cutoff => self.phase,
+        };
+        match self.phase {
+            _BurnPhase::AwaitingIgnition | _BurnPhase::Thrusting => {
+                table._checkpoint(self.group, self.phase._to_phase(), nav)?;
+            }
+            _BurnPhase::Cutoff => table._release(self.group), // done; nothing to resume.
+        }
+        Ok(self.phase)
+    }
+}
+
+impl _FlightController {
+    pub(crate) fn _step_burn(&mut self) -> Option<Result<_BurnPhase, _BurnError>> {
+        //! steps the burn in progress, if there is one, and commands the engine for the phase it's in. A finished burn
+        //! is dropped. Only the checkpoint needs nav; without a solution, the burn still ignites and cuts off on time.
+        let now = self.clock.now();
+        let burn = self.burn.as_mut()?;
+        let nav = self.nav.as_ref().map(_NavFilter::_state);
+        let phase = match burn._step(now, nav, &mut self.restart_table) {
+            Ok(phase) => phase,
+            Err(error) => return Some(Err(error.into())),
+        };
+        match phase {
+            _BurnPhase::AwaitingIgnition => {}
+            _BurnPhase::Thrusting => {
+                if !self.engine._is_lit() {
+                    if let Err(error) = self.engine._throttle(UnitFp::from_int(1)) {
+                        return Some(Err(error.into()));
+                    }
+                }
+            }
+            _BurnPhase::Cutoff => {
+                self.engine._cutoff();
+                self.burn = None;
+            }
+        }
+        Some(Ok(phase))
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)] // this is test code.
+mod tests {
+    use super::*;
+    use crate::hardware::rocket::_Rocket;
+    use agc_utils::{Quaternion, SimClock, SolarVec3D, StepVec3D};
+    use tokio::sync::watch;
+
+    fn nav_at(secs: i64, x: f64) -> _NavState {
+        _NavState {
+            position: SolarVec3D::from_floats(x, 0.0, 0.0).unwrap(),
+            velocity: StepVec3D::new(),
+            attitude: Quaternion::IDENTITY,
+            at: SimTime::from_secs(secs),
+        }
+    }
+
+    #[test]
+    fn phases_follow_the_clock() {
+        let mut table = _RestartTable::default();
+        let nav = nav_at(0, 0.0);
+        let mut burn = _Burn::_start(
+            &mut table,
+            SimTime::from_secs(10),
+            SimTime::from_secs(5),
+            &nav,
+        )
+        .unwrap();
+        let mut step = |secs| {
+            burn._step(SimTime::from_secs(secs), Some(&nav), &mut table)
+                .unwrap()
+        };
+        assert_eq!(step(9), _BurnPhase::AwaitingIgnition);
+        assert_eq!(step(10), _BurnPhase::Thrusting);
+        assert_eq!(step(14), _BurnPhase::Thrusting);
+        assert_eq!(step(15), _BurnPhase::Cutoff);
+        assert_eq!(table._checkpoint_of(0), None); // released.
+    }
+
+    #[test]
+    fn phase_numbers_round_trip() {
+        for phase in [
+            _BurnPhase::AwaitingIgnition,
+            _BurnPhase::Thrusting,
+            _BurnPhase::Cutoff,
+        ] {
+            assert_eq!(_BurnPhase::_from_phase(phase._to_phase()), Some(phase));
+        }
+        assert_eq!(_BurnPhase::_from_phase(3), None);
+        assert_eq!(_BurnPhase::_from_phase(u8::MAX), None);
+    }
+
+    #[test]
+    fn resumes_mid_burn_from_table() {
+        let mut table = _RestartTable::default();
+        let nav = nav_at(0, 0.0);
+        let mut burn = _Burn::_start(
+            &mut table,
+            SimTime::from_secs(10),
+            SimTime::from_secs(5),
+            &nav,
+        )
+        .unwrap();
+        burn._step(SimTime::from_secs(12), Some(&nav), &mut table)
+            .unwrap();
+
+        // the restart loses `burn`; only the table survives.
+        let resumed = _Burn::_resume(0, table._checkpoint_of(0).unwrap()).unwrap();
+        assert_eq!(resumed, burn);
+        assert_eq!(resumed._phase(), _BurnPhase::Thrusting);
+        assert_eq!(resumed._cutoff_time(), SimTime::from_secs(15));
+    }
+
+    fn launch(clock: &SimClock) -> _FlightController {
+        let truth = _Rocket::_new(
+            SolarVec3D::from_floats(10_000.0, 0.0, 0.0).unwrap(),
+            StepVec3D::new(),
+            Quaternion::IDENTITY,
+        );
+        let (_truth_sender, truth_receiver) = watch::channel(truth);
+        _FlightController::_launch(clock.reader(), truth_receiver, 11).unwrap()
+    }
+
+    #[test]
+    fn restart_mid_burn_resumes_thrusting_and_keeps_newer_nav() {
+        let mut clock = SimClock::new();
+        let mut controller = launch(&clock);
+        let nav = *controller.nav.as_ref().unwrap()._state();
+        controller.burn = Some(
+            _Burn::_start(
+                &mut controller.restart_table,
+                SimTime::from_secs(10),
+                SimTime::from_secs(20),
+                &nav,
+            )
+            .unwrap(),
+        );
+        clock.advance(SimTime::from_secs(15));
+        controller.nav = Some(_NavFilter::_new(&nav_at(15, 20_000.0)));
+        assert_eq!(controller._step_burn(), Some(Ok(_BurnPhase::Thrusting)));
+
+        // nav moves on past the checkpoint, then the restart hits; the newer nav isn't rewound.
+        controller.nav = Some(_NavFilter::_new(&nav_at(16, 30_000.0)));
+        controller._software_restart();
+        assert_eq!(
+            controller.nav.as_ref().map(|nav| *nav._state()),
+            Some(nav_at(16, 30_000.0))
+        );
+        // but nav lost, or older than the checkpoint, is restored from it.
+        controller.nav = None;
+        controller._software_restart();
+        assert_eq!(
+            controller.nav.as_ref().map(|nav| *nav._state()),
+            Some(nav_at(15, 20_000.0))
+        );
+        let burn = controller.burn.unwrap();
+        assert_eq!(burn._phase(), _BurnPhase::Thrusting);
+        assert_eq!(burn._cutoff_time(), SimTime::from_secs(30));
+
+        // and it still cuts off on time, shutting the engine down.
+        clock.advance(SimTime::from_secs(15));
+        assert_eq!(controller._step_burn(), Some(Ok(_BurnPhase::Cutoff)));
+        assert_eq!(controller.burn, None);
+        assert!(controller.engine._step(clock.now()).is_ok());
+        clock.advance(SimTime::from_secs(5));
+        assert!(controller.engine._step(clock.now()).is_ok());
+        assert!(!controller.engine._is_lit());
+        controller._software_restart();
+        assert_eq!(controller.burn, None); // a finished burn isn't resumed.
+        controller._shutdown().unwrap();
+    }
+
+    #[test]
+    fn restart_before_ignition_still_ignites_on_time() {
+        let mut clock = SimClock::new();
+        let mut controller = launch(&clock);
+        let nav = *controller.nav.as_ref().unwrap()._state();
+        controller.burn = Some(
+            _Burn::_start(
+                &mut controller.restart_table,
+                SimTime::from_secs(10),
+                SimTime::from_secs(5),
+                &nav,
+            )
+            .unwrap(),
+        );
+        controller._software_restart();
+        assert_eq!(
+            controller.burn.map(|burn| burn._phase()),
+            Some(_BurnPhase::AwaitingIgnition)
+        );
+        assert!(!controller.engine._is_lit());
+        clock.advance(SimTime::from_secs(10));
+        assert_eq!(controller._step_burn(), Some(Ok(_BurnPhase::Thrusting)));
+        assert!(controller.engine._is_lit());
+        assert_eq!(controller.executive._restarts(), 1);
+        controller._shutdown().unwrap();
+    }
+
+    #[test]
+    fn burn_first_stepped_after_cutoff_never_ignites() {
+        let mut clock = SimClock::new();
+        let mut controller = launch(&clock);
+        let nav = *controller.nav.as_ref().unwrap()._state();
+        controller.burn = Some(
+            _Burn::_start(
+                &mut controller.restart_table,
+                SimTime::from_secs(10),
+                SimTime::from_secs(5),
+                &nav,
+            )
+            .unwrap(),
+        );
+        clock.advance(SimTime::from_secs(20));
+        assert_eq!(controller._step_burn(), Some(Ok(_BurnPhase::Cutoff)));
+        assert!(!controller.engine._is_lit());
+        assert_eq!(controller.burn, None);
+        assert_eq!(controller.restart_table._checkpoint_of(0), None);
+        controller._shutdown().unwrap();
+    }
+
+    #[test]
+    fn burn_without_nav_still_runs_on_time() {
+        let mut clock = SimClock::new();
+        let mut controller = launch(&clock);
+        let nav = *controller.nav.as_ref().unwrap()._state();
+        controller.burn = Some(
+            _Burn::_start(
+                &mut controller.restart_table,
+                SimTime::from_secs(10),
+                SimTime::from_secs(5),
+                &nav,
+            )
+            .unwrap(),
+        );
+        controller.nav = None;
+        clock.advance(SimTime::from_secs(10));
+        assert_eq!(controller._step_burn(), Some(Ok(_BurnPhase::Thrusting)));
+        assert!(controller.engine._is_lit());
+        // the phase is checkpointed, with the last nav there was.
+        let checkpoint = controller.restart_table._checkpoint_of(0).unwrap();
+        assert_eq!((checkpoint.phase, checkpoint.nav), (1, nav));
+        clock.advance(SimTime::from_secs(5));
+        assert_eq!(controller._step_burn(), Some(Ok(_BurnPhase::Cutoff)));
+        assert!(!controller.engine._is_lit());
+        controller._shutdown().unwrap();
+    }
+
+    #[test]
+    fn restart_without_programs_keeps_nav() {
+        let clock = SimClock::new();
+        let mut controller = launch(&clock);
+        let nav = controller.nav.clone();
+        controller._software_restart();
+        assert_eq!(controller.nav, nav);
+        assert_eq!(controller._step_burn(), None);
+        controller._shutdown().unwrap();
+    }
+}
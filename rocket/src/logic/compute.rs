@@ -0,0 +1,216 @@
+//! contains the compute-token budget which models the flight computer's limited CPU time (constraint 6).
+//! Tokens regenerate at a fixed rate of simulated time up to a capacity; every task the controller runs must first pay
+//! for itself. A task that can't be paid for is refused (and counted) rather than run, so running out is observable.
+
+use std::fmt::Display;
+
+use agc_utils::{CostTable, OpCounts, SimTime};
+
+// the flight computer's budget. A token is roughly one AGC instruction: the real machine managed ~40k per second,
+// and a second's worth can be banked for bursts of work.
+pub(crate) const _TOKEN_CAPACITY: u64 = 40_000;
+pub(crate) const _TOKEN_REGEN_PER_SECOND: u64 = 40_000;
+
+// sim time is a FixedPoint<20> count of seconds; regeneration is computed on its raw value to keep partial tokens.
+const _TIME_FRACTION_BITS: u32 = 20;
+const _TIME_FRACTION_MASK: u64 = (1 << _TIME_FRACTION_BITS) - 1;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct _BudgetExhausted {
+    /// A task was refused; carries what it needed and what was available at the time.
+    pub(crate) needed: u64,
+    pub(crate) available: u64,
+}
+
+impl Display for _BudgetExhausted {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Compute budget exhausted: needed {} tokens, {} available",
+            self.needed, self.available
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct _ComputeBudget {
+    /// Size: 48B.
+    tokens: u64,
+    capacity: u64,
+    regen_per_second: u64,
+    regen_remainder: u64, // fractional token carried between regenerations, in units of 2^-20 tokens.
+    last_regen: SimTime,
+    refusals: u64, // total tasks refused for lack of tokens.
+}
+
+impl _ComputeBudget {
+    pub(crate) fn _new(capacity: u64, regen_per_second: u64, now: SimTime) -> Self {
+        //! a full budget, regenerating from now.
+        Self {
+            tokens: capacity,
+            capacity,
+            regen_per_second,
+            regen_remainder: 0,
+            last_regen: now,
+            refusals: 0,
+        }
+    }
+
+    pub(crate) fn _available(&self) -> u64 {
+        self.tokens
+    }
+
+    pub(crate) fn _refusals(&self) -> u64 {
+        self.refusals
+    }
+
+    pub(crate) fn _regenerate(&mut self, now: SimTime) {
+        //! adds the tokens earned since the last regeneration, up to capacity.
+        let elapsed = now.elapsed_since(self.last_regen).as_fp().internal();
+        self.last_regen = self.last_regen.max(now);
+        // (MR A.2b) elapsed_since clamps at zero, so elapsed is non-negative.
+        let elapsed = u64::try_from(elapsed).unwrap_or(0);
+        // (MR B.3) saturation needs ~10^13 s at 1k tokens/s; a saturated gain still just fills the budget.
+        let earned = elapsed
+            .saturating_mul(self.regen_per_second)
+            .saturating_add(self.regen_remainder);
+        self.regen_remainder = earned & _TIME_FRACTION_MASK;
+        self.tokens = self
+            .tokens
+            .saturating_add(earned >> _TIME_FRACTION_BITS) // (MR B.3) capped at capacity immediately below.
+            .min(self.capacity);
+        if self.tokens == self.capacity {
+            self.regen_remainder = 0; // a full budget doesn't bank fractions either.
+        }
+    }
+
+    pub(crate) fn _charge(
+        &mut self,
+        counts: &OpCounts,
+        table: &CostTable,
+    ) -> Result<(), _BudgetExhausted> {
+        //! pays for a routine at the price of its operations, as declared or as tallied by an OpTally.
+        self._try_spend(counts.cost(table))
+    }
+
+    pub(crate) fn _settle(&mut self, reserved: &OpCounts, performed: &OpCounts, table: &CostTable) {
+        //! squares a routine charged in advance with what its OpTally counted once it ran: whatever was paid for but
+        //! not performed, because the routine stopped early, is given back, up to capacity.
+        let unused = reserved.cost(table).saturating_sub(performed.cost(table));
+        self.tokens = self.tokens.saturating_add(unused).min(self.capacity); // (MR B.3) capped immediately.
+    }
+
+    pub(crate) fn _try_spend(&mut self, cost: u64) -> Result<(), _BudgetExhausted> {
+        //! pays for a task if there are enough tokens. Otherwise nothing is spent, and the refusal is counted.
+        match self.tokens.checked_sub(cost) {
+            Some(remaining) => {
+                self.tokens = remaining;
+                Ok(())
+            }
+            None => {
+                self.refusals = self.refusals.saturating_add(1); // (MR B.3) a saturated count is still "a lot".
+                Err(_BudgetExhausted {
+                    needed: cost,
+                    available: self.tokens,
+                })
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::arithmetic_side_effects)] // this is test code.
+mod tests {
+    use super::*;
+    use agc_utils::TimeFp;
+
+    #[test]
+    fn spending_and_refusal() {
+        let mut budget = _ComputeBudget::_new(100, 10, SimTime::ZERO);
+        budget._try_spend(60).unwrap();
+        assert_eq!(budget._available(), 40);
+        assert_eq!(
+            budget._try_spend(41),
+            Err(_BudgetExhausted {
+                needed: 41,
+                available: 40
+            })
+        );
+        assert_eq!((budget._available(), budget._refusals()), (40, 1));
+        budget._try_spend(40).unwrap();
+        assert_eq!(budget._available(), 0);
+    }
+
+    #[test]
+    fn regenerates_up_to_capacity() {
+        let mut budget = _ComputeBudget::_new(100, 10, SimTime::ZERO);
+        budget._try_spend(100).unwrap();
+        budget._regenerate(SimTime::from_secs(3));
+        assert_eq!(budget._available(), 30);
+        budget._regenerate(SimTime::from_secs(1_000));
+        assert_eq!(budget._available(), 100);
+    }
+
+    #[test]
+    fn partial_tokens_are_kept() {
+        let mut budget = _ComputeBudget::_new(100, 10, SimTime::ZERO);
+        budget._try_spend(100).unwrap();
+        // 0.05s at a time; each step earns half a token.
+        let step = TimeFp::from_f64_trusted(0.05).internal();
+        for i in 1..=10 {
+            budget._regenerate(SimTime::from_fp(TimeFp::with_internal(step * i)));
+        }
+        assert!((4..=5).contains(&budget._available()));
+    }
+
+    #[test]
+    fn time_going_backwards_earns_nothing() {
+        let mut budget = _ComputeBudget::_new(100, 10, SimTime::from_secs(10));
+        budget._try_spend(100).unwrap();
+        budget._regenerate(SimTime::from_secs(5));
+        assert_eq!(budget._available(), 0);
+    }
+
+    #[test]
+    fn huge_gaps_saturate_safely() {
+        let mut budget = _ComputeBudget::_new(100, u64::MAX, SimTime::ZERO);
+        budget._try_spend(100).unwrap();
+        budget._regenerate(SimTime::from_secs(1 << 40));
+        assert_eq!(budget._available(), 100);
+    }
+
+    #[test]
+    fn charges_tallied_operations() {
+        let mut budget = _ComputeBudget::_new(100, 0, SimTime::ZERO);
+        let tally = agc_utils::OpTally::new();
+        let v = tally.counted(agc_utils::StepVec3D::from_floats(3.0, 4.0, 0.0).unwrap());
+        v.checked_magnitude().unwrap();
+        budget._charge(&tally.reset(), &CostTable::AGC).unwrap();
+        assert_eq!(budget._available(), 100 - (2 + 3 * 3 + 40));
+        v.checked_magnitude().unwrap();
+        assert!(budget._charge(&tally.reset(), &CostTable::AGC).is_err());
+    }
+
+    #[test]
+    fn settling_returns_unperformed_work() {
+        let mut budget = _ComputeBudget::_new(100, 0, SimTime::ZERO);
+        let reserved = OpCounts {
+            adds: 30,
+            muls: 10,
+            ..Default::default()
+        };
+        budget._charge(&reserved, &CostTable::AGC).unwrap();
+        assert_eq!(budget._available(), 40);
+        let performed = OpCounts {
+            adds: 30,
+            ..Default::default()
+        };
+        budget._settle(&reserved, &performed, &CostTable::AGC);
+        assert_eq!(budget._available(), 70);
+        // never beyond what was reserved, nor beyond capacity.
+        budget._settle(&performed, &reserved, &CostTable::AGC);
+        assert_eq!(budget._available(), 70);
+        budget._settle(&reserved, &OpCounts::default(), &CostTable::AGC);
+        assert_eq!(budget._available(), 100);
+    }
+}
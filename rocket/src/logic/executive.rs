@@ -0,0 +1,331 @@
+//! contains the Executive and Waitlist; the flight computer's job scheduler, modelled on the AGC's.
+//! The Executive holds a fixed table of "core sets", one per job waiting to run, and always hands out the highest
+//! priority job first. Jobs that need scratch space also take one of a smaller pool of "VAC areas". The Waitlist holds
+//! a fixed number of tasks to be scheduled as jobs at a given sim time.
+//! If any of these run out, the Executive raises the matching program alarm (1201: no VAC areas, 1202: no core sets,
+//! 1203: waitlist full) and performs a software restart, dropping all queued work, rather than panicking; as happened
+//! during Apollo 11's descent. Everything is stack-allocated (MR F.1).
+
+use std::fmt::Display;
+
+use agc_utils::SimTime;
+
+use super::main_loop::_Task;
+
+// the AGC's own limits.
+pub(crate) const _CORE_SETS: usize = 7;
+pub(crate) const _VAC_AREAS: usize = 5;
+pub(crate) const _WAITLIST_SLOTS: usize = 9;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum _ProgramAlarm {
+    NoVacAreas,   // 1201
+    NoCoreSets,   // 1202
+    WaitlistFull, // 1203
+}
+
+impl _ProgramAlarm {
+    pub(crate) const fn _code(self) -> u16 {
+        match self {
+            _ProgramAlarm::NoVacAreas => 1201,
+            _ProgramAlarm::NoCoreSets => 1202,
+            _ProgramAlarm::WaitlistFull => 1203,
+        }
+    }
+}
+
+impl Display for _ProgramAlarm {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "PROGRAM ALARM {}", self._code())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct _Job {
+    /// A unit of work waiting in the Executive. Size: 8B.
+    pub(crate) task: _Task,
+    pub(crate) priority: u8, // higher runs first.
+    pub(crate) needs_vac: bool,
+    sequence: u32, // order of arrival; breaks priority ties first-come first-served.
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct _WaitlistEntry {
+    /// A task to become a job at a given time. Size: 16B.
+    due: SimTime,
+    task: _Task,
+    priority: u8,
+    needs_vac: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct _Executive {
+    core_sets: [Option<_Job>; _CORE_SETS],
+    waitlist: [Option<_WaitlistEntry>; _WAITLIST_SLOTS],
+    next_sequence: u32,
+    last_alarm: Option<_ProgramAlarm>,
+    restarts: u32, // software restarts since power-up.
+}
+
+impl Default for _Executive {
+    fn default() -> Self {
+        Self::_new()
+    }
+}
+
+impl _Executive {
+    pub(crate) const fn _new() -> Self {
+        Self {
+            core_sets: [None; _CORE_SETS],
+            waitlist: [None; _WAITLIST_SLOTS],
+            next_sequence: 0,
+            last_alarm: None,
+            restarts: 0,
+        }
+    }
+
+    pub(crate) fn _last_alarm(&self) -> Option<_ProgramAlarm> {
+        self.last_alarm
+    }
+
+    pub(crate) fn _restarts(&self) -> u32 {
+        self.restarts
+    }
+
+    pub(crate) fn _queued(&self) -> u8 {
+        //! the number of jobs waiting.
+        // (MR B.3) at most _CORE_SETS, which fits.
+        u8::try_from(self.core_sets.iter().flatten().count()).unwrap_or(u8::MAX)
+    }
+
+    pub(crate) fn _is_queued(&self, task: _Task) -> bool {
+        //! whether a job for this task is already waiting.
+        self.core_sets.iter().flatten().any(|job| job.task == task)
+    }
+
+    pub(crate) fn _add_job(
+        &mut self,
+        task: _Task,
+        priority: u8,
+        needs_vac: bool,
+    ) -> Result<(), _ProgramAlarm> {
+        //! queues a job (the AGC's NOVAC/FINDVAC). On overflow, raises the alarm and restarts.
+        if needs_vac && self._vac_areas_in_use() >= _VAC_AREAS {
+            return Err(self._alarm(_ProgramAlarm::NoVacAreas));
+        }
+        let Some(slot) = self.core_sets.iter_mut().find(|slot| slot.is_none()) else {
+            return Err(self._alarm(_ProgramAlarm::NoCoreSets));
+        };
+        *slot = Some(_Job {
+            task,
+            priority,
+            needs_vac,
+            sequence: self.next_sequence,
+        });
+        self.next_sequence = self.next_sequence.wrapping_add(1); // (MR B.3) only compared within a 7-job window.
+        Ok(())
+    }
+
+    pub(crate) fn _add_waitlist_task(
+        &mut self,
+        due: SimTime,
+        task: _Task,
+        priority: u8,
+        needs_vac: bool,
+    ) -> Result<(), _ProgramAlarm> {
+        //! arranges for a job to be queued at the given time (the AGC's WAITLIST). On overflow, alarms and restarts.
+        let Some(slot) = self.waitlist.iter_mut().find(|slot| slot.is_none()) else {
+            return Err(self._alarm(_ProgramAlarm::WaitlistFull));
+        };
+        *slot = Some(_WaitlistEntry {
+            due,
+            task,
+            priority,
+            needs_vac,
+        });
+        Ok(())
+    }
+
+    pub(crate) fn _release_due(&mut self, now: SimTime) -> Result<(), _ProgramAlarm> {
+        //! moves every waitlist task that has come due into the job table.
+        // (MR D.3) bounded by the waitlist size.
+        for index in 0.._WAITLIST_SLOTS {
+            let due = self.waitlist.get(index).copied().flatten();
+            if let Some(entry) = due.filter(|entry| now.has_reached(entry.due)) {
+                if let Some(slot) = self.waitlist.get_mut(index) {
+                    *slot = None;
+                }
+                self._add_job(entry.task, entry.priority, entry.needs_vac)?;
+            }
+        }
+        Ok(())
+    }
+
+    pub(crate) fn _peek(&self) -> Option<_Job> {
+        //! the job that would run next: highest priority, then earliest queued.
+        self.core_sets
+            .iter()
+            .flatten()
+            .copied()
+            .min_by_key(|job| (std::cmp::Reverse(job.priority), job.sequence))
+    }
+
+    pub(crate) fn _take(&mut self) -> Option<_Job> {
+        //! removes and returns the next job, freeing its core set (and VAC area).
+        let next = self._peek()?;
+        let slot = self
+            .core_sets
+            .iter_mut()
+            .find(|slot| **slot == Some(next))?;
+        *slot = None;
+        Some(next)
+    }
+
+    fn _vac_areas_in_use(&self) -> usize {
+        self.core_sets
+            .iter()
+            .flatten()
+            .filter(|job| job.needs_vac)
+            .count()
+    }
+
+    pub(crate) fn _restart(&mut self) {
+        //! performs a software restart: all queued jobs and waitlist tasks are dropped. Programs must re-establish
+        //! their own work afterwards (see restart.rs).
+        self.core_sets = [None; _CORE_SETS];
+        self.waitlist = [None; _WAITLIST_SLOTS];
+        self.restarts = self.restarts.saturating_add(1); // (MR B.3) a saturated count is still "a lot".
+    }
+
+    fn _alarm(&mut self, alarm: _ProgramAlarm) -> _ProgramAlarm {
+        //! raises a program alarm, and restarts.
+        self.last_alarm = Some(alarm);
+        self._restart();
+        alarm
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)] // this is test code.
+mod tests {
+    use super::*;
+    use _Task::*;
+
+    #[test]
+    fn highest_priority_first_then_fifo() {
+        let mut executive = _Executive::_new();
+        executive._add_job(ProcessAltimeter, 10, false).unwrap();
+        executive._add_job(ProcessInertial, 30, false).unwrap();
+        executive._add_job(ProcessAltimeter, 30, false).unwrap();
+        let order: Vec<_> = (0..3).map(|_| executive._take().unwrap()).collect();
+        assert_eq!(
+            order
+                .iter()
+                .map(|job| (job.task, job.priority))
+                .collect::<Vec<_>>(),
+            [
+                (ProcessInertial, 30),
+                (ProcessAltimeter, 30),
+                (ProcessAltimeter, 10)
+            ]
+        );
+        assert_eq!(executive._take(), None);
+    }
+
+    #[test]
+    fn peek_does_not_remove() {
+        let mut executive = _Executive::_new();
+        executive._add_job(ProcessInertial, 5, false).unwrap();
+        assert_eq!(executive._peek(), executive._peek());
+        assert!(executive._is_queued(ProcessInertial));
+        assert!(!executive._is_queued(ProcessAltimeter));
+        assert_eq!(executive._queued(), 1);
+        executive._take().unwrap();
+        assert!(!executive._is_queued(ProcessInertial));
+        assert_eq!(executive._queued(), 0);
+    }
+
+    #[test]
+    fn core_set_overflow_is_1202_and_restarts() {
+        let mut executive = _Executive::_new();
+        for _ in 0.._CORE_SETS {
+            executive._add_job(ProcessAltimeter, 1, false).unwrap();
+        }
+        executive
+            ._add_waitlist_task(SimTime::from_secs(5), ProcessInertial, 1, false)
+            .unwrap();
+        let alarm = executive._add_job(ProcessInertial, 1, false).unwrap_err();
+        assert_eq!(alarm._code(), 1202);
+        assert_eq!(executive._last_alarm(), Some(_ProgramAlarm::NoCoreSets));
+        assert_eq!(executive._restarts(), 1);
+        assert_eq!(executive._peek(), None);
+        executive._release_due(SimTime::from_secs(5)).unwrap();
+        assert_eq!(executive._peek(), None); // the waitlist was cleared too.
+        executive._add_job(ProcessInertial, 1, false).unwrap(); // and it works again afterwards.
+    }
+
+    #[test]
+    fn vac_overflow_is_1201() {
+        let mut executive = _Executive::_new();
+        for _ in 0.._VAC_AREAS {
+            executive._add_job(ProcessAltimeter, 1, true).unwrap();
+        }
+        executive._add_job(ProcessAltimeter, 1, false).unwrap(); // core sets remain for jobs without VAC.
+        let alarm = executive._add_job(ProcessInertial, 1, true).unwrap_err();
+        assert_eq!(alarm._code(), 1201);
+        assert_eq!(executive._restarts(), 1);
+    }
+
+    #[test]
+    fn taking_a_job_frees_its_vac_area() {
+        let mut executive = _Executive::_new();
+        for _ in 0.._VAC_AREAS {
+            executive._add_job(ProcessAltimeter, 1, true).unwrap();
+        }
+        executive._take().unwrap();
+        executive._add_job(ProcessInertial, 1, true).unwrap();
+        assert_eq!(executive._restarts(), 0);
+    }
+
+    #[test]
+    fn waitlist_releases_at_due_time() {
+        let mut executive = _Executive::_new();
+        executive
+            ._add_waitlist_task(SimTime::from_secs(10), ProcessInertial, 3, false)
+            .unwrap();
+        executive._release_due(SimTime::from_secs(9)).unwrap();
+        assert_eq!(executive._peek(), None);
+        executive._release_due(SimTime::from_secs(10)).unwrap();
+        assert_eq!(executive._take().unwrap().task, ProcessInertial);
+        executive._release_due(SimTime::from_secs(11)).unwrap();
+        assert_eq!(executive._peek(), None); // released only once.
+    }
+
+    #[test]
+    fn waitlist_overflow_is_1203() {
+        let mut executive = _Executive::_new();
+        for _ in 0.._WAITLIST_SLOTS {
+            executive
+                ._add_waitlist_task(SimTime::from_secs(1), ProcessAltimeter, 1, false)
+                .unwrap();
+        }
+        let alarm = executive
+            ._add_waitlist_task(SimTime::from_secs(1), ProcessAltimeter, 1, false)
+            .unwrap_err();
+        assert_eq!(alarm._code(), 1203);
+        assert_eq!(alarm.to_string(), "PROGRAM ALARM 1203");
+    }
+
+    #[test]
+    fn releasing_into_a_full_table_alarms() {
+        let mut executive = _Executive::_new();
+        for _ in 0.._CORE_SETS {
+            executive._add_job(ProcessAltimeter, 1, false).unwrap();
+        }
+        executive
+            ._add_waitlist_task(SimTime::from_secs(1), ProcessInertial, 1, false)
+            .unwrap();
+        let alarm = executive._release_due(SimTime::from_secs(1)).unwrap_err();
+        assert_eq!(alarm, _ProgramAlarm::NoCoreSets);
+    }
+}
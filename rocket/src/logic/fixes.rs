@@ -0,0 +1,230 @@
+//! contains the controller's side of the drift corrections (constraint 5): asking for a fix, and folding it into the
+//! navigation filter once it arrives. Fixes are measured outside the filter, so unlike sensor readings they correct
+//! drift rather than accumulate it.
+
+use agc_utils::{OpTally, SimTime};
+
+use crate::hardware::flight_controller::_FlightController;
+use crate::hardware::ground::{
+    _ContactError, _GROUND_POSITION_VARIANCE, _GROUND_VELOCITY_VARIANCE,
+};
+use crate::hardware::sighting::{_SightingError, _SIGHTING_POSITION_VARIANCE};
+use crate::logic::main_loop::_TaskError;
+
+impl _FlightController {
+    pub(crate) fn _request_sighting(&mut self) -> Result<SimTime, _SightingError> {
+        //! asks the crew for a star sighting. Returns when its fix will be ready for the main loop to take in.
+        self.sighting._request(self.clock.now())
+    }
+
+    pub(crate) fn _apply_sighting(
+        &mut self,
+        now: SimTime,
+        tally: &OpTally,
+    ) -> Result<(), _TaskError> {
+        //! collects a completed sighting and corrects navigation with it. Nothing to do if none has completed.
+        let Some(fix) = self.sighting._collect(now) else {
+            return Ok(());
+        };
+        let fix = fix?;
+        // (MR B.5) with no nav solution there's nothing to correct; a sighting alone has no velocity to start one.
+        if let Some(nav) = self.nav.as_mut() {
+            nav._update_position(&fix.position, _SIGHTING_POSITION_VARIANCE, tally)?;
+            nav._realign(fix.attitude);
+        }
+        Ok(())
+    }
+
+    pub(crate) fn _request_contact(&mut self) -> Result<SimTime, _ContactError> {
+        //! signals Mission Control for a state vector. Returns when it will arrive for the main loop to take in.
+        self.ground._request(self.clock.now())
+    }
+
+    pub(crate) fn _apply_ground_update(
+        &mut self,
+        now: SimTime,
+        tally: &OpTally,
+    ) -> Result<(), _TaskError> {
+        //! collects an uplinked state vector and corrects navigation with it, and takes up the body to range against.
+        //! Nothing to do if none has arrived.
+        let Some(update) = self.ground._collect(now) else {
+            return Ok(());
+        };
+        let update = update?;
+        self.reference_body = update.reference_body;
+        // (MR B.5) as for sightings; the state vector is only ever a correction, never a fresh start.
+        if let Some(nav) = self.nav.as_mut() {
+            nav._update_state_vector(
+                (&update.position, _GROUND_POSITION_VARIANCE),
+                (&update.velocity, _GROUND_VELOCITY_VARIANCE),
+                tally,
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::float_arithmetic)] // this is test code.
+mod tests {
+    use super::*;
+    use crate::hardware::rocket::_Rocket;
+    use crate::hardware::sensors::{_SensorReading, inertial_platform::_InertialReading};
+    use crate::hardware::sighting::_SIGHTING_DURATION;
+    use crate::logic::navigation::{_NavFilter, _NavState};
+    use agc_utils::{
+        EulerAngles, Quaternion, SimClock, SolarFp, SolarVec3D, StepFp, StepVec3D, UnitFp,
+    };
+    use tokio::sync::watch;
+
+    fn along_x(metres: f64) -> SolarVec3D {
+        //! a point this far along x from a million km out from the Sun; clear of it, so the crew can see stars.
+        SolarVec3D::from_floats(1e9 + metres, 0.0, 0.0).unwrap()
+    }
+
+    fn launch(clock: &SimClock) -> _FlightController {
+        let truth = _Rocket::_new(along_x(10_000.0), StepVec3D::new(), Quaternion::IDENTITY);
+        let (_truth_sender, truth_receiver) = watch::channel(truth);
+        _FlightController::_launch(clock.reader(), truth_receiver, 11).unwrap()
+    }
+
+    fn error(controller: &_FlightController) -> SolarFp {
+        //! how far the believed position is from the truth used in launch().
+        let truth = along_x(10_000.0);
+        controller
+            .nav
+            .as_ref()
+            .unwrap()
+            ._state()
+            .position
+            .vector_to(&truth)
+            .magnitude()
+    }
+
+    #[test]
+    fn sighting_corrects_drifted_nav() {
+        let mut clock = SimClock::new();
+        let mut controller = launch(&clock);
+        // 5km adrift, facing the wrong way, and no longer sure of itself.
+        let drifted = _NavState {
+            position: along_x(15_000.0),
+            velocity: StepVec3D::new(),
+            attitude: Quaternion::from_euler(EulerAngles {
+                roll: UnitFp::from_int(0),
+                pitch: UnitFp::from_int(0),
+                yaw: UnitFp::FRAC_PI_2,
+            }),
+            at: SimTime::ZERO,
+        };
+        let mut nav = _NavFilter::_new(&drifted);
+        let coasting = _SensorReading {
+            data: _InertialReading {
+                acceleration: StepVec3D::new(),
+                angular_rate: StepVec3D::new(),
+            },
+            time: _SIGHTING_DURATION,
+        };
+        nav._propagate(&coasting, &StepVec3D::new(), &OpTally::new())
+            .unwrap();
+        controller.nav = Some(nav);
+        let before = error(&controller);
+
+        let due = controller._request_sighting().unwrap();
+        assert_eq!(due, _SIGHTING_DURATION);
+        controller
+            ._apply_sighting(clock.now(), &OpTally::new())
+            .unwrap(); // not done yet; nothing happens.
+        assert_eq!(error(&controller), before);
+        clock.advance(_SIGHTING_DURATION);
+        controller
+            ._apply_sighting(clock.now(), &OpTally::new())
+            .unwrap();
+        // the filter weighs its own ~8000m^2 against the sighting's ~83000m^2, so moves ~9% of the way.
+        let corrected = before.checked_sub(error(&controller)).unwrap();
+        assert!(corrected > SolarFp::from_int(300), "{corrected:?}");
+        let attitude = controller.nav.as_ref().unwrap()._state().attitude;
+        assert!(attitude.angle_to(&Quaternion::IDENTITY) < UnitFp::from_f64(1e-3).unwrap());
+        controller._shutdown().unwrap();
+    }
+
+    #[test]
+    fn sightings_go_through_the_main_loop() {
+        let mut clock = SimClock::new();
+        let mut controller = launch(&clock);
+        assert!(controller._request_sighting().is_ok());
+        assert_eq!(
+            controller._request_sighting(),
+            Err(_SightingError::Busy {
+                until: _SIGHTING_DURATION
+            })
+        );
+        clock.advance(_SIGHTING_DURATION);
+        controller._tick();
+        assert!(!controller.sighting._is_ready(clock.now())); // collected.
+        assert!(controller._request_sighting().is_ok());
+        controller._shutdown().unwrap();
+    }
+
+    #[test]
+    fn ground_update_corrects_position_and_velocity() {
+        let mut clock = SimClock::new();
+        let mut controller = launch(&clock);
+        // wrongly believing it's moving at 2m/s, so 5km adrift after coasting, and no longer sure of itself.
+        let drifted = _NavState {
+            position: along_x(13_200.0),
+            velocity: StepVec3D::from_floats(2.0, 0.0, 0.0).unwrap(),
+            attitude: Quaternion::IDENTITY,
+            at: SimTime::ZERO,
+        };
+        let mut nav = _NavFilter::_new(&drifted);
+        let coasting = _SensorReading {
+            data: _InertialReading {
+                acceleration: StepVec3D::new(),
+                angular_rate: StepVec3D::new(),
+            },
+            time: SimTime::from_secs(900),
+        };
+        nav._propagate(&coasting, &StepVec3D::new(), &OpTally::new())
+            .unwrap();
+        controller.nav = Some(nav);
+        let before = error(&controller);
+        // and ranging against where the body was long ago.
+        controller.reference_body = along_x(-5_000.0);
+
+        // launch() leaves the rocket by the Sun by Earth's reckoning, so the signal takes ~500s each way.
+        let due = controller._request_contact().unwrap();
+        assert!(
+            due > SimTime::from_secs(900) && due < SimTime::from_secs(1_100),
+            "{due}"
+        );
+        assert!(matches!(
+            controller._request_contact(),
+            Err(_ContactError::Busy { .. })
+        ));
+        controller
+            ._apply_ground_update(clock.now(), &OpTally::new())
+            .unwrap(); // still in flight; nothing happens.
+        assert_eq!(error(&controller), before);
+        clock.advance(due);
+        controller._tick();
+        assert!(!controller.ground._is_ready(clock.now())); // collected.
+
+        // the filter weighs its own ~8000m^2 against the ground's ~3300m^2, and corrects its velocity with it.
+        let corrected = before.checked_sub(error(&controller)).unwrap();
+        assert!(corrected > SolarFp::from_int(2_500), "{corrected:?}");
+        let velocity = controller
+            .nav
+            .as_ref()
+            .unwrap()
+            ._state()
+            .velocity
+            .magnitude();
+        assert!(velocity < StepFp::from_f64(0.5).unwrap(), "{velocity:?}");
+        assert_eq!(controller.reference_body, SolarVec3D::new());
+        assert!(matches!(
+            controller._request_contact(),
+            Err(_ContactError::QuotaExhausted { .. })
+        ));
+        controller._shutdown().unwrap();
+    }
+}
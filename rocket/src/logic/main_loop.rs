@@ -0,0 +1,678 @@
+//! contains the flight controller's main loop. Each tick regenerates the compute budget for the sim time elapsed,
+//! queues a job in the Executive for every task with fresh input, then runs jobs in priority order while they can be
+//! paid for, keeping each reading that passes validation and feeding it to the navigation filter. The first job refused
+//! for lack of tokens stops the pass: it, and everything below it, waits for a later tick rather than stalling the
+//! loop. Program alarms raised along the way are reported; after the restart they cause, protected programs are resumed
+//! from their checkpoints, and unread input is simply queued again on the next tick. Then any protected program in
+//! progress is stepped, checkpointing it with the navigation state just updated. Finally each sensor's watchdog is run,
+//! rebooting any that have gone silent. Silence is judged by the timestamps of what each sensor publishes, so a sensor
+//! whose readings are rejected, or left unread for lack of budget, is still known to be alive. Trust takes more: once
+//! failed, a sensor's readings reach navigation only after one of them has passed validation.
+
+use agc_utils::{CostTable, OpCounts, OpTally, SimTime, StepVec3D};
+
+use crate::hardware::flight_controller::{_FlightController, _SensorLink};
+use crate::hardware::ground::_ContactError;
+use crate::hardware::sensors::{_SensorReading, command::_send};
+use crate::hardware::sighting::_SightingError;
+use crate::logic::burn::{_BurnError, _BurnPhase};
+use crate::logic::executive::{_ProgramAlarm, _CORE_SETS};
+use crate::logic::navigation::{
+    _NavError, _POSITION_FIX_OPS, _PROPAGATE_OPS, _RANGE_OPS, _STATE_VECTOR_FIX_OPS,
+};
+use crate::logic::validation::{_Plausible, _Rejection};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum _Task {
+    ProcessInertial, // take in the latest IMU reading, and propagate navigation with it; dead reckoning.
+    ProcessAltimeter, // take in the latest altimeter reading, and correct navigation with it.
+    ApplySighting,   // correct navigation with a completed star sighting.
+    ApplyGroundUpdate, // correct navigation with a state vector uplinked by Mission Control.
+}
+
+impl _Task {
+    pub(crate) const fn _ops(self) -> OpCounts {
+        //! the operations this task performs when it runs in full; what it's charged before running.
+        match self {
+            _Task::ProcessInertial => _PROPAGATE_OPS,
+            _Task::ProcessAltimeter => _RANGE_OPS,
+            _Task::ApplySighting => _POSITION_FIX_OPS,
+            _Task::ApplyGroundUpdate => _STATE_VECTOR_FIX_OPS,
+        }
+    }
+
+    pub(crate) const fn _priority(self) -> u8 {
+        //! Executive priority of this task's job; dead reckoning from the IMU matters most.
+        match self {
+            _Task::ProcessInertial => 30,
+            _Task::ApplyGroundUpdate => 26, // rarer still, and the only fix of velocity.
+            _Task::ApplySighting => 25,     // rare, and worth more than any range.
+            _Task::ProcessAltimeter => 20,
+        }
+    }
+
+    pub(crate) const fn _needs_vac(self) -> bool {
+        //! whether the job needs a VAC area of scratch space, as vector work does.
+        match self {
+            _Task::ProcessInertial | _Task::ApplySighting | _Task::ApplyGroundUpdate => true,
+            _Task::ProcessAltimeter => false,
+        }
+    }
+}
+
+// every task the main loop can queue.
+const _TASKS: [_Task; 4] = [
+    _Task::ProcessInertial,
+    _Task::ProcessAltimeter,
+    _Task::ApplySighting,
+    _Task::ApplyGroundUpdate,
+];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum _TaskError {
+    Rejected(_Rejection), // the reading failed validation, so navigation never saw it.
+    Navigation(_NavError), // the reading was kept, but navigation couldn't use it; the filter is unchanged.
+    Sighting(_SightingError), // the crew's sighting produced no fix.
+    Contact(_ContactError), // Mission Control's uplink produced no state vector.
+}
+
+impl From<_Rejection> for _TaskError {
+    fn from(value: _Rejection) -> Self {
+        _TaskError::Rejected(value)
+    }
+}
+
+impl From<_SightingError> for _TaskError {
+    fn from(value: _SightingError) -> Self {
+        _TaskError::Sighting(value)
+    }
+}
+
+impl From<_ContactError> for _TaskError {
+    fn from(value: _ContactError) -> Self {
+        _TaskError::Contact(value)
+    }
+}
+
+impl From<_NavError> for _TaskError {
+    fn from(value: _NavError) -> Self {
+        _TaskError::Navigation(value)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub(crate) struct _TickReport {
+    /// What a single tick of the main loop managed to do. Size: 72B.
+    pub(crate) processed: u8, // jobs run.
+    pub(crate) refused: u8,    // jobs left waiting once the budget ran out.
+    pub(crate) rejected: u8, // readings processed but failing validation; counted per reason in each sensor's link.
+    pub(crate) nav_faults: u8, // kept readings and fixes that navigation couldn't use.
+    pub(crate) reboots: u8,  // reboot commands sent by the sensor watchdogs.
+    pub(crate) alarm: Option<_ProgramAlarm>, // the last program alarm raised this tick, if any.
+    pub(crate) burn: Option<Result<_BurnPhase, _BurnError>>, // the phase the burn in progress was stepped to.
+    pub(crate) tokens_left: u64,
+}
+
+impl _FlightController {
+    pub(crate) fn _tick(&mut self) -> _TickReport {
+        //! a single pass of the main loop.
+        let now = self.clock.now();
+        self.budget._regenerate(now);
+        let mut report = _TickReport {
+            alarm: self.executive._release_due(now).err(),
+            ..Default::default()
+        };
+        if report.alarm.is_some() {
+            self._resume_programs();
+        }
+        for task in _TASKS {
+            if self._has_fresh_input(task) && !self.executive._is_queued(task) {
+                if let Err(alarm) =
+                    self.executive
+                        ._add_job(task, task._priority(), task._needs_vac())
+                {
+                    report.alarm = Some(alarm);
+                    self._resume_programs();
+                }
+            }
+        }
+        // (MR D.3) each pass either empties a core set or stops, so this is bounded by the table size.
+        for _ in 0.._CORE_SETS {
+            let Some(job) = self.executive._peek() else {
+                break;
+            };
+            // (MR B.5) a refusal is counted in the budget and reported below; the job stays queued for a later tick.
+            if self
+                .budget
+                ._charge(&job.task._ops(), &CostTable::AGC)
+                .is_err()
+            {
+                report.refused = self.executive._queued();
+                break;
+            }
+            let _ = self.executive._take(); // (MR B.5) this is the job just peeked.
+            let tally = OpTally::new();
+            let outcome = self._run(job.task, &tally);
+            // a task that stopped early, say on a rejected reading, only pays for the operations it performed.
+            self.budget
+                ._settle(&job.task._ops(), &tally.counts(), &CostTable::AGC);
+
+            // (MR B.5) the rejection's reason is counted by the sensor's validator; here, only that it happened.
+            match outcome {
+                Ok(()) => {}
+                Err(_TaskError::Rejected(_)) => {
+                    report.rejected = report.rejected.saturating_add(1); // (MR B.3) at most _CORE_SETS.
+                }
+                Err(
+                    _TaskError::Navigation(_) | _TaskError::Sighting(_) | _TaskError::Contact(_),
+                ) => {
+                    report.nav_faults = report.nav_faults.saturating_add(1); // (MR B.3) as above.
+                }
+            }
+            report.processed = report.processed.saturating_add(1); // (MR B.3) as above.
+        }
+        report.burn = self._step_burn();
+        report.reboots = self._watch_sensors(now);
+        report.tokens_left = self.budget._available();
+        report
+    }
+
+    fn _watch_sensors(&mut self, now: SimTime) -> u8 {
+        //! runs each sensor's watchdog, sending any reboots due. Returns how many were sent.
+        u8::from(_watch(&mut self.altimeter, now))
+            .saturating_add(u8::from(_watch(&mut self.inertial_platform, now))) // (MR B.3) at most 2.
+    }
+
+    fn _has_fresh_input(&self, task: _Task) -> bool {
+        //! whether the task has something new to work on. A sensor whose thread has gone never has.
+        match task {
+            _Task::ProcessInertial => _is_fresh(&self.inertial_platform),
+            _Task::ProcessAltimeter => _is_fresh(&self.altimeter),
+            _Task::ApplySighting => self.sighting._is_ready(self.clock.now()),
+            _Task::ApplyGroundUpdate => self.ground._is_ready(self.clock.now()),
+        }
+    }
+
+    fn _run(&mut self, task: _Task, tally: &OpTally) -> Result<(), _TaskError> {
+        //! carries out a task that has already been paid for, counting its operations on the tally.
+        let now = self.clock.now();
+        match task {
+            _Task::ProcessInertial => {
+                // a failed sensor's first accepted reading only earns back its trust; navigation waits for the next.
+                let trusted = self.inertial_platform.watchdog._is_trusted();
+                let reading = _accept_latest(&mut self.inertial_platform, now, None)?;
+                // there's no onboard gravity model yet; gravity shows up as drift, for star sightings and Mission
+                // Control updates to correct.
+                if let (true, Some(nav)) = (trusted, self.nav.as_mut()) {
+                    nav._propagate(&reading, &StepVec3D::new(), tally)?;
+                }
+            }
+            _Task::ProcessAltimeter => {
+                // (MR B.5) a range navigation can't predict is only checked against physics, not against navigation.
+                let predicted = self
+                    .nav
+                    .as_ref()
+                    .and_then(|nav| nav._predicted_range(&self.reference_body, tally).ok());
+                let trusted = self.altimeter.watchdog._is_trusted(); // as for the IMU.
+                let reading = _accept_latest(&mut self.altimeter, now, predicted.as_ref())?;
+                if let (true, Some(nav)) = (trusted, self.nav.as_mut()) {
+                    nav._update_range(reading.data, &self.reference_body, tally)?;
+                }
+            }
+            _Task::ApplySighting => self._apply_sighting(now, tally)?,
+            _Task::ApplyGroundUpdate => self._apply_ground_update(now, tally)?,
+        }
+        Ok(())
+    }
+}
+
+fn _is_fresh<T>(link: &_SensorLink<T>) -> bool {
+    link.readings.has_changed().unwrap_or(false)
+}
+
+fn _accept_latest<T: _Plausible>(
+    link: &mut _SensorLink<T>,
+    now: SimTime,
+    predicted: Option<&T>,
+) -> Result<_SensorReading<T>, _Rejection> {
+    //! marks the sensor's reading as seen, and keeps it as the latest if it's plausible, vouching for the sensor to
+    //! its watchdog. Returns the kept reading.
+    let reading = *link.readings.borrow_and_update();
+    link.validator._validate(&reading, now, predicted)?;
+    link.watchdog._record_accepted(reading.time, now);
+    link.latest = Some(reading);
+    Ok(reading)
+}
+
+fn _watch<T>(link: &mut _SensorLink<T>, now: SimTime) -> bool {
+    //! checks a sensor's watchdog, and sends the reboot it asks for. Returns whether one was sent.
+    let stamped = link.readings.borrow().time; // not marked as seen; that's for the job that processes it.
+    link.watchdog._record_reading(stamped, now);
+    let Some(command) = link.watchdog._check(now) else {
+        return false;
+    };
+    // (MR B.5) a full or closed queue means the thread itself is gone; the watchdog will try again later regardless.
+    _send(&link.commands, command).is_ok()
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::arithmetic_side_effects)] // this is test code.
+mod tests {
+    use super::*;
+    use crate::hardware::flight_controller::_SensorId;
+    use crate::hardware::rocket::_Rocket;
+    use crate::hardware::sensors::command::_SensorCommand;
+    use crate::hardware::sensors::{
+        command::_command_channel,
+        harness::{_wait_for_change, _wait_for_reading},
+    };
+    use crate::logic::burn::_Burn;
+    use crate::logic::compute::{_ComputeBudget, _TOKEN_REGEN_PER_SECOND};
+    use crate::logic::validation::_Validator;
+    use crate::logic::watchdog::{_SensorHealth, _Watchdog, _WatchdogConfig};
+    use agc_utils::{Quaternion, SimClock, SolarFp, SolarVec3D, StepVec3D};
+    use tokio::sync::watch;
+
+    fn cost(task: _Task) -> u64 {
+        task._ops().cost(&CostTable::AGC)
+    }
+
+    fn launch(clock: &mut SimClock) -> (_FlightController, watch::Sender<_Rocket>) {
+        //! a controller whose sensors have both published a reading it hasn't yet seen.
+        let truth = _Rocket::_new(
+            SolarVec3D::from_floats(10_000.0, 0.0, 0.0).unwrap(),
+            StepVec3D::new(),
+            Quaternion::IDENTITY,
+        );
+        let (truth_sender, truth_receiver) = watch::channel(truth);
+        let mut controller = _FlightController::_launch(clock.reader(), truth_receiver, 7).unwrap();
+        clock.advance(SimTime::from_secs(2));
+        assert!(_wait_for_change(&mut controller.altimeter.readings));
+        assert!(_wait_for_change(&mut controller.inertial_platform.readings));
+        (controller, truth_sender)
+    }
+
+    fn quieten<T: Copy>(link: &mut _SensorLink<T>) -> watch::Sender<_SensorReading<T>> {
+        //! takes a sensor's readings from a channel the test holds instead, starting from its latest, already seen.
+        //! Its thread may still be catching up with the clock; this way nothing more arrives unless the test sends it.
+        let (sender, readings) = watch::channel(*link.readings.borrow());
+        link.readings = readings;
+        sender
+    }
+
+    #[test]
+    fn processes_fresh_readings() {
+        let mut clock = SimClock::new();
+        let (mut controller, _truth) = launch(&mut clock);
+        let report = controller._tick();
+        assert_eq!(
+            (report.processed, report.refused, report.rejected),
+            (2, 0, 0)
+        );
+        assert_eq!(
+            report.tokens_left,
+            40_000 - cost(_Task::ProcessInertial) - cost(_Task::ProcessAltimeter)
+        );
+        assert!(controller.altimeter.latest.is_some());
+        assert!(controller.inertial_platform.latest.is_some());
+        controller._shutdown().unwrap();
+    }
+
+    #[test]
+    fn readings_drive_navigation() {
+        let mut clock = SimClock::new();
+        let (mut controller, _truth) = launch(&mut clock);
+        let initial = controller.nav.clone().unwrap();
+        let report = controller._tick();
+        assert_eq!((report.rejected, report.nav_faults), (0, 0));
+        let nav = controller.nav.as_ref().unwrap();
+        // propagated to the IMU's reading, and the range agreed with where the rocket is believed to be.
+        assert_eq!(
+            Some(nav._state().at),
+            controller
+                .inertial_platform
+                .latest
+                .map(|latest| latest.time)
+        );
+        assert_ne!(nav._covariance(), initial._covariance());
+        let error = nav
+            ._state()
+            .position
+            .vector_to(&initial._state().position)
+            .magnitude();
+        assert!(error < SolarFp::from_int(10), "{error:?}");
+        controller._shutdown().unwrap();
+    }
+
+    #[test]
+    fn nothing_fresh_costs_nothing() {
+        let mut clock = SimClock::new();
+        let (mut controller, _truth) = launch(&mut clock);
+        controller._tick();
+        let altimeter = quieten(&mut controller.altimeter);
+        let _imu = quieten(&mut controller.inertial_platform);
+        let report = controller._tick();
+        assert_eq!((report.processed, report.refused), (0, 0));
+        // but the next reading is.
+        altimeter.send_modify(|reading| reading.time = clock.now());
+        let report = controller._tick();
+        assert_eq!((report.processed, report.refused), (1, 0));
+        controller._shutdown().unwrap();
+    }
+
+    #[test]
+    fn exhaustion_is_observable_and_recoverable() {
+        let mut clock = SimClock::new();
+        let (mut controller, _truth) = launch(&mut clock);
+        // enough for the IMU only, and no regeneration.
+        let imu_only = cost(_Task::ProcessInertial) + 50;
+        controller.budget = _ComputeBudget::_new(imu_only, 0, clock.now());
+        let report = controller._tick();
+        assert_eq!((report.processed, report.refused), (1, 1));
+        assert_eq!(report.tokens_left, 50);
+        assert_eq!(controller.budget._refusals(), 1);
+        assert!(controller.altimeter.latest.is_none());
+
+        // the refused reading is still waiting once tokens are available again, and nothing newer is competing.
+        let _imu = quieten(&mut controller.inertial_platform);
+        let altimeter_only = cost(_Task::ProcessAltimeter);
+        controller.budget = _ComputeBudget::_new(altimeter_only, 0, clock.now());
+        let report = controller._tick();
+        assert_eq!((report.processed, report.refused), (1, 0));
+        assert!(controller.altimeter.latest.is_some());
+        controller._shutdown().unwrap();
+    }
+
+    #[test]
+    fn program_alarm_is_reported_and_recovered_from() {
+        let mut clock = SimClock::new();
+        let (mut controller, _truth) = launch(&mut clock);
+        // a table already full of altimeter work leaves no core set for the IMU's job.
+        for _ in 0.._CORE_SETS {
+            controller
+                .executive
+                ._add_job(_Task::ProcessAltimeter, 0, false)
+                .unwrap();
+        }
+        let report = controller._tick();
+        assert_eq!(report.alarm, Some(_ProgramAlarm::NoCoreSets));
+        assert_eq!(controller.executive._restarts(), 1);
+        // the restart dropped the stale jobs, so the altimeter's, queued after it, still ran.
+        assert_eq!(report.processed, 1);
+        assert!(controller.inertial_platform.latest.is_none());
+
+        // the IMU's unread reading is simply queued again.
+        let report = controller._tick();
+        assert_eq!(
+            (report.processed, report.refused, report.alarm),
+            (1, 0, None)
+        );
+        assert!(controller.inertial_platform.latest.is_some());
+        controller._shutdown().unwrap();
+    }
+
+    #[test]
+    fn alarm_mid_burn_keeps_nav() {
+        let mut clock = SimClock::new();
+        let (mut controller, _truth) = launch(&mut clock);
+        let nav = *controller.nav.as_ref().unwrap()._state();
+        controller.burn = Some(
+            _Burn::_start(
+                &mut controller.restart_table,
+                SimTime::from_secs(1),
+                SimTime::from_secs(100),
+                &nav,
+            )
+            .unwrap(),
+        );
+        let report = controller._tick();
+        assert_eq!(report.burn, Some(Ok(_BurnPhase::Thrusting)));
+        let nav = controller.nav.clone().unwrap();
+        // stepped every tick, so the checkpoint holds the nav just updated.
+        assert_eq!(
+            controller
+                .restart_table
+                ._checkpoint_of(0)
+                .map(|checkpoint| checkpoint.nav),
+            Some(*nav._state())
+        );
+
+        // an alarm, with nothing run after it that could change nav.
+        clock.advance(SimTime::from_secs(1));
+        assert!(_wait_for_change(&mut controller.inertial_platform.readings));
+        controller.budget = _ComputeBudget::_new(0, 0, clock.now());
+        for _ in 0.._CORE_SETS {
+            controller
+                .executive
+                ._add_job(_Task::ProcessAltimeter, 0, false)
+                .unwrap();
+        }
+        let report = controller._tick();
+        assert_eq!(report.alarm, Some(_ProgramAlarm::NoCoreSets));
+        assert_eq!(report.burn, Some(Ok(_BurnPhase::Thrusting)));
+        assert_eq!(controller.nav, Some(nav)); // covariance and all.
+        controller._shutdown().unwrap();
+    }
+
+    #[test]
+    fn priorities_are_respected() {
+        assert!(_Task::ProcessInertial._priority() > _Task::ProcessAltimeter._priority());
+        assert!(_TASKS.iter().all(|task| cost(*task) > 0));
+        // a second of dead reckoning at 10Hz and ranging at 1Hz must leave room for fixes.
+        let routine = 10 * cost(_Task::ProcessInertial) + cost(_Task::ProcessAltimeter);
+        assert!(routine + cost(_Task::ApplyGroundUpdate) < _TOKEN_REGEN_PER_SECOND);
+    }
+
+    #[test]
+    fn silent_sensor_is_rebooted_and_distrusted() {
+        let mut clock = SimClock::new();
+        let (mut controller, _truth) = launch(&mut clock);
+        controller._tick();
+        assert!(controller.altimeter.watchdog._is_trusted());
+        controller
+            ._command(_SensorId::Altimeter, _SensorCommand::PowerOff)
+            .unwrap();
+        clock.advance(SimTime::from_secs(5));
+        // the IMU keeps reporting, so only the altimeter goes silent.
+        let since = clock.now().saturating_sub(SimTime::from_f64(0.15).unwrap());
+        assert!(_wait_for_reading(
+            &controller.inertial_platform.readings,
+            since
+        ));
+        let report = controller._tick();
+        assert_eq!(report.reboots, 1);
+        assert_eq!(
+            controller.altimeter.watchdog._health(),
+            _SensorHealth::Failed
+        );
+        assert!(controller.altimeter.latest.is_some());
+        assert!(!controller.altimeter.watchdog._is_trusted());
+        assert!(controller.inertial_platform.watchdog._is_trusted());
+        controller._shutdown().unwrap();
+    }
+
+    #[test]
+    fn rebooted_sensor_publishing_garbage_stays_untrusted() {
+        let mut clock = SimClock::new();
+        let (mut controller, _truth) = launch(&mut clock);
+        controller._tick();
+        let altimeter = quieten(&mut controller.altimeter);
+        // the IMU falls silent too, so navigation moves only with the altimeter, but its watchdog won't notice.
+        let _imu = quieten(&mut controller.inertial_platform);
+        controller.inertial_platform.watchdog = _Watchdog::_new(
+            SimTime::from_secs(1_000),
+            _WatchdogConfig::_DEFAULT,
+            clock.now(),
+        );
+        clock.advance(SimTime::from_secs(5));
+        assert_eq!(controller._tick().reboots, 1);
+        // back from its reboot, publishing on time but nonsense.
+        let publish = |data, now| {
+            altimeter.send_replace(_SensorReading { data, time: now });
+        };
+        let nav = controller.nav.clone();
+        for _ in 0..3 {
+            clock.advance(SimTime::from_secs(1));
+            publish(SolarFp::with_internal(i64::MIN), clock.now());
+            let report = controller._tick();
+            assert_eq!((report.rejected, report.reboots), (1, 0));
+            assert_eq!(
+                controller.altimeter.watchdog._health(),
+                _SensorHealth::Failed
+            );
+        }
+        assert_eq!(controller.nav, nav);
+        // a plausible reading restores trust, but only the one after it is navigated with.
+        let range = nav
+            .as_ref()
+            .unwrap()
+            ._predicted_range(&controller.reference_body, &OpTally::new())
+            .unwrap();
+        clock.advance(SimTime::from_secs(1));
+        publish(range, clock.now());
+        assert_eq!(controller._tick().rejected, 0);
+        assert!(controller.altimeter.watchdog._is_trusted());
+        assert_eq!(controller.nav, nav);
+        clock.advance(SimTime::from_secs(1));
+        publish(range, clock.now());
+        controller._tick();
+        assert_ne!(controller.nav, nav);
+        controller._shutdown().unwrap();
+    }
+
+    #[test]
+    fn out_of_range_altimeter_is_alive() {
+        let mut clock = SimClock::new();
+        let (mut controller, truth) = launch(&mut clock);
+        controller._tick();
+        let kept = controller.altimeter.latest;
+        truth.send_replace(_Rocket::_new(
+            SolarVec3D::from_floats(100_000.0, 0.0, 0.0).unwrap(),
+            StepVec3D::new(),
+            Quaternion::IDENTITY,
+        ));
+        clock.advance(SimTime::from_secs(10));
+        let since = clock.now().saturating_sub(SimTime::from_f64(1.5).unwrap());
+        assert!(_wait_for_reading(&controller.altimeter.readings, since));
+        controller._tick();
+        // every reading is rejected, but it keeps reporting, so it isn't rebooted.
+        assert_eq!(
+            controller
+                .altimeter
+                .validator
+                ._rejections()
+                ._count(_Rejection::OutOfRange),
+            1
+        );
+        assert_eq!(controller.altimeter.latest, kept);
+        assert_eq!(
+            controller.altimeter.watchdog._health(),
+            _SensorHealth::Healthy
+        );
+        controller._shutdown().unwrap();
+    }
+
+    #[test]
+    fn rejected_readings_pay_only_for_what_they_did() {
+        let mut clock = SimClock::new();
+        let (mut controller, _truth) = launch(&mut clock);
+        controller._tick();
+        let altimeter = quieten(&mut controller.altimeter);
+        let _imu = quieten(&mut controller.inertial_platform);
+        controller.budget = _ComputeBudget::_new(cost(_Task::ProcessAltimeter), 0, clock.now());
+        altimeter.send_replace(_SensorReading {
+            data: SolarFp::with_internal(i64::MIN),
+            time: clock.now(),
+        });
+        let report = controller._tick();
+        assert_eq!((report.processed, report.rejected), (1, 1));
+        // the range was predicted for validation, but never reached the filter.
+        let tally = OpTally::new();
+        let nav = controller.nav.as_ref().unwrap();
+        assert!(nav
+            ._predicted_range(&controller.reference_body, &tally)
+            .is_ok());
+        assert_eq!(
+            report.tokens_left,
+            cost(_Task::ProcessAltimeter) - tally.counts().cost(&CostTable::AGC)
+        );
+        controller._shutdown().unwrap();
+    }
+
+    #[test]
+    fn starved_sensors_are_not_rebooted() {
+        let mut clock = SimClock::new();
+        let (mut controller, _truth) = launch(&mut clock);
+        controller.budget = _ComputeBudget::_new(0, 0, clock.now());
+        // ten seconds of refusals, a second at a time.
+        for _ in 0..10 {
+            clock.advance(SimTime::from_secs(1));
+            let now = clock.now();
+            let since = |secs| now.saturating_sub(SimTime::from_f64(secs).unwrap());
+            assert!(_wait_for_reading(
+                &controller.altimeter.readings,
+                since(1.5)
+            ));
+            assert!(_wait_for_reading(
+                &controller.inertial_platform.readings,
+                since(0.15)
+            ));
+            let report = controller._tick();
+            assert_eq!((report.processed, report.reboots), (0, 0));
+            assert!(report.refused > 0);
+        }
+        assert_eq!(
+            controller.inertial_platform.watchdog._health(),
+            _SensorHealth::Healthy
+        );
+        assert_eq!(
+            controller.altimeter.watchdog._health(),
+            _SensorHealth::Healthy
+        );
+        controller._shutdown().unwrap();
+    }
+
+    #[test]
+    fn implausible_readings_are_not_kept() {
+        let reading = |internal, secs| _SensorReading {
+            data: SolarFp::with_internal(internal),
+            time: SimTime::from_secs(secs),
+        };
+        let (sender, readings) = watch::channel(reading(640_000, 1)); // 10km.
+        let (commands, _receiver) = _command_channel();
+        let mut link = _SensorLink {
+            readings,
+            commands,
+            validator: _Validator::default(),
+            watchdog: _Watchdog::_new(
+                SimTime::from_secs(1),
+                _WatchdogConfig::_DEFAULT,
+                SimTime::ZERO,
+            ),
+            latest: None,
+        };
+        _accept_latest(&mut link, SimTime::from_secs(1), None).unwrap();
+        sender.send_replace(reading(i64::MIN, 2));
+        assert_eq!(
+            _accept_latest(&mut link, SimTime::from_secs(2), None),
+            Err(_Rejection::OutOfRange)
+        );
+        // 10km against a prediction of 20km: plausible in itself, but not where navigation thinks the rocket is.
+        sender.send_replace(reading(640_000, 3));
+        let predicted = SolarFp::from_int(20_000);
+        assert_eq!(
+            _accept_latest(&mut link, SimTime::from_secs(3), Some(&predicted)),
+            Err(_Rejection::Inconsistent)
+        );
+        assert_eq!(
+            link.latest.map(|latest| latest.time),
+            Some(SimTime::from_secs(1))
+        );
+        assert!(!_is_fresh(&link)); // seen, even though rejected.
+        assert_eq!(
+            link.validator._rejections()._count(_Rejection::OutOfRange),
+            1
+        );
+        assert_eq!(link.validator._rejections()._total(), 2);
+    }
+}
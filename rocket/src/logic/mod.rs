@@ -1 +1,9 @@
-
+pub mod burn; // restart-protected engine burn program
+pub mod compute; // compute-token budget
+pub mod executive; // AGC-style job scheduler
+pub mod fixes; // star sightings folded into navigation
+pub mod main_loop;
+pub mod navigation; // the navigation filter; the controller's believed state
+pub mod restart; // restart groups and checkpointing
+pub mod validation; // sensor reading plausibility checks
+pub mod watchdog; // hung sensor detection
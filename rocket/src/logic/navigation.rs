@@ -0,0 +1,688 @@
+//! contains the navigation filter: the controller's believed state, kept separately from the true state in _Rocket.
+//! An extended Kalman filter over position and velocity, with attitude carried alongside:
+//!   - propagation: each IMU reading turns the body-frame specific force into a reference-frame acceleration through
+//!     the believed attitude, then integrates it; gyro rates integrate the attitude itself. Uncertainty grows.
+//!   - update: each altimeter range to a known body centre corrects position (and, through their correlation,
+//!     velocity) by the Kalman gain. Uncertainty shrinks along the line of sight only.
+//!   - fixes: a position measured outside the filter, by a star sighting or Mission Control, is taken as three
+//!     independent measurements, one per axis; so is a velocity. A measured attitude simply replaces the integrated one.
+//!
+//! Everything is fixed point on the stack. The covariance is a 6x6 FixedPoint<20> (m^2, m^2/s, m^2/s^2): wide enough
+//! for kilometre-scale position variance, fine enough for mm/s velocity. Each step is computed into temporaries and
+//! only committed once every operation has succeeded, so an arithmetic failure leaves the filter as it was.
+
+use std::fmt::Display;
+
+use agc_utils::{
+    ArithmeticError, FixedPoint, Matrix, MatrixError, OpCounts, OpTally, Quaternion,
+    QuaternionError, SimTime, SolarFp, SolarVec3D, StepFp, StepVec3D, UnitFp, Vec3D,
+};
+
+use crate::hardware::sensors::{_SensorReading, inertial_platform::_InertialReading};
+
+pub(crate) type _CovarianceFp = FixedPoint<20>;
+pub(crate) type _Covariance = Matrix<6, 6, 20>;
+type _Sensitivity = Matrix<1, 6, 20>; // H, for a scalar measurement.
+
+// initial uncertainty; the ground-loaded state vector is good to ~10m and ~0.1m/s.
+const _INITIAL_POSITION_VARIANCE: _CovarianceFp = _CovarianceFp::from_f64_trusted(100.0); // m^2.
+const _INITIAL_VELOCITY_VARIANCE: _CovarianceFp = _CovarianceFp::from_f64_trusted(0.01); // m^2/s^2.
+
+// growth of uncertainty per second of propagation: accelerometer bias and noise feed velocity, unmodelled forces
+// (and the missing gravity model) feed both.
+const _POSITION_PROCESS_NOISE: _CovarianceFp = _CovarianceFp::from_f64_trusted(0.01); // m^2/s.
+const _VELOCITY_PROCESS_NOISE: _CovarianceFp = _CovarianceFp::from_f64_trusted(1e-4); // m^2/s^3.
+
+// a Variant altimeter is out by up to 0.5% of range; at its 40km limit, that's 200m.
+const _RANGE_VARIANCE: _CovarianceFp = _CovarianceFp::from_f64_trusted(40_000.0); // m^2.
+
+// the operations each routine below performs, which the main loop reserves before running it. Each routine does its
+// arithmetic on values counted by the tally it's given, and the tests hold these to what the tally counts.
+//
+// propagation: the 6x6 F P F^T dominates at 432 muls and 360 adds; rotating the specific force takes 36 muls, and
+// integrating the attitude 37 muls, 14 divs and 6 roots (two unit vectors, a magnitude, sin/cos, two normalisations).
+pub(crate) const _PROPAGATE_OPS: OpCounts = OpCounts {
+    adds: 453,
+    muls: 521,
+    divs: 14,
+    magnitudes: 6,
+};
+
+// one scalar update: P H^T (36 muls), the gain (6 divs), and P - K S K^T (72 muls, 36 adds).
+const _SCALAR_UPDATE_ADDS: u64 = 81;
+const _SCALAR_UPDATE_MULS: u64 = 120;
+const _SCALAR_UPDATE_DIVS: u64 = 6;
+
+// a range: predicting it for validation, then the line of sight's direction and length, and a scalar update.
+pub(crate) const _RANGE_OPS: OpCounts = OpCounts {
+    adds: _SCALAR_UPDATE_ADDS.saturating_add(13),
+    muls: _SCALAR_UPDATE_MULS.saturating_add(9),
+    divs: _SCALAR_UPDATE_DIVS.saturating_add(3),
+    magnitudes: 3,
+};
+
+// a fix: each axis is an innovation and a scalar update; a sighting measures three, Mission Control six.
+pub(crate) const _POSITION_FIX_OPS: OpCounts = _axis_updates(3);
+pub(crate) const _STATE_VECTOR_FIX_OPS: OpCounts = _axis_updates(6);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct _NavState {
+    /// The controller's best estimate of where the rocket is, how it's moving and which way it faces. Size: 88B.
+    pub(crate) position: SolarVec3D,
+    pub(crate) velocity: StepVec3D,
+    pub(crate) attitude: Quaternion, // body to reference frame.
+    pub(crate) at: SimTime,          // when this estimate applies.
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum _NavError {
+    Arithmetic(ArithmeticError),
+    Matrix(MatrixError),
+    Attitude(QuaternionError),
+    DegenerateGeometry, // the range was to a body centre the filter believes it's sitting on; no direction to correct.
+}
+
+impl From<ArithmeticError> for _NavError {
+    fn from(value: ArithmeticError) -> Self {
+        _NavError::Arithmetic(value)
+    }
+}
+
+impl From<MatrixError> for _NavError {
+    fn from(value: MatrixError) -> Self {
+        _NavError::Matrix(value)
+    }
+}
+
+impl From<QuaternionError> for _NavError {
+    fn from(value: QuaternionError) -> Self {
+        _NavError::Attitude(value)
+    }
+}
+
+impl Display for _NavError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            _NavError::Arithmetic(error) => write!(f, "Navigation arithmetic failed: {error:?}"),
+            _NavError::Matrix(error) => write!(f, "Navigation covariance failed: {error:?}"),
+            _NavError::Attitude(error) => write!(f, "Navigation attitude failed: {error:?}"),
+            _NavError::DegenerateGeometry => {
+                write!(f, "Navigation range update has no line of sight")
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct _NavFilter {
+    /// Size: 400B; passed by reference.
+    state: _NavState,
+    position_fraction: StepVec3D, // sub-SolarFp displacement carried between steps, so slow motion isn't lost.
+    covariance: _Covariance,
+}
+
+impl _NavFilter {
+    pub(crate) fn _new(state: &_NavState) -> Self {
+        //! a filter starting from a known state, at the initial uncertainty.
+        let mut covariance = _Covariance::zero();
+        for (i, row) in covariance.0.iter_mut().enumerate() {
+            if let Some(diagonal) = row.get_mut(i) {
+                *diagonal = if i < 3 {
+                    _INITIAL_POSITION_VARIANCE
+                } else {
+                    _INITIAL_VELOCITY_VARIANCE
+                };
+            }
+        }
+        Self {
+            state: *state,
+            position_fraction: StepVec3D::new(),
+            covariance,
+        }
+    }
+
+    pub(crate) fn _state(&self) -> &_NavState {
+        &self.state
+    }
+
+    pub(crate) fn _covariance(&self) -> &_Covariance {
+        &self.covariance
+    }
+
+    pub(crate) fn _position_variance(&self) -> _CovarianceFp {
+        //! total position variance (the trace of its block); a single measure of how lost the filter is.
+        (0..3).filter_map(|i| self.covariance.get(i, i)).fold(
+            _CovarianceFp::with_internal(0),
+            |sum, variance| {
+                sum.saturating_add(variance) // (MR B.3) a saturated variance is still "very lost".
+            },
+        )
+    }
+
+    pub(crate) fn _propagate(
+        &mut self,
+        reading: &_SensorReading<_InertialReading>,
+        gravity: &StepVec3D,
+        tally: &OpTally,
+    ) -> Result<(), _NavError> {
+        //! advances the believed state to the reading's time. Gravity, in the reference frame, is the caller's.
+        let elapsed = reading.time.elapsed_since(self.state.at);
+        if elapsed == SimTime::ZERO {
+            return Ok(()); // a repeated or out-of-order reading; nothing to integrate.
+        }
+        let dt: StepFp = elapsed.as_fp().convert()?;
+        let acceleration = tally
+            .counted(self.state.attitude)
+            .rotate(&reading.data.acceleration)?
+            .checked_add(gravity)?;
+        let half_dt_squared = tally.counted(dt).checked_mul(dt)?.value().rshift(1);
+        let displacement = tally
+            .counted(self.state.velocity)
+            .checked_scale(dt)?
+            .checked_add(&acceleration.checked_scale(half_dt_squared)?.value())?
+            .checked_add(&self.position_fraction)?;
+        let (whole, fraction) = _split_displacement(&displacement.value())?;
+        let position = tally.counted(self.state.position).checked_add(&whole)?;
+        let velocity = tally
+            .counted(self.state.velocity)
+            .checked_add(&acceleration.checked_scale(dt)?.value())?;
+        let attitude =
+            _integrate_attitude(&self.state.attitude, &reading.data.angular_rate, dt, tally)?;
+        let covariance = self._propagated_covariance(elapsed, tally)?;
+
+        self.state = _NavState {
+            position: position.value(),
+            velocity: velocity.value(),
+            attitude,
+            at: reading.time,
+        };
+        self.position_fraction = fraction;
+        self.covariance = covariance;
+        Ok(())
+    }
+
+    pub(crate) fn _predicted_range(
+        &self,
+        body: &SolarVec3D,
+        tally: &OpTally,
+    ) -> Result<SolarFp, _NavError> {
+        //! the distance to a body centre the altimeter should read, if the believed state is right.
+        let range = tally
+            .counted(*body)
+            .checked_sub(&self.state.position)?
+            .checked_magnitude()?;
+        Ok(range.value())
+    }
+
+    pub(crate) fn _update_range(
+        &mut self,
+        range: SolarFp,
+        body: &SolarVec3D,
+        tally: &OpTally,
+    ) -> Result<(), _NavError> {
+        //! corrects the believed state with a measured range to a body centre.
+        let line_of_sight = tally.counted(self.state.position).checked_sub(body)?;
+        let direction = line_of_sight
+            .to_unit_vector()
+            .map_err(|_| _NavError::DegenerateGeometry)?
+            .value();
+        // H: the range's sensitivity to each state; the line of sight for position, nothing for velocity.
+        let mut sensitivity = _Sensitivity::zero();
+        for (h, component) in
+            sensitivity
+                .0
+                .iter_mut()
+                .flatten()
+                .zip([direction.0, direction.1, direction.2])
+        {
+            *h = component.convert()?;
+        }
+        let innovation = tally
+            .counted(range)
+            .checked_sub(line_of_sight.checked_magnitude()?.value())?
+            .value()
+            .convert()?;
+        *self = self._scalar_update(&sensitivity, innovation, _RANGE_VARIANCE, tally)?;
+        Ok(())
+    }
+
+    pub(crate) fn _update_position(
+        &mut self,
+        measured: &SolarVec3D,
+        variance: _CovarianceFp,
+        tally: &OpTally,
+    ) -> Result<(), _NavError> {
+        //! corrects the believed state with a measured position, equally uncertain along each axis.
+        *self = self._updated_axes(0, (measured, variance), |state| state.position, tally)?;
+        Ok(())
+    }
+
+    pub(crate) fn _update_state_vector(
+        &mut self,
+        position: (&SolarVec3D, _CovarianceFp),
+        velocity: (&StepVec3D, _CovarianceFp),
+        tally: &OpTally,
+    ) -> Result<(), _NavError> {
+        //! corrects the believed state with a measured position and velocity, each with its per-axis variance.
+        let updated = self._updated_axes(0, position, |state| state.position, tally)?;
+        *self = updated._updated_axes(3, velocity, |state| state.velocity, tally)?;
+        Ok(())
+    }
+
+    pub(crate) fn _realign(&mut self, attitude: Quaternion) {
+        //! replaces the believed attitude with a measured one. The filter keeps no attitude uncertainty to weigh the
+        //! two by, and a measured attitude is far better than an integrated one.
+        self.state.attitude = attitude;
+    }
+
+    fn _updated_axes<const N: u8>(
+        &self,
+        first: usize,
+        (measured, variance): (&Vec3D<N>, _CovarianceFp),
+        believed: fn(&_NavState) -> Vec3D<N>,
+        tally: &OpTally,
+    ) -> Result<Self, _NavError> {
+        //! the filter after measuring three consecutive states directly, starting at index `first`. Returns it.
+        let mut updated = self.clone();
+        // (MR D.3) one update per axis; with independent axes, the same as a single 3D update, without an inverse.
+        for axis in 0..3 {
+            let mut sensitivity = _Sensitivity::zero();
+            if let Some(h) = sensitivity
+                .0
+                .iter_mut()
+                .flatten()
+                .nth(first.saturating_add(axis))
+            {
+                *h = _CovarianceFp::from_int(1); // (MR B.3) first is 0 or 3; can't saturate.
+            }
+            let (Some(believed), Some(measured)) = (
+                _component(&believed(&updated.state), axis),
+                _component(measured, axis),
+            ) else {
+                continue;
+            };
+            let innovation = tally
+                .counted(measured)
+                .checked_sub(believed)?
+                .value()
+                .convert()?;
+            updated = updated._scalar_update(&sensitivity, innovation, variance, tally)?;
+        }
+        Ok(updated)
+    }
+
+    fn _scalar_update(
+        &self,
+        sensitivity: &_Sensitivity,
+        innovation: _CovarianceFp,
+        variance: _CovarianceFp,
+        tally: &OpTally,
+    ) -> Result<Self, _NavError> {
+        //! the Kalman update for one scalar measurement, given its sensitivity to the state (H), how far it was from
+        //! prediction, and its variance. Returns the updated filter.
+        let p_ht = tally
+            .counted(self.covariance)
+            .checked_mul(&sensitivity.transpose())?
+            .value();
+        let predicted_variance = tally
+            .counted(*sensitivity)
+            .checked_mul(&p_ht)?
+            .value()
+            .get(0, 0)
+            .unwrap_or(_CovarianceFp::with_internal(0));
+        let innovation_variance = tally
+            .counted(predicted_variance)
+            .checked_add(variance)?
+            .value();
+        // divide each element rather than scaling by 1/S, which at 20 bits would lose most of its precision.
+        let mut gain = p_ht;
+        for k in gain.0.iter_mut().flatten() {
+            // (MR B.4) errors, rather than panics, on a zero S.
+            *k = tally.counted(*k).checked_div(innovation_variance)?.value();
+        }
+        let correction = tally.counted(gain).checked_scale(innovation)?.value();
+        let column = |i| {
+            correction
+                .get(i, 0)
+                .unwrap_or(_CovarianceFp::with_internal(0))
+        };
+        // the position correction goes through the carried fraction, so corrections finer than SolarFp still count.
+        let displacement = tally.counted(self.position_fraction).checked_add(&Vec3D(
+            column(0).convert()?,
+            column(1).convert()?,
+            column(2).convert()?,
+        ))?;
+        let (whole, position_fraction) = _split_displacement(&displacement.value())?;
+        let velocity = tally.counted(self.state.velocity).checked_add(&Vec3D(
+            column(3).convert()?,
+            column(4).convert()?,
+            column(5).convert()?,
+        ))?;
+        let position = tally.counted(self.state.position).checked_add(&whole)?;
+        // P - K S K^T; equal to (I - KH)P, but symmetric by construction.
+        let gain_spread = tally
+            .counted(gain)
+            .checked_mul(&gain.transpose())?
+            .checked_scale(innovation_variance)?;
+        let covariance = tally
+            .counted(self.covariance)
+            .checked_sub(&gain_spread.value())?;
+        Ok(Self {
+            state: _NavState {
+                position: position.value(),
+                velocity: velocity.value(),
+                ..self.state
+            },
+            position_fraction,
+            covariance: covariance.value(),
+        })
+    }
+
+    fn _propagated_covariance(
+        &self,
+        elapsed: SimTime,
+        tally: &OpTally,
+    ) -> Result<_Covariance, _NavError> {
+        //! F P F^T + Q for a constant-acceleration step of the given length.
+        let dt: _CovarianceFp = elapsed.as_fp().convert()?;
+        let mut transition = _Covariance::identity();
+        let mut noise = _Covariance::zero();
+        for (i, (transition_row, noise_row)) in
+            transition.0.iter_mut().zip(noise.0.iter_mut()).enumerate()
+        {
+            if i < 3 {
+                // position picks up velocity * dt.
+                if let Some(coupling) = transition_row.get_mut(i.saturating_add(3)) {
+                    *coupling = dt;
+                }
+            }
+            if let Some(diagonal) = noise_row.get_mut(i) {
+                let density = if i < 3 {
+                    _POSITION_PROCESS_NOISE
+                } else {
+                    _VELOCITY_PROCESS_NOISE
+                };
+                *diagonal = tally.counted(density).checked_mul(dt)?.value();
+            }
+        }
+        let propagated = tally
+            .counted(transition)
+            .checked_mul(&self.covariance)?
+            .checked_mul(&transition.transpose())?
+            .checked_add(&noise)?;
+        Ok(propagated.value())
+    }
+}
+
+const fn _axis_updates(axes: u64) -> OpCounts {
+    //! the operations of measuring this many states directly, one scalar update each.
+    OpCounts {
+        adds: _SCALAR_UPDATE_ADDS.saturating_add(1).saturating_mul(axes),
+        muls: _SCALAR_UPDATE_MULS.saturating_mul(axes),
+        divs: _SCALAR_UPDATE_DIVS.saturating_mul(axes),
+        magnitudes: 0,
+    }
+}
+
+fn _component<const N: u8>(vector: &Vec3D<N>, axis: usize) -> Option<FixedPoint<N>> {
+    //! a vector's x, y or z by index.
+    [vector.0, vector.1, vector.2].get(axis).copied()
+}
+
+fn _split_displacement(
+    displacement: &StepVec3D,
+) -> Result<(SolarVec3D, StepVec3D), ArithmeticError> {
+    //! splits a displacement into the part a SolarVec3D can hold, and the sub-resolution rest.
+    let (x, x_rest) = displacement.0.convert_with_remainder()?;
+    let (y, y_rest) = displacement.1.convert_with_remainder()?;
+    let (z, z_rest) = displacement.2.convert_with_remainder()?;
+    Ok((Vec3D(x, y, z), Vec3D(x_rest, y_rest, z_rest)))
+}
+
+fn _integrate_attitude(
+    attitude: &Quaternion,
+    angular_rate: &StepVec3D,
+    dt: StepFp,
+    tally: &OpTally,
+) -> Result<Quaternion, _NavError> {
+    //! turns the attitude by a body-frame rate held for dt.
+    let rate = tally.counted(*angular_rate);
+    let Ok(axis) = rate.to_unit_vector() else {
+        return Ok(*attitude); // not rotating.
+    };
+    let angle: UnitFp = rate
+        .checked_magnitude()?
+        .checked_mul(dt)?
+        .value()
+        .convert()?;
+    let turn = axis.rotation_about(angle)?;
+    let turned = tally.counted(*attitude).mult(&turn.value()).normalised()?;
+    Ok(turned.value())
+}
+
+#[cfg(test)]
+#[allow(
+    clippy::unwrap_used,
+    clippy::float_arithmetic,
+    clippy::arithmetic_side_effects
+)] // this is test code.
+mod tests {
+    use super::*;
+    use agc_utils::{CostTable, EulerAngles};
+
+    fn state(x: f64, vx: f64) -> _NavState {
+        _NavState {
+            position: SolarVec3D::from_floats(x, 0.0, 0.0).unwrap(),
+            velocity: StepVec3D::from_floats(vx, 0.0, 0.0).unwrap(),
+            attitude: Quaternion::IDENTITY,
+            at: SimTime::ZERO,
+        }
+    }
+
+    fn imu(
+        acceleration: (f64, f64, f64),
+        rate_z: f64,
+        secs: f64,
+    ) -> _SensorReading<_InertialReading> {
+        _SensorReading {
+            data: _InertialReading {
+                acceleration: StepVec3D::from_floats(
+                    acceleration.0,
+                    acceleration.1,
+                    acceleration.2,
+                )
+                .unwrap(),
+                angular_rate: StepVec3D::from_floats(0.0, 0.0, rate_z).unwrap(),
+            },
+            time: SimTime::from_f64(secs).unwrap(),
+        }
+    }
+
+    fn close(a: f64, b: f64, tolerance: f64) -> bool {
+        (a - b).abs() < tolerance
+    }
+
+    #[test]
+    fn coasts_at_constant_velocity() {
+        let mut filter = _NavFilter::_new(&state(0.0, 3.0));
+        for step in 1..=100 {
+            let reading = imu((0.0, 0.0, 0.0), 0.0, f64::from(step) * 0.1);
+            filter
+                ._propagate(&reading, &StepVec3D::new(), &OpTally::new())
+                .unwrap();
+        }
+        // 30m in 10s; the carried fraction stops the 1/64m position grid eating the slow motion.
+        assert!(close(filter._state().position.0.to_f64(), 30.0, 0.05));
+        assert_eq!(filter._state().at, SimTime::from_secs(10));
+    }
+
+    #[test]
+    fn integrates_acceleration_through_attitude() {
+        // facing a quarter turn left, body +x thrust pushes along reference +y.
+        let mut initial = state(0.0, 0.0);
+        initial.attitude = Quaternion::from_euler(EulerAngles {
+            roll: UnitFp::from_int(0),
+            pitch: UnitFp::from_int(0),
+            yaw: UnitFp::FRAC_PI_2,
+        });
+        let mut filter = _NavFilter::_new(&initial);
+        filter
+            ._propagate(
+                &imu((2.0, 0.0, 0.0), 0.0, 1.0),
+                &StepVec3D::new(),
+                &OpTally::new(),
+            )
+            .unwrap();
+        let velocity = filter._state().velocity;
+        assert!(close(velocity.1.to_f64(), 2.0, 1e-6));
+        assert!(close(velocity.0.to_f64(), 0.0, 1e-6));
+        assert!(close(filter._state().position.1.to_f64(), 1.0, 0.05));
+        // gravity is added in the reference frame, as given.
+        let gravity = StepVec3D::from_floats(-1.0, 0.0, 0.0).unwrap();
+        filter
+            ._propagate(&imu((0.0, 0.0, 0.0), 0.0, 2.0), &gravity, &OpTally::new())
+            .unwrap();
+        assert!(close(filter._state().velocity.0.to_f64(), -1.0, 1e-6));
+    }
+
+    #[test]
+    fn gyro_rates_turn_the_attitude() {
+        let mut filter = _NavFilter::_new(&state(0.0, 0.0));
+        for step in 1..=10 {
+            let reading = imu((0.0, 0.0, 0.0), 0.1, f64::from(step) * 0.1);
+            filter
+                ._propagate(&reading, &StepVec3D::new(), &OpTally::new())
+                .unwrap();
+        }
+        let yaw = filter._state().attitude.to_euler().yaw.to_f64();
+        assert!(close(yaw, 0.1, 1e-6));
+    }
+
+    #[test]
+    fn uncertainty_grows_then_shrinks_with_a_range() {
+        let body = SolarVec3D::new();
+        let mut filter = _NavFilter::_new(&state(10_000.0, 0.0));
+        let initial = filter._position_variance();
+        for step in 1..=100 {
+            let reading = imu((0.0, 0.0, 0.0), 0.0, f64::from(step) * 0.1);
+            filter
+                ._propagate(&reading, &StepVec3D::new(), &OpTally::new())
+                .unwrap();
+        }
+        let grown = filter._position_variance();
+        assert!(grown > initial);
+        filter
+            ._update_range(SolarFp::from_int(10_000), &body, &OpTally::new())
+            .unwrap();
+        assert!(filter._position_variance() < grown);
+        // the update keeps the covariance symmetric.
+        let covariance = filter._covariance();
+        assert_eq!(covariance.get(0, 3), covariance.get(3, 0));
+    }
+
+    #[test]
+    fn range_pulls_position_along_line_of_sight() {
+        let body = SolarVec3D::new();
+        let mut filter = _NavFilter::_new(&state(10_000.0, 0.0));
+        assert_eq!(
+            filter._predicted_range(&body, &OpTally::new()).unwrap(),
+            SolarFp::from_int(10_000)
+        );
+        // measured 30m further out: the filter moves out by its share of the difference, 100 / (100 + 40000).
+        filter
+            ._update_range(SolarFp::from_int(10_030), &body, &OpTally::new())
+            .unwrap();
+        // to within SolarFp's 1/64m; the rest is carried in the position fraction.
+        let moved = filter._state().position.0.to_f64() - 10_000.0;
+        assert!(close(moved, 30.0 * 100.0 / 40_100.0, 1.0 / 64.0), "{moved}");
+        assert!(close(filter._state().position.1.to_f64(), 0.0, 1e-9));
+    }
+
+    #[test]
+    fn position_fix_corrects_every_axis() {
+        let mut filter = _NavFilter::_new(&state(10_000.0, 0.0));
+        let measured = SolarVec3D::from_floats(10_100.0, -100.0, 50.0).unwrap();
+        // as trustworthy as the filter: it should meet the measurement halfway.
+        filter
+            ._update_position(&measured, _INITIAL_POSITION_VARIANCE, &OpTally::new())
+            .unwrap();
+        let position = filter._state().position;
+        assert!(close(position.0.to_f64(), 10_050.0, 0.05));
+        assert!(close(position.1.to_f64(), -50.0, 0.05));
+        assert!(close(position.2.to_f64(), 25.0, 0.05));
+        assert!(close(filter._position_variance().to_f64(), 150.0, 0.01));
+        let turned = Quaternion::from_euler(EulerAngles {
+            roll: UnitFp::from_int(0),
+            pitch: UnitFp::from_int(0),
+            yaw: UnitFp::FRAC_PI_2,
+        });
+        filter._realign(turned);
+        assert_eq!(filter._state().attitude, turned);
+    }
+
+    #[test]
+    fn repeated_readings_and_failures_leave_state_alone() {
+        let mut filter = _NavFilter::_new(&state(0.0, 1.0));
+        let before = filter.clone();
+        filter
+            ._propagate(
+                &imu((0.0, 0.0, 0.0), 0.0, 0.0),
+                &StepVec3D::new(),
+                &OpTally::new(),
+            )
+            .unwrap();
+        assert_eq!(filter, before);
+        assert_eq!(
+            filter._update_range(SolarFp::from_int(5), &SolarVec3D::new(), &OpTally::new()),
+            Err(_NavError::DegenerateGeometry)
+        );
+        let unreachable = SolarVec3D::from_floats(1e12, 0.0, 0.0).unwrap();
+        assert!(filter
+            ._update_position(&unreachable, _CovarianceFp::from_int(1), &OpTally::new())
+            .is_err());
+        assert_eq!(filter, before);
+        let huge = Vec3D(
+            StepFp::with_internal(i64::MAX),
+            StepFp::from_int(0),
+            StepFp::from_int(0),
+        );
+        assert!(filter
+            ._propagate(&imu((0.0, 0.0, 0.0), 0.0, 1.0), &huge, &OpTally::new())
+            .is_err());
+        assert_eq!(filter, before);
+    }
+
+    #[test]
+    fn declared_ops_match_a_tally_of_each_routine() {
+        //! runs each routine in full on counted values; what it's charged must be what it performed.
+        let tally = OpTally::new();
+        let body = SolarVec3D::new();
+        let mut filter = _NavFilter::_new(&state(10_000.0, 1.0));
+        filter
+            ._propagate(&imu((0.5, 0.0, 0.0), 0.1, 0.1), &StepVec3D::new(), &tally)
+            .unwrap();
+        assert_eq!(tally.reset(), _PROPAGATE_OPS);
+        let predicted = filter._predicted_range(&body, &tally).unwrap();
+        filter._update_range(predicted, &body, &tally).unwrap();
+        assert_eq!(tally.reset(), _RANGE_OPS);
+        let position = SolarVec3D::from_floats(10_010.0, 5.0, -5.0).unwrap();
+        filter
+            ._update_position(&position, _INITIAL_POSITION_VARIANCE, &tally)
+            .unwrap();
+        assert_eq!(tally.reset(), _POSITION_FIX_OPS);
+        let velocity = StepVec3D::from_floats(1.0, 0.1, 0.0).unwrap();
+        filter
+            ._update_state_vector(
+                (&position, _INITIAL_POSITION_VARIANCE),
+                (&velocity, _INITIAL_VELOCITY_VARIANCE),
+                &tally,
+            )
+            .unwrap();
+        assert_eq!(tally.reset(), _STATE_VECTOR_FIX_OPS);
+        // without a rotation there's no attitude to integrate, and less work done.
+        filter
+            ._propagate(&imu((0.5, 0.0, 0.0), 0.0, 0.2), &StepVec3D::new(), &tally)
+            .unwrap();
+        assert!(tally.counts().cost(&CostTable::AGC) < _PROPAGATE_OPS.cost(&CostTable::AGC));
+    }
+}
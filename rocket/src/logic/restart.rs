@@ -0,0 +1,228 @@
+//! contains restart protection, after the AGC's restart groups. A program that must survive a software restart
+//! registers itself in a restart group, then checkpoints its phase, along with the navigation state it was working
+//! from, at each point it could safely be resumed from. The restart table lives in memory that a restart doesn't
+//! clear; everything else in flight (queued jobs, the programs' working state) is lost. After a restart each
+//! registered program is rebuilt at its last checkpointed phase. Resuming repeats the work since that checkpoint, so
+//! each phase's actions must be safe to repeat.
+//! The navigation filter survives a restart: it commits each update whole, so there's nothing half-written to roll
+//! back, and rewinding it would throw away both newer fixes and the covariance. A checkpoint's nav is only used when
+//! the controller has none of its own, or has one older than the checkpoint.
+
+use std::fmt::Display;
+
+use agc_utils::SimTime;
+
+use crate::hardware::flight_controller::_FlightController;
+use crate::logic::burn::_Burn;
+use crate::logic::navigation::{_NavFilter, _NavState};
+
+// the AGC's count of restart groups.
+pub(crate) const _RESTART_GROUPS: usize = 6;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum _Program {
+    /// A restart-protected program, with the parameters it was started with.
+    Burn {
+        ignition: SimTime,
+        duration: SimTime,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct _Checkpoint {
+    /// The point a program can be resumed from. Size: 112B.
+    pub(crate) program: _Program,
+    pub(crate) phase: u8, // meaning is up to the program.
+    pub(crate) nav: _NavState,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum _RestartError {
+    NoFreeGroup { program: _Program }, // every group is held by another program.
+    NotRegistered { group: usize },    // the group was released, or never registered.
+}
+
+impl Display for _RestartError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            _RestartError::NoFreeGroup { program } => {
+                write!(f, "No free restart group for {program:?}")
+            }
+            _RestartError::NotRegistered { group } => {
+                write!(f, "Restart group {group} has no program registered")
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub(crate) struct _RestartTable {
+    /// Size: 6 * 120B; each group is an Option<_Checkpoint>.
+    groups: [Option<_Checkpoint>; _RESTART_GROUPS],
+}
+
+impl _RestartTable {
+    pub(crate) fn _register(
+        &mut self,
+        program: _Program,
+        phase: u8,
+        nav: &_NavState,
+    ) -> Result<usize, _RestartError> {
+        //! claims a free restart group for a program, with its first checkpoint. Returns the group.
+        let Some((group, slot)) = self
+            .groups
+            .iter_mut()
+            .enumerate()
+            .find(|(_, slot)| slot.is_none())
+        else {
+            return Err(_RestartError::NoFreeGroup { program });
+        };
+        *slot = Some(_Checkpoint {
+            program,
+            phase,
+            nav: *nav,
+        });
+        Ok(group)
+    }
+
+    pub(crate) fn _checkpoint(
+        &mut self,
+        group: usize,
+        phase: u8,
+        nav: Option<&_NavState>,
+    ) -> Result<(), _RestartError> {
+        //! records the phase a program has reached, and the nav state it reached it with. With no nav solution, the
+        //! last one checkpointed is kept.
+        match self.groups.get_mut(group).and_then(Option::as_mut) {
+            Some(checkpoint) => {
+                checkpoint.phase = phase;
+                if let Some(nav) = nav {
+                    checkpoint.nav = *nav;
+                }
+                Ok(())
+            }
+            None => Err(_RestartError::NotRegistered { group }),
+        }
+    }
+
+    pub(crate) fn _release(&mut self, group: usize) {
+        //! frees a group once its program has finished; it won't be resumed after a restart.
+        if let Some(slot) = self.groups.get_mut(group) {
+            *slot = None;
+        }
+    }
+
+    pub(crate) fn _checkpoint_of(&self, group: usize) -> Option<&_Checkpoint> {
+        self.groups.get(group).and_then(Option::as_ref)
+    }
+
+    fn _latest_nav(&self) -> Option<&_NavState> {
+        //! the newest nav state held by any checkpoint.
+        self.groups
+            .iter()
+            .flatten()
+            .map(|checkpoint| &checkpoint.nav)
+            .max_by_key(|nav| nav.at)
+    }
+}
+
+impl _FlightController {
+    pub(crate) fn _software_restart(&mut self) {
+        //! simulates a restart of the flight computer: drops all queued work, then resumes protected programs.
+        self.executive._restart();
+        self._resume_programs();
+    }
+
+    pub(crate) fn _resume_programs(&mut self) {
+        //! rebuilds each registered program at its last checkpoint, and restores nav from the newest checkpoint if the
+        //! controller's own is missing or older. Programs with no restart group are lost.
+        self.burn = None;
+        // (MR D.3) bounded by the group count.
+        for group in 0.._RESTART_GROUPS {
+            let Some(checkpoint) = self.restart_table._checkpoint_of(group) else {
+                continue;
+            };
+            match checkpoint.program {
+                _Program::Burn { .. } => self.burn = _Burn::_resume(group, checkpoint),
+            }
+        }
+        let Some(checkpointed) = self.restart_table._latest_nav() else {
+            return;
+        };
+        let current = self.nav.as_ref().map(|nav| nav._state().at);
+        // the covariance isn't checkpointed; a restored state starts again at the initial uncertainty.
+        if current.is_none_or(|at| at < checkpointed.at) {
+            self.nav = Some(_NavFilter::_new(checkpointed));
+        }
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)] // this is test code.
+mod tests {
+    use super::*;
+    use agc_utils::{Quaternion, SolarVec3D, StepVec3D};
+
+    fn nav_at(secs: i64) -> _NavState {
+        _NavState {
+            position: SolarVec3D::from_floats(1_000.0, 0.0, 0.0).unwrap(),
+            velocity: StepVec3D::new(),
+            attitude: Quaternion::IDENTITY,
+            at: SimTime::from_secs(secs),
+        }
+    }
+
+    const PROGRAM: _Program = _Program::Burn {
+        ignition: SimTime::from_secs(10),
+        duration: SimTime::from_secs(5),
+    };
+
+    #[test]
+    fn register_checkpoint_release() {
+        let mut table = _RestartTable::default();
+        let group = table._register(PROGRAM, 0, &nav_at(0)).unwrap();
+        table._checkpoint(group, 2, Some(&nav_at(3))).unwrap();
+        let checkpoint = table._checkpoint_of(group).unwrap();
+        assert_eq!((checkpoint.program, checkpoint.phase), (PROGRAM, 2));
+        assert_eq!(checkpoint.nav, nav_at(3));
+        table._release(group);
+        assert_eq!(table._checkpoint_of(group), None);
+        assert_eq!(
+            table._checkpoint(group, 3, Some(&nav_at(4))),
+            Err(_RestartError::NotRegistered { group })
+        );
+    }
+
+    #[test]
+    fn groups_run_out() {
+        let mut table = _RestartTable::default();
+        for _ in 0.._RESTART_GROUPS {
+            table._register(PROGRAM, 0, &nav_at(0)).unwrap();
+        }
+        assert_eq!(
+            table._register(PROGRAM, 0, &nav_at(0)),
+            Err(_RestartError::NoFreeGroup { program: PROGRAM })
+        );
+        table._release(2);
+        assert_eq!(table._register(PROGRAM, 0, &nav_at(0)), Ok(2));
+    }
+
+    #[test]
+    fn out_of_range_groups_are_unregistered() {
+        let mut table = _RestartTable::default();
+        table._release(_RESTART_GROUPS); // no-op rather than a panic.
+        assert_eq!(table._checkpoint_of(_RESTART_GROUPS), None);
+        assert!(table._checkpoint(99, 0, Some(&nav_at(0))).is_err());
+    }
+
+    #[test]
+    fn newest_nav_wins() {
+        let mut table = _RestartTable::default();
+        assert_eq!(table._latest_nav(), None);
+        let first = table._register(PROGRAM, 0, &nav_at(5)).unwrap();
+        table._register(PROGRAM, 0, &nav_at(8)).unwrap();
+        assert_eq!(table._latest_nav(), Some(&nav_at(8)));
+        table._checkpoint(first, 1, Some(&nav_at(9))).unwrap();
+        assert_eq!(table._latest_nav(), Some(&nav_at(9)));
+    }
+}
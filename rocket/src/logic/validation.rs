@@ -0,0 +1,413 @@
+//! contains plausibility checks on sensor readings (constraint 3c). A garbage reading has the right type, so it can only
+//! be caught by what it says. Each reading is checked, in order, for:
+//!   1. staleness: it must be recent, and not from the future.
+//!   2. range: each value must be one the rocket could physically see.
+//!   3. rate of change: it can't have moved further from the last accepted reading than physics allows in the time.
+//!   4. consistency: it must agree with what navigation predicted, when there's a prediction.
+//!
+//! The first failed check rejects the reading, with a reason code that is counted per sensor. Every check works
+//! component by component on checked arithmetic, so garbage at the extremes of the type can't overflow; any value
+//! that can't even be compared is rejected.
+
+use std::fmt::Display;
+
+use agc_utils::{FixedPoint, SimTime, SolarFp, StepFp, StepVec3D, TimeFp, Vec3D};
+
+use crate::hardware::sensors::{_SensorReading, inertial_platform::_InertialReading};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum _Rejection {
+    Stale,         // older than the sensor's staleness limit.
+    FromTheFuture, // timestamped after now.
+    OutOfRange,    // physically impossible in itself.
+    RateOfChange,  // too far from the last accepted reading for the time between them.
+    Inconsistent,  // disagrees with the navigation prediction.
+}
+
+// every rejection, in code order.
+const _REJECTIONS: [_Rejection; 5] = [
+    _Rejection::Stale,
+    _Rejection::FromTheFuture,
+    _Rejection::OutOfRange,
+    _Rejection::RateOfChange,
+    _Rejection::Inconsistent,
+];
+
+impl _Rejection {
+    pub(crate) const fn _code(self) -> u8 {
+        //! a stable number for logs, and the index of this rejection's count.
+        match self {
+            _Rejection::Stale => 0,
+            _Rejection::FromTheFuture => 1,
+            _Rejection::OutOfRange => 2,
+            _Rejection::RateOfChange => 3,
+            _Rejection::Inconsistent => 4,
+        }
+    }
+}
+
+impl Display for _Rejection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let reason = match self {
+            _Rejection::Stale => "stale reading",
+            _Rejection::FromTheFuture => "reading timestamped in the future",
+            _Rejection::OutOfRange => "value out of physical range",
+            _Rejection::RateOfChange => "value changed faster than physically possible",
+            _Rejection::Inconsistent => "value inconsistent with navigation",
+        };
+        write!(f, "Reading rejected (V{}): {reason}", self._code())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub(crate) struct _RejectionCounts {
+    /// How often each rejection has occurred, indexed by code. Size: 40B.
+    counts: [u64; _REJECTIONS.len()],
+}
+
+impl _RejectionCounts {
+    pub(crate) fn _count(&self, rejection: _Rejection) -> u64 {
+        self.counts
+            .get(usize::from(rejection._code()))
+            .copied()
+            .unwrap_or(0)
+    }
+
+    pub(crate) fn _total(&self) -> u64 {
+        // (MR B.3) a saturated total is still "a lot".
+        self.counts
+            .iter()
+            .fold(0, |total, count| total.saturating_add(*count))
+    }
+
+    fn _record(&mut self, rejection: _Rejection) {
+        if let Some(count) = self.counts.get_mut(usize::from(rejection._code())) {
+            *count = count.saturating_add(1); // (MR B.3) a saturated count is still "a lot".
+        }
+    }
+}
+
+pub(crate) trait _Plausible: Copy {
+    /// A kind of reading that can be checked for plausibility, with limits for the sensor that produces it.
+    const MAX_AGE: SimTime; // the oldest a reading may be and still be used.
+
+    /// whether every value is physically possible.
+    fn _in_range(&self) -> bool;
+
+    /// whether the change from the previous accepted reading is possible in the time elapsed.
+    fn _change_possible(&self, previous: &Self, elapsed: SimTime) -> bool;
+
+    /// whether this agrees with what navigation predicted.
+    fn _consistent_with(&self, predicted: &Self) -> bool;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct _Validator<T> {
+    /// The plausibility state for one sensor: what it last got right, and how often it's been wrong.
+    last_accepted: Option<_SensorReading<T>>,
+    rejections: _RejectionCounts,
+}
+
+impl<T> Default for _Validator<T> {
+    fn default() -> Self {
+        Self {
+            last_accepted: None,
+            rejections: _RejectionCounts::default(),
+        }
+    }
+}
+
+impl<T: _Plausible> _Validator<T> {
+    pub(crate) fn _rejections(&self) -> &_RejectionCounts {
+        &self.rejections
+    }
+
+    pub(crate) fn _validate(
+        &mut self,
+        reading: &_SensorReading<T>,
+        now: SimTime,
+        predicted: Option<&T>,
+    ) -> Result<T, _Rejection> {
+        //! accepts or rejects a reading; an accepted one becomes the baseline for the next rate-of-change check.
+        let result = self._check(reading, now, predicted);
+        match result {
+            Ok(_) => self.last_accepted = Some(*reading),
+            Err(rejection) => self.rejections._record(rejection),
+        }
+        result
+    }
+
+    fn _check(
+        &self,
+        reading: &_SensorReading<T>,
+        now: SimTime,
+        predicted: Option<&T>,
+    ) -> Result<T, _Rejection> {
+        //! runs the checks in order, stopping at the first failure.
+        if reading.time > now {
+            return Err(_Rejection::FromTheFuture);
+        }
+        if now.elapsed_since(reading.time) > T::MAX_AGE {
+            return Err(_Rejection::Stale);
+        }
+        if !reading.data._in_range() {
+            return Err(_Rejection::OutOfRange);
+        }
+        if let Some(previous) = &self.last_accepted {
+            let elapsed = reading.time.elapsed_since(previous.time);
+            if !reading.data._change_possible(&previous.data, elapsed) {
+                return Err(_Rejection::RateOfChange);
+            }
+        }
+        match predicted {
+            Some(predicted) if !reading.data._consistent_with(predicted) => {
+                Err(_Rejection::Inconsistent)
+            }
+            Some(_) | None => Ok(reading.data),
+        }
+    }
+}
+
+// altimeter limits. It reads out to 40km, and nothing the rocket does can move it faster than escape speed.
+const _ALTITUDE_MAX: SolarFp = SolarFp::from_f64_trusted(45_000.0); // m; the range, plus margin for drift.
+const _ALTITUDE_RATE_MAX: SolarFp = SolarFp::from_f64_trusted(12_000.0); // m/s.
+const _ALTITUDE_NOISE: SolarFp = SolarFp::from_f64_trusted(500.0); // m; allowance for variance between readings.
+const _ALTITUDE_NAV_TOLERANCE: SolarFp = SolarFp::from_f64_trusted(2_000.0); // m.
+
+// IMU limits, per axis. ~10g and ~1 rotation every 6s are far past anything the engine or wheels can do.
+const _ACCELERATION_MAX: StepFp = StepFp::from_f64_trusted(100.0); // m/s^2.
+const _ANGULAR_RATE_MAX: StepFp = StepFp::from_f64_trusted(1.0); // rad/s.
+const _JERK_MAX: StepFp = StepFp::from_f64_trusted(50.0); // m/s^3; engine ignition and cutoff transients.
+const _ANGULAR_ACCELERATION_MAX: StepFp = StepFp::from_f64_trusted(0.5); // rad/s^2.
+const _ACCELERATION_NOISE: StepFp = StepFp::from_f64_trusted(0.01); // m/s^2; noise, plus the variant state's.
+const _ANGULAR_RATE_NOISE: StepFp = StepFp::from_f64_trusted(1e-5); // rad/s.
+const _ACCELERATION_NAV_TOLERANCE: StepFp = StepFp::from_f64_trusted(1.0); // m/s^2.
+const _ANGULAR_RATE_NAV_TOLERANCE: StepFp = StepFp::from_f64_trusted(0.05); // rad/s.
+
+impl _Plausible for SolarFp {
+    // three missed polls of the 1s altimeter.
+    const MAX_AGE: SimTime = SimTime::from_secs(3);
+
+    fn _in_range(&self) -> bool {
+        //! an altitude from the surface up to the altimeter's range.
+        (SolarFp::from_int(0)..=_ALTITUDE_MAX).contains(self)
+    }
+
+    fn _change_possible(&self, previous: &Self, elapsed: SimTime) -> bool {
+        // (MR B.3) a saturated allowance accepts any change; right after a long gap.
+        let allowance = _ALTITUDE_RATE_MAX
+            .saturating_mul(elapsed.as_solar_fp())
+            .saturating_add(_ALTITUDE_NOISE);
+        _close(*self, *previous, allowance)
+    }
+
+    fn _consistent_with(&self, predicted: &Self) -> bool {
+        _close(*self, *predicted, _ALTITUDE_NAV_TOLERANCE)
+    }
+}
+
+impl _Plausible for _InertialReading {
+    // three missed polls of the 0.1s IMU.
+    const MAX_AGE: SimTime = SimTime::from_fp(TimeFp::from_f64_trusted(0.3));
+
+    fn _in_range(&self) -> bool {
+        _all_within(&self.acceleration, _ACCELERATION_MAX)
+            && _all_within(&self.angular_rate, _ANGULAR_RATE_MAX)
+    }
+
+    fn _change_possible(&self, previous: &Self, elapsed: SimTime) -> bool {
+        // (MR B.5) a gap too long to express as a StepFp is long enough for any change.
+        let Ok::<StepFp, _>(elapsed) = elapsed.as_fp().convert() else {
+            return true;
+        };
+        // (MR B.3) saturated allowances accept any change, which is right after a gap long enough to cause them.
+        let acceleration_allowance = _JERK_MAX
+            .saturating_mul(elapsed)
+            .saturating_add(_ACCELERATION_NOISE);
+        let rate_allowance = _ANGULAR_ACCELERATION_MAX
+            .saturating_mul(elapsed)
+            .saturating_add(_ANGULAR_RATE_NOISE);
+        _all_close(
+            &self.acceleration,
+            &previous.acceleration,
+            acceleration_allowance,
+        ) && _all_close(&self.angular_rate, &previous.angular_rate, rate_allowance)
+    }
+
+    fn _consistent_with(&self, predicted: &Self) -> bool {
+        _all_close(
+            &self.acceleration,
+            &predicted.acceleration,
+            _ACCELERATION_NAV_TOLERANCE,
+        ) && _all_close(
+            &self.angular_rate,
+            &predicted.angular_rate,
+            _ANGULAR_RATE_NAV_TOLERANCE,
+        )
+    }
+}
+
+fn _within<const N: u8>(value: FixedPoint<N>, limit: FixedPoint<N>) -> bool {
+    //! whether value lies in [-limit, limit]. Comparison only; no arithmetic on the value that could overflow.
+    // (MR B.3) limits are small positive constants.
+    let lower = FixedPoint::<N>::with_internal(0).saturating_sub(limit);
+    (lower..=limit).contains(&value)
+}
+
+fn _close<const N: u8>(a: FixedPoint<N>, b: FixedPoint<N>, tolerance: FixedPoint<N>) -> bool {
+    //! whether a and b are within tolerance of each other. A difference too large to represent is not close.
+    a.checked_sub(b)
+        .is_ok_and(|difference| _within(difference, tolerance))
+}
+
+fn _all_within(vector: &StepVec3D, limit: StepFp) -> bool {
+    _within(vector.0, limit) && _within(vector.1, limit) && _within(vector.2, limit)
+}
+
+fn _all_close<const N: u8>(a: &Vec3D<N>, b: &Vec3D<N>, tolerance: FixedPoint<N>) -> bool {
+    _close(a.0, b.0, tolerance) && _close(a.1, b.1, tolerance) && _close(a.2, b.2, tolerance)
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)] // this is test code.
+mod tests {
+    use super::*;
+
+    fn altitude(metres: f64, secs: i64) -> _SensorReading<SolarFp> {
+        _SensorReading {
+            data: SolarFp::from_f64(metres).unwrap(),
+            time: SimTime::from_secs(secs),
+        }
+    }
+
+    fn inertial(acceleration: f64, secs: f64) -> _SensorReading<_InertialReading> {
+        _SensorReading {
+            data: _InertialReading {
+                acceleration: StepVec3D::from_floats(acceleration, 0.0, 0.0).unwrap(),
+                angular_rate: StepVec3D::from_floats(0.0, 0.0, 0.001).unwrap(),
+            },
+            time: SimTime::from_f64(secs).unwrap(),
+        }
+    }
+
+    #[test]
+    fn plausible_readings_are_accepted() {
+        let mut validator = _Validator::default();
+        let now = SimTime::from_secs(10);
+        let reading = altitude(10_000.0, 10);
+        assert_eq!(validator._validate(&reading, now, None), Ok(reading.data));
+        let next = altitude(10_100.0, 11);
+        assert_eq!(
+            validator._validate(&next, SimTime::from_secs(11), Some(&next.data)),
+            Ok(next.data)
+        );
+        assert_eq!(validator._rejections()._total(), 0);
+    }
+
+    #[test]
+    fn stale_and_future_readings() {
+        let mut validator = _Validator::default();
+        let now = SimTime::from_secs(10);
+        assert_eq!(
+            validator._validate(&altitude(100.0, 6), now, None),
+            Err(_Rejection::Stale)
+        );
+        assert!(validator._validate(&altitude(100.0, 7), now, None).is_ok()); // exactly at the limit.
+        assert_eq!(
+            validator._validate(&altitude(100.0, 11), now, None),
+            Err(_Rejection::FromTheFuture)
+        );
+    }
+
+    #[test]
+    fn garbage_is_out_of_range() {
+        let mut validator = _Validator::default();
+        let now = SimTime::from_secs(1);
+        for internal in [i64::MIN, -1, i64::MAX, 1 << 40] {
+            let garbage = _SensorReading {
+                data: SolarFp::with_internal(internal),
+                time: now,
+            };
+            assert_eq!(
+                validator._validate(&garbage, now, None),
+                Err(_Rejection::OutOfRange)
+            );
+        }
+        let mut garbage = inertial(0.0, 1.0);
+        garbage.data.angular_rate.1 = StepFp::with_internal(i64::MIN);
+        assert_eq!(
+            _Validator::default()._validate(&garbage, now, None),
+            Err(_Rejection::OutOfRange)
+        );
+        assert_eq!(validator._rejections()._count(_Rejection::OutOfRange), 4);
+    }
+
+    #[test]
+    fn rate_of_change_is_against_last_accepted() {
+        let mut validator = _Validator::default();
+        assert!(validator
+            ._validate(&altitude(10_000.0, 10), SimTime::from_secs(10), None)
+            .is_ok());
+        // 13km in 1s is too fast; the rejected reading doesn't become the baseline.
+        assert_eq!(
+            validator._validate(&altitude(23_000.0, 11), SimTime::from_secs(11), None),
+            Err(_Rejection::RateOfChange)
+        );
+        assert!(validator
+            ._validate(&altitude(21_000.0, 11), SimTime::from_secs(11), None)
+            .is_ok());
+    }
+
+    #[test]
+    fn inertial_jumps_are_caught() {
+        let mut validator = _Validator::default();
+        let now = SimTime::from_secs(1);
+        validator._validate(&inertial(2.0, 0.9), now, None).unwrap();
+        assert!(validator._validate(&inertial(4.0, 1.0), now, None).is_ok()); // 20 m/s^3 is within limits.
+        assert_eq!(
+            validator._validate(&inertial(40.0, 1.0), now, None),
+            Err(_Rejection::RateOfChange)
+        );
+    }
+
+    #[test]
+    fn inconsistent_with_nav() {
+        let mut validator = _Validator::default();
+        let now = SimTime::from_secs(1);
+        let reading = inertial(2.0, 1.0);
+        let mut predicted = reading.data;
+        predicted.acceleration.0 = StepFp::from_int(5);
+        assert_eq!(
+            validator._validate(&reading, now, Some(&predicted)),
+            Err(_Rejection::Inconsistent)
+        );
+        predicted.acceleration.0 = StepFp::from_f64(2.5).unwrap();
+        assert!(validator._validate(&reading, now, Some(&predicted)).is_ok());
+    }
+
+    #[test]
+    fn rejections_are_counted_by_code() {
+        let mut validator = _Validator::default();
+        let now = SimTime::from_secs(10);
+        validator
+            ._validate(&altitude(100.0, 1), now, None)
+            .unwrap_err();
+        validator
+            ._validate(&altitude(100.0, 2), now, None)
+            .unwrap_err();
+        validator
+            ._validate(&altitude(-5.0, 10), now, None)
+            .unwrap_err();
+        let counts = validator._rejections();
+        assert_eq!(counts._count(_Rejection::Stale), 2);
+        assert_eq!(counts._count(_Rejection::OutOfRange), 1);
+        assert_eq!(counts._total(), 3);
+        for (code, rejection) in _REJECTIONS.iter().enumerate() {
+            assert_eq!(usize::from(rejection._code()), code);
+        }
+        assert_eq!(
+            _Rejection::Stale.to_string(),
+            "Reading rejected (V0): stale reading"
+        );
+    }
+}
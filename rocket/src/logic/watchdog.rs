@@ -0,0 +1,210 @@
+//! contains the sensor watchdog. A hung sensor doesn't report that it's hung; its readings just stop arriving. So the
+//! controller keeps, per sensor, the timestamp of the newest reading it has published, and counts the polling periods
+//! missed since. Past a few missed periods the sensor is suspect; past more, it has failed: it is sent a reboot
+//! (repeated while it stays silent) and its readings aren't trusted until one of them passes validation again.
+//! Only liveness decides a reboot. A reading counts whether or not it's plausible, and whether or not the controller
+//! had the budget to process it; a sensor that is alive but wrong is the validator's to reject, not a reason to reboot.
+//! But a failed sensor isn't trusted just for being alive: one rebooted into publishing garbage stays failed.
+
+use agc_utils::SimTime;
+
+use crate::hardware::sensors::command::_SensorCommand;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct _WatchdogConfig {
+    /// Missed polling periods before each escalation. Size: 2B.
+    pub(crate) suspect_after: u8,
+    pub(crate) fail_after: u8, // also the wait between repeated reboots of a failed sensor.
+}
+
+impl _WatchdogConfig {
+    // a single late reading is normal jitter; two is suspicious. A reboot takes a few periods to clear, so allow for it.
+    pub(crate) const _DEFAULT: Self = Self {
+        suspect_after: 2,
+        fail_after: 5,
+    };
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum _SensorHealth {
+    Healthy,
+    Suspect, // late; its last reading is still used, but may be about to fail.
+    Failed, // silent too long; rebooted, and not used until it publishes a reading that passes validation.
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct _Watchdog {
+    /// Size: 40B.
+    period: SimTime, // the sensor's nominal polling period.
+    config: _WatchdogConfig,
+    health: _SensorHealth,
+    last_fresh: SimTime, // the timestamp of the newest reading published.
+    last_reboot: Option<SimTime>, // when the latest reboot was sent, while failed.
+}
+
+impl _Watchdog {
+    pub(crate) fn _new(period: SimTime, config: _WatchdogConfig, now: SimTime) -> Self {
+        //! a watchdog for a sensor just powered up; it counts as fresh from now.
+        Self {
+            period,
+            config,
+            health: _SensorHealth::Healthy,
+            last_fresh: now,
+            last_reboot: None,
+        }
+    }
+
+    pub(crate) fn _health(&self) -> _SensorHealth {
+        self.health
+    }
+
+    pub(crate) fn _is_trusted(&self) -> bool {
+        //! whether the sensor's readings should be used.
+        match self.health {
+            _SensorHealth::Healthy | _SensorHealth::Suspect => true,
+            _SensorHealth::Failed => false,
+        }
+    }
+
+    pub(crate) fn _record_reading(&mut self, stamped: SimTime, now: SimTime) {
+        //! notes the timestamp of the sensor's latest reading. If it has moved on, the sensor is alive: no longer
+        //! suspect, and not to be rebooted again. A failed sensor stays failed until _record_accepted(). A timestamp
+        //! from the future only counts as now.
+        let stamped = stamped.min(now);
+        if stamped <= self.last_fresh {
+            return;
+        }
+        self.last_fresh = stamped;
+        self.last_reboot = None;
+        if self.health == _SensorHealth::Suspect {
+            self.health = _SensorHealth::Healthy;
+        }
+    }
+
+    pub(crate) fn _record_accepted(&mut self, stamped: SimTime, now: SimTime) {
+        //! notes a reading of the sensor's that passed validation; the only way a failed sensor is trusted again.
+        self._record_reading(stamped, now);
+        self.health = _SensorHealth::Healthy;
+    }
+
+    pub(crate) fn _check(&mut self, now: SimTime) -> Option<_SensorCommand> {
+        //! updates the sensor's health for the time now. Returns a reboot command when one should be sent.
+        let missed = self._periods_since(self.last_fresh, now);
+        if missed < u64::from(self.config.fail_after) {
+            // a failed sensor that's publishing again is alive, but not yet trusted.
+            if self.health != _SensorHealth::Failed {
+                self.health = if missed < u64::from(self.config.suspect_after) {
+                    _SensorHealth::Healthy
+                } else {
+                    _SensorHealth::Suspect
+                };
+            }
+            return None;
+        }
+        self.health = _SensorHealth::Failed;
+        let reboot_due = match self.last_reboot {
+            Some(sent) => self._periods_since(sent, now) >= u64::from(self.config.fail_after),
+            None => true,
+        };
+        if !reboot_due {
+            return None;
+        }
+        self.last_reboot = Some(now);
+        Some(_SensorCommand::Reboot)
+    }
+
+    fn _periods_since(&self, since: SimTime, now: SimTime) -> u64 {
+        //! whole polling periods elapsed since a time. A zero period is treated as always overdue.
+        let elapsed = now.elapsed_since(since).as_fp().internal();
+        let period = self.period.as_fp().internal();
+        // (MR B.4) checked_div returns None for a zero period; (MR A.2b) elapsed_since clamps at zero, so the quotient
+        // is non-negative.
+        elapsed.checked_div(period).map_or(u64::MAX, |periods| {
+            u64::try_from(periods).unwrap_or(u64::MAX)
+        })
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)] // this is test code.
+mod tests {
+    use super::*;
+
+    fn watchdog() -> _Watchdog {
+        _Watchdog::_new(
+            SimTime::from_secs(1),
+            _WatchdogConfig::_DEFAULT,
+            SimTime::ZERO,
+        )
+    }
+
+    #[test]
+    fn escalates_with_missed_periods() {
+        let mut watchdog = watchdog();
+        assert_eq!(watchdog._check(SimTime::from_secs(1)), None);
+        assert_eq!(watchdog._health(), _SensorHealth::Healthy);
+        assert_eq!(watchdog._check(SimTime::from_secs(2)), None);
+        assert_eq!(watchdog._health(), _SensorHealth::Suspect);
+        assert!(watchdog._is_trusted());
+        assert_eq!(
+            watchdog._check(SimTime::from_secs(5)),
+            Some(_SensorCommand::Reboot)
+        );
+        assert_eq!(watchdog._health(), _SensorHealth::Failed);
+        assert!(!watchdog._is_trusted());
+    }
+
+    #[test]
+    fn reboots_repeat_while_silent() {
+        let mut watchdog = watchdog();
+        assert!(watchdog._check(SimTime::from_secs(5)).is_some());
+        assert_eq!(watchdog._check(SimTime::from_secs(6)), None);
+        assert_eq!(watchdog._check(SimTime::from_secs(9)), None);
+        assert_eq!(
+            watchdog._check(SimTime::from_secs(10)),
+            Some(_SensorCommand::Reboot)
+        );
+    }
+
+    #[test]
+    fn accepted_reading_restores_trust() {
+        let mut watchdog = watchdog();
+        watchdog._check(SimTime::from_secs(7));
+        // alive again, so not rebooted, but nothing it has said has been believed yet.
+        watchdog._record_reading(SimTime::from_secs(8), SimTime::from_secs(8));
+        assert_eq!(watchdog._check(SimTime::from_secs(9)), None);
+        assert_eq!(watchdog._health(), _SensorHealth::Failed);
+        watchdog._record_accepted(SimTime::from_secs(9), SimTime::from_secs(9));
+        assert_eq!(watchdog._health(), _SensorHealth::Healthy);
+        assert_eq!(watchdog._check(SimTime::from_secs(10)), None);
+        assert!(watchdog._is_trusted());
+        // and a later failure reboots straight away, not on the old schedule.
+        assert!(watchdog._check(SimTime::from_secs(14)).is_some());
+    }
+
+    #[test]
+    fn degenerate_periods_and_times() {
+        let mut zero_period =
+            _Watchdog::_new(SimTime::ZERO, _WatchdogConfig::_DEFAULT, SimTime::ZERO);
+        assert!(zero_period._check(SimTime::ZERO).is_some());
+        let mut watchdog = watchdog();
+        watchdog._record_reading(SimTime::from_secs(10), SimTime::from_secs(10));
+        watchdog._record_reading(SimTime::from_secs(3), SimTime::from_secs(10)); // out of order; doesn't wind back.
+        assert_eq!(watchdog._check(SimTime::from_secs(11)), None);
+        assert_eq!(watchdog._check(SimTime::from_secs(1)), None); // time before the last fresh reading.
+    }
+
+    #[test]
+    fn only_a_newer_timestamp_is_a_sign_of_life() {
+        let mut watchdog = watchdog();
+        watchdog._record_reading(SimTime::from_secs(1), SimTime::from_secs(1));
+        assert!(watchdog._check(SimTime::from_secs(6)).is_some());
+        // the same reading seen again, as every tick sees it, isn't a new one.
+        watchdog._record_reading(SimTime::from_secs(1), SimTime::from_secs(7));
+        assert_eq!(watchdog._health(), _SensorHealth::Failed);
+        // and one stamped in the future only vouches for the sensor up to now.
+        watchdog._record_accepted(SimTime::from_secs(1_000), SimTime::from_secs(8));
+        assert_eq!(watchdog._health(), _SensorHealth::Healthy);
+        assert!(watchdog._check(SimTime::from_secs(13)).is_some());
+    }
+}
@@ -0,0 +1,354 @@
+//! opt-in operation counting over FixedPoint, Vec3D, Matrix and Quaternion arithmetic. Wrapping a value in Counted (via an OpTally) makes
+//! every operation on it tick the tally, so the cost of a routine can be measured rather than guessed. Costs are
+//! applied afterwards from a CostTable, so the same counts can be priced under different models of the hardware.
+//! Vector operations are tallied as the scalar operations they're made of, so a table only needs scalar prices; a
+//! length-n dot (so each element of a matrix product) is n muls and n - 1 adds, and a sine and cosine, being a
+//! series of about the same length as a root, is priced as a magnitude.
+
+use std::cell::Cell;
+
+use crate::fixed_point::{ArithmeticError, FixedPoint, UnitFp};
+use crate::matrix::Matrix;
+use crate::quaternion::{Quaternion, QuaternionError};
+use crate::vec3d::{UnitVec3D, Vec3D};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct OpCounts {
+    pub adds: u64, // additions and subtractions.
+    pub muls: u64,
+    pub divs: u64,
+    pub magnitudes: u64, // each involves a wide square root; far dearer than the adds and muls around it.
+}
+
+impl OpCounts {
+    pub fn cost(&self, table: &CostTable) -> u64 {
+        //! the total price of these operations. Saturates rather than overflowing.
+        self.adds
+            .saturating_mul(table.add)
+            .saturating_add(self.muls.saturating_mul(table.mul))
+            .saturating_add(self.divs.saturating_mul(table.div))
+            .saturating_add(self.magnitudes.saturating_mul(table.magnitude))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CostTable {
+    pub add: u64,
+    pub mul: u64,
+    pub div: u64,
+    pub magnitude: u64,
+}
+
+impl CostTable {
+    /// Relative timings of the Apollo Guidance Computer: add ~1 memory cycle, multiply ~3, divide ~6, and a square root
+    /// being a subroutine of several dozen instructions.
+    pub const AGC: Self = Self {
+        add: 1,
+        mul: 3,
+        div: 6,
+        magnitude: 40,
+    };
+}
+
+#[derive(Debug, Default)]
+pub struct OpTally {
+    /// Running counts for one routine. Interior mutability lets any number of Counted values share it by reference.
+    adds: Cell<u64>,
+    muls: Cell<u64>,
+    divs: Cell<u64>,
+    magnitudes: Cell<u64>,
+}
+
+impl OpTally {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn counted<T>(&self, value: T) -> Counted<'_, T> {
+        //! wraps a value so that arithmetic on it is tallied here.
+        Counted { value, tally: self }
+    }
+
+    pub fn counts(&self) -> OpCounts {
+        OpCounts {
+            adds: self.adds.get(),
+            muls: self.muls.get(),
+            divs: self.divs.get(),
+            magnitudes: self.magnitudes.get(),
+        }
+    }
+
+    pub fn reset(&self) -> OpCounts {
+        //! zeroes the tally, returning what it had counted.
+        let counts = self.counts();
+        self.adds.set(0);
+        self.muls.set(0);
+        self.divs.set(0);
+        self.magnitudes.set(0);
+        counts
+    }
+
+    fn record(&self, counts: OpCounts) {
+        let add = |cell: &Cell<u64>, n: u64| cell.set(cell.get().saturating_add(n));
+        add(&self.adds, counts.adds);
+        add(&self.muls, counts.muls);
+        add(&self.divs, counts.divs);
+        add(&self.magnitudes, counts.magnitudes);
+    }
+}
+
+const fn ops(adds: u64, muls: u64, divs: u64, magnitudes: u64) -> OpCounts {
+    OpCounts {
+        adds,
+        muls,
+        divs,
+        magnitudes,
+    }
+}
+
+// a unit vector: the magnitude, then one division per component.
+const UNIT_VECTOR_OPS: OpCounts = ops(2, 3, 3, 1);
+// a quaternion's renormalisation: the squared norm, its root, then one division per component.
+const NORMALISE_OPS: OpCounts = ops(3, 4, 4, 1);
+
+fn count(n: usize) -> u64 {
+    u64::try_from(n).unwrap_or(u64::MAX) // (MR A.2b) a saturated count is still "a lot".
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Counted<'t, T> {
+    /// A value whose arithmetic is tallied. Operations are counted whether or not they succeed; the work was done.
+    value: T,
+    tally: &'t OpTally,
+}
+
+impl<'t, T: Copy> Counted<'t, T> {
+    pub fn value(&self) -> T {
+        self.value
+    }
+
+    fn wrap<U>(&self, value: U) -> Counted<'t, U> {
+        Counted {
+            value,
+            tally: self.tally,
+        }
+    }
+
+    fn counted<U, E>(&self, counts: OpCounts, result: Result<U, E>) -> Result<Counted<'t, U>, E> {
+        self.tally.record(counts);
+        result.map(|value| self.wrap(value))
+    }
+}
+
+impl<'t, const N: u8> Counted<'t, FixedPoint<N>> {
+    pub fn checked_add(&self, rhs: FixedPoint<N>) -> Result<Self, ArithmeticError> {
+        self.counted(ops(1, 0, 0, 0), self.value.checked_add(rhs))
+    }
+
+    pub fn checked_sub(&self, rhs: FixedPoint<N>) -> Result<Self, ArithmeticError> {
+        self.counted(ops(1, 0, 0, 0), self.value.checked_sub(rhs))
+    }
+
+    pub fn checked_mul(&self, rhs: FixedPoint<N>) -> Result<Self, ArithmeticError> {
+        self.counted(ops(0, 1, 0, 0), self.value.checked_mul(rhs))
+    }
+
+    pub fn checked_div(&self, rhs: FixedPoint<N>) -> Result<Self, ArithmeticError> {
+        self.counted(ops(0, 0, 1, 0), self.value.checked_div(rhs))
+    }
+}
+
+impl<'t, const N: u8> Counted<'t, Vec3D<N>> {
+    pub fn checked_add(&self, other: &Vec3D<N>) -> Result<Self, ArithmeticError> {
+        self.counted(ops(3, 0, 0, 0), self.value.checked_add(other))
+    }
+
+    pub fn checked_sub(&self, other: &Vec3D<N>) -> Result<Self, ArithmeticError> {
+        self.counted(ops(3, 0, 0, 0), self.value.checked_sub(other))
+    }
+
+    pub fn checked_scale(&self, scale_factor: FixedPoint<N>) -> Result<Self, ArithmeticError> {
+        self.counted(ops(0, 3, 0, 0), self.value.checked_scale(scale_factor))
+    }
+
+    pub fn checked_magnitude(&self) -> Result<Counted<'t, FixedPoint<N>>, ArithmeticError> {
+        //! counted as the squares and sum it's made of, plus the root.
+        self.counted(ops(2, 3, 0, 1), self.value.checked_magnitude())
+    }
+
+    pub fn dot(&self, other: &Vec3D<N>) -> Result<Counted<'t, FixedPoint<N>>, ArithmeticError> {
+        self.counted(ops(2, 3, 0, 0), self.value.dot(other))
+    }
+
+    pub fn cross(&self, other: &Vec3D<N>) -> Result<Self, ArithmeticError> {
+        self.counted(ops(3, 6, 0, 0), self.value.cross(other))
+    }
+
+    pub fn to_unit_vector(&self) -> Result<Counted<'t, UnitVec3D>, ArithmeticError> {
+        self.counted(UNIT_VECTOR_OPS, self.value.to_unit_vector())
+    }
+}
+
+impl<'t> Counted<'t, UnitVec3D> {
+    pub fn rotation_about(
+        &self,
+        angle: UnitFp,
+    ) -> Result<Counted<'t, Quaternion>, QuaternionError> {
+        //! the rotation of angle radians about this axis, as Quaternion::from_axis_angle(): the axis made unit, the
+        //! half angle's sine and cosine, the sine spread over the axis, and a renormalisation.
+        let counts = ops(
+            UNIT_VECTOR_OPS.adds + NORMALISE_OPS.adds,
+            UNIT_VECTOR_OPS.muls + 3 + NORMALISE_OPS.muls,
+            UNIT_VECTOR_OPS.divs + NORMALISE_OPS.divs,
+            UNIT_VECTOR_OPS.magnitudes + 1 + NORMALISE_OPS.magnitudes,
+        );
+        self.counted(counts, Quaternion::from_axis_angle(self.value, angle))
+    }
+}
+
+impl<'t, const R: usize, const C: usize, const N: u8> Counted<'t, Matrix<R, C, N>> {
+    pub fn checked_add(&self, other: &Matrix<R, C, N>) -> Result<Self, ArithmeticError> {
+        self.counted(ops(count(R * C), 0, 0, 0), self.value.checked_add(other))
+    }
+
+    pub fn checked_sub(&self, other: &Matrix<R, C, N>) -> Result<Self, ArithmeticError> {
+        self.counted(ops(count(R * C), 0, 0, 0), self.value.checked_sub(other))
+    }
+
+    pub fn checked_scale(&self, scale_factor: FixedPoint<N>) -> Result<Self, ArithmeticError> {
+        self.counted(
+            ops(0, count(R * C), 0, 0),
+            self.value.checked_scale(scale_factor),
+        )
+    }
+
+    pub fn checked_mul<const K: usize>(
+        &self,
+        other: &Matrix<C, K, N>,
+    ) -> Result<Counted<'t, Matrix<R, K, N>>, ArithmeticError> {
+        //! counted as R x K dots of length C.
+        let dots = count(R * K);
+        let counts = ops(
+            dots.saturating_mul(count(C.saturating_sub(1))),
+            dots.saturating_mul(count(C)),
+            0,
+            0,
+        );
+        self.counted(counts, self.value.checked_mul(other))
+    }
+}
+
+impl<'t> Counted<'t, Quaternion> {
+    pub fn mult(&self, other: &Quaternion) -> Self {
+        //! four length-4 dots.
+        self.tally.record(ops(12, 16, 0, 0));
+        self.wrap(self.value.mult(other))
+    }
+
+    pub fn normalised(&self) -> Result<Self, QuaternionError> {
+        self.counted(NORMALISE_OPS, self.value.normalised())
+    }
+
+    pub fn rotate<const N: u8>(
+        &self,
+        vector: &Vec3D<N>,
+    ) -> Result<Counted<'t, Vec3D<N>>, ArithmeticError> {
+        //! counted as building the rotation matrix (three muls per element, with one add off the diagonal and two
+        //! on it), then three length-3 dots.
+        self.counted(ops(12 + 6, 27 + 9, 0, 0), self.value.rotate(vector))
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+    use crate::fixed_point::StepFp;
+    use crate::vec3d::StepVec3D;
+
+    #[test]
+    fn scalar_operations_are_tallied() {
+        let tally = OpTally::new();
+        let x = tally.counted(StepFp::from_int(6));
+        let result = x
+            .checked_add(StepFp::from_int(2))
+            .unwrap()
+            .checked_mul(StepFp::from_int(3))
+            .unwrap()
+            .checked_div(StepFp::from_int(4))
+            .unwrap()
+            .checked_sub(StepFp::from_int(1))
+            .unwrap();
+        assert_eq!(result.value(), StepFp::from_int(5));
+        assert_eq!(tally.counts(), ops(2, 1, 1, 0));
+    }
+
+    #[test]
+    fn vector_operations_count_their_scalar_parts() {
+        let tally = OpTally::new();
+        let v = tally.counted(StepVec3D::from_floats(3.0, 4.0, 0.0).unwrap());
+        let other = StepVec3D::from_floats(1.0, 0.0, 0.0).unwrap();
+        assert_eq!(v.checked_magnitude().unwrap().value(), StepFp::from_int(5));
+        assert_eq!(v.dot(&other).unwrap().value(), StepFp::from_int(3));
+        v.cross(&other).unwrap();
+        v.checked_add(&other)
+            .unwrap()
+            .checked_scale(StepFp::from_int(2))
+            .unwrap();
+        assert_eq!(tally.counts(), ops(2 + 2 + 3 + 3, 3 + 3 + 6 + 3, 0, 1));
+    }
+
+    #[test]
+    fn matrix_products_count_their_dots() {
+        let tally = OpTally::new();
+        let a = tally.counted(Matrix::<2, 3, 20>::zero());
+        let b = Matrix::<3, 4, 20>::zero();
+        a.checked_mul(&b).unwrap();
+        assert_eq!(tally.reset(), ops(2 * 4 * 2, 2 * 4 * 3, 0, 0));
+        a.checked_add(&Matrix::zero())
+            .unwrap()
+            .checked_scale(FixedPoint::from_int(2))
+            .unwrap();
+        assert_eq!(tally.counts(), ops(6, 6, 0, 0));
+    }
+
+    #[test]
+    fn rotations_are_tallied() {
+        let tally = OpTally::new();
+        let axis = tally.counted(UnitVec3D::from_floats(0.0, 0.0, 2.0).unwrap());
+        let turn = axis.rotation_about(UnitFp::FRAC_PI_2).unwrap();
+        assert_eq!(tally.reset(), ops(5, 10, 7, 3));
+        let x = StepVec3D::from_floats(1.0, 0.0, 0.0).unwrap();
+        let rotated = turn.rotate(&x).unwrap().value();
+        assert!((rotated.1.to_f64() - 1.0).abs() < 1e-6);
+        turn.mult(&Quaternion::IDENTITY).normalised().unwrap();
+        assert_eq!(tally.counts(), ops(18 + 12 + 3, 36 + 16 + 4, 4, 1));
+    }
+
+    #[test]
+    fn failures_still_count() {
+        let tally = OpTally::new();
+        let x = tally.counted(StepFp::from_int(1));
+        assert!(x.checked_div(StepFp::from_int(0)).is_err());
+        assert_eq!(tally.counts(), ops(0, 0, 1, 0));
+    }
+
+    #[test]
+    fn reset_returns_counts() {
+        let tally = OpTally::new();
+        tally
+            .counted(StepFp::from_int(1))
+            .checked_add(StepFp::from_int(1))
+            .unwrap();
+        assert_eq!(tally.reset(), ops(1, 0, 0, 0));
+        assert_eq!(tally.counts(), OpCounts::default());
+    }
+
+    #[test]
+    fn costs_from_table() {
+        let counts = ops(10, 2, 1, 1);
+        assert_eq!(counts.cost(&CostTable::AGC), 10 + 6 + 6 + 40);
+        let saturating = ops(u64::MAX, 1, 0, 0);
+        assert_eq!(saturating.cost(&CostTable::AGC), u64::MAX);
+    }
+}
@@ -10,6 +10,7 @@ use std::{
 pub(crate) const UNIT_FIXED_POINT_DECIMAL_BITS: u8 = 60;
 pub(crate) const STEP_FIXED_POINT_DECIMAL_BITS: u8 = 40;
 pub(crate) const SOLAR_FIXED_POINT_DECIMAL_BITS: u8 = 6; // bounded by Jupiter GM.
+pub(crate) const TIME_FIXED_POINT_DECIMAL_BITS: u8 = 20; // ~1us resolution, ~280k years of range.
 
 pub type UnitFp = FixedPoint<UNIT_FIXED_POINT_DECIMAL_BITS>;
 pub type StepFp = FixedPoint<STEP_FIXED_POINT_DECIMAL_BITS>;
@@ -30,6 +31,134 @@ pub enum FloatConversionError {
     OutOfBounds,
 }
 
+/// the operation being attempted when an ArithmeticError occurred.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ArithmeticOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Sqrt,
+    Asin,
+    Acos,
+    Dot,
+    Cross,
+    ScaleByUnit,
+}
+
+/// produced by the checked_* family of methods. Carries the raw internal operands and the scale (N) so the failing
+/// calculation can be reconstructed from a log line.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ArithmeticError {
+    Overflow {
+        operation: ArithmeticOp,
+        lhs: i64,
+        rhs: i64,
+        decimal_bits: u8,
+    },
+    DivisionByZero {
+        dividend: i64,
+        decimal_bits: u8,
+    },
+    MagnitudeOverflow {
+        components: (i64, i64, i64),
+        decimal_bits: u8,
+    },
+    VectorOverflow {
+        operation: ArithmeticOp,
+        lhs: (i64, i64, i64),
+        rhs: (i64, i64, i64),
+        decimal_bits: u8,
+    },
+    ZeroVector {
+        decimal_bits: u8,
+    },
+    OutOfDomain {
+        operation: ArithmeticOp,
+        operand: i64,
+        decimal_bits: u8,
+    },
+    ConversionOverflow {
+        internal: i64,
+        from_bits: u8,
+        to_bits: u8,
+    },
+    PrecisionLost {
+        internal: i64,
+        from_bits: u8,
+        to_bits: u8,
+        lost_internal: i64, // the dropped low bits, at the source scale.
+    },
+}
+
+impl Display for ArithmeticError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ArithmeticError::Overflow {
+                operation,
+                lhs,
+                rhs,
+                decimal_bits,
+            } => write!(
+                f,
+                "{operation:?} overflowed on FixedPoint<{decimal_bits}>: internals {lhs} and {rhs}"
+            ),
+            ArithmeticError::DivisionByZero {
+                dividend,
+                decimal_bits,
+            } => write!(
+                f,
+                "division by zero on FixedPoint<{decimal_bits}>: dividend internal {dividend}"
+            ),
+            ArithmeticError::MagnitudeOverflow {
+                components,
+                decimal_bits,
+            } => write!(
+                f,
+                "magnitude overflowed on Vec3D<{decimal_bits}>: internals {components:?}"
+            ),
+            ArithmeticError::VectorOverflow {
+                operation,
+                lhs,
+                rhs,
+                decimal_bits,
+            } => write!(
+                f,
+                "{operation:?} overflowed on Vec3D<{decimal_bits}>: internals {lhs:?} and {rhs:?}"
+            ),
+            ArithmeticError::ZeroVector { decimal_bits } => write!(
+                f,
+                "zero-length Vec3D<{decimal_bits}> has no direction"
+            ),
+            ArithmeticError::OutOfDomain {
+                operation,
+                operand,
+                decimal_bits,
+            } => write!(
+                f,
+                "{operation:?} undefined for FixedPoint<{decimal_bits}> with internal {operand}"
+            ),
+            ArithmeticError::ConversionOverflow {
+                internal,
+                from_bits,
+                to_bits,
+            } => write!(
+                f,
+                "FixedPoint<{from_bits}> internal {internal} too large for FixedPoint<{to_bits}>"
+            ),
+            ArithmeticError::PrecisionLost {
+                internal,
+                from_bits,
+                to_bits,
+                lost_internal,
+            } => write!(
+                f,
+                "FixedPoint<{from_bits}> internal {internal} loses {lost_internal} converting to FixedPoint<{to_bits}>"
+            ),
+        }
+    }
+}
+
 impl<const N: u8> FixedPoint<N> {
     pub fn from_int(int: i64) -> Self {
         // creates a FixedPoint which REPRESENTS the selected int. To create one with a specified stored int, use with_internal().
@@ -41,6 +170,11 @@ impl<const N: u8> FixedPoint<N> {
         Self(int)
     }
 
+    pub const fn internal(&self) -> i64 {
+        // The stored internal value; the inverse of with_internal(). Used for randomising values within bounds.
+        self.0
+    }
+
     pub const fn from_f64(float: f64) -> Result<Self, FloatConversionError> {
         // handle trivial cases - subnormal is *significantly* below the finest precision.
         if float == 0.0 || float.is_subnormal() {
@@ -164,6 +298,53 @@ impl<const N: u8> FixedPoint<N> {
         }
     }
 
+    pub fn convert<const M: u8>(self) -> Result<FixedPoint<M>, ArithmeticError> {
+        //! re-expresses self at scale M. Errors if the value can't be held at the new scale.
+        //! Narrowing (M < N) drops low bits, rounding towards negative infinity; see convert_lossless() to forbid that.
+        self.convert_with_remainder()
+            .map(|(converted, _)| converted)
+    }
+
+    pub fn convert_lossless<const M: u8>(self) -> Result<FixedPoint<M>, ArithmeticError> {
+        //! as convert(), but also errors if any precision would be dropped. The error reports the dropped part.
+        let (converted, remainder) = self.convert_with_remainder::<M>()?;
+        match remainder.0 {
+            0 => Ok(converted),
+            lost_internal => Err(ArithmeticError::PrecisionLost {
+                internal: self.0,
+                from_bits: N,
+                to_bits: M,
+                lost_internal,
+            }),
+        }
+    }
+
+    pub fn convert_with_remainder<const M: u8>(
+        self,
+    ) -> Result<(FixedPoint<M>, Self), ArithmeticError> {
+        //! re-expresses self at scale M, also returning the part that didn't fit (at scale N, always >= 0).
+        //! converted + remainder == self exactly.
+        let overflow = ArithmeticError::ConversionOverflow {
+            internal: self.0,
+            from_bits: N,
+            to_bits: M,
+        };
+        if M >= N {
+            // widening: shift left, checking no significant bits (including the sign) are pushed out.
+            let shift = u32::from(M - N);
+            let shifted = self.0.checked_shl(shift).ok_or(overflow)?;
+            if shifted >> shift != self.0 {
+                return Err(overflow);
+            }
+            Ok((FixedPoint(shifted), Self(0)))
+        } else {
+            // narrowing: can never overflow, but drops the low (N - M) bits.
+            let shift = N - M;
+            let mask = (1i64 << shift) - 1;
+            Ok((FixedPoint(self.0 >> shift), Self(self.0 & mask)))
+        }
+    }
+
     pub fn div_by_solar(self, divisor: &SolarFp) -> Self {
         //! divide an FP of any type by a SolarFp. Needed for acceleration calculations.
         //! Implementation assumes SolarFP is the largest type.
@@ -179,6 +360,113 @@ impl<const N: u8> FixedPoint<N> {
     }
 }
 
+/// Checked, saturating and wrapping arithmetic. These never panic, and exist so that flight code can comply with MR B.1/B.3.
+/// The std::ops implementations further down panic on overflow and are retained for the physics engine.
+impl<const N: u8> FixedPoint<N> {
+    fn overflow(self, operation: ArithmeticOp, rhs: Self) -> ArithmeticError {
+        //! builds the diagnostic error for an overflowing operation between self and rhs.
+        ArithmeticError::Overflow {
+            operation,
+            lhs: self.0,
+            rhs: rhs.0,
+            decimal_bits: N,
+        }
+    }
+
+    fn wide_mul(self, rhs: Self) -> i128 {
+        //! the full-width product of two internals, rescaled to N. Cannot overflow: |i64 * i64| < 2^126.
+        (self.0 as i128 * rhs.0 as i128) >> N // (MR A.2a) i64 -> i128 direct superset.
+    }
+
+    fn wide_div(self, rhs: Self) -> Option<i128> {
+        //! the full-width quotient of two internals, rescaled to N. None if rhs is zero.
+        //! The shift cannot overflow: i64 << N with N < 64 needs at most 127 bits.
+        let wide_self = (self.0 as i128) << N; // (MR A.2a) i64 -> i128 direct superset.
+        wide_self.checked_div(rhs.0 as i128) // (MR A.2a) i64 -> i128 direct superset.
+    }
+
+    pub fn checked_add(self, rhs: Self) -> Result<Self, ArithmeticError> {
+        self.0
+            .checked_add(rhs.0)
+            .map(Self)
+            .ok_or(self.overflow(ArithmeticOp::Add, rhs))
+    }
+
+    pub fn checked_sub(self, rhs: Self) -> Result<Self, ArithmeticError> {
+        self.0
+            .checked_sub(rhs.0)
+            .map(Self)
+            .ok_or(self.overflow(ArithmeticOp::Sub, rhs))
+    }
+
+    pub fn checked_mul(self, rhs: Self) -> Result<Self, ArithmeticError> {
+        i64::try_from(self.wide_mul(rhs))
+            .map(Self)
+            .map_err(|_| self.overflow(ArithmeticOp::Mul, rhs))
+    }
+
+    pub fn checked_div(self, rhs: Self) -> Result<Self, ArithmeticError> {
+        let wide_ans = self.wide_div(rhs).ok_or(ArithmeticError::DivisionByZero {
+            dividend: self.0,
+            decimal_bits: N,
+        })?;
+        i64::try_from(wide_ans)
+            .map(Self)
+            .map_err(|_| self.overflow(ArithmeticOp::Div, rhs))
+    }
+
+    pub fn saturating_add(self, rhs: Self) -> Self {
+        Self(self.0.saturating_add(rhs.0))
+    }
+
+    pub fn saturating_sub(self, rhs: Self) -> Self {
+        Self(self.0.saturating_sub(rhs.0))
+    }
+
+    pub fn saturating_mul(self, rhs: Self) -> Self {
+        Self(saturate_wide(self.wide_mul(rhs)))
+    }
+
+    pub fn saturating_div(self, rhs: Self) -> Self {
+        //! division by zero saturates towards the sign of self (0 / 0 gives 0).
+        match self.wide_div(rhs) {
+            Some(wide_ans) => Self(saturate_wide(wide_ans)),
+            None => Self(match self.0.signum() {
+                1 => i64::MAX,
+                -1 => i64::MIN,
+                _ => 0,
+            }),
+        }
+    }
+
+    pub fn wrapping_add(self, rhs: Self) -> Self {
+        Self(self.0.wrapping_add(rhs.0))
+    }
+
+    pub fn wrapping_sub(self, rhs: Self) -> Self {
+        Self(self.0.wrapping_sub(rhs.0))
+    }
+
+    pub fn wrapping_mul(self, rhs: Self) -> Self {
+        //! keeps the low 64 bits of the rescaled product.
+        Self(self.wide_mul(rhs) as i64) // (MR A.2b) truncation is the explicitly requested behaviour.
+    }
+
+    pub fn wrapping_div(self, rhs: Self) -> Self {
+        //! keeps the low 64 bits of the rescaled quotient. Division by zero has no wrapped value, so it saturates
+        //! as in saturating_div.
+        match self.wide_div(rhs) {
+            Some(wide_ans) => Self(wide_ans as i64), // (MR A.2b) truncation is the explicitly requested behaviour.
+            None => self.saturating_div(rhs),
+        }
+    }
+}
+
+fn saturate_wide(wide: i128) -> i64 {
+    //! clamps a wide intermediate into the i64 range.
+    wide.clamp(i64::MIN as i128, i64::MAX as i128) as i64 // (MR A.2b) clamped to i64 range on the same line.
+}
+
 impl UnitFp {
     pub fn scale_by_other<const N: u8>(self, scalar: FixedPoint<N>) -> FixedPoint<N> {
         //! used in the scaling of unit vectors. Scales by a scalar of varying type, spits that type back out.
@@ -188,6 +476,22 @@ impl UnitFp {
         let internal = (internal_wide >> UNIT_FIXED_POINT_DECIMAL_BITS) as i64;
         FixedPoint::<N>(internal)
     }
+
+    pub fn checked_scale_by_other<const N: u8>(
+        self,
+        scalar: FixedPoint<N>,
+    ) -> Result<FixedPoint<N>, ArithmeticError> {
+        //! panic-free scale_by_other().
+        let internal_wide = self.0 as i128 * scalar.0 as i128; // (MR A.2a) i64 -> i128 direct superset.
+        i64::try_from(internal_wide >> UNIT_FIXED_POINT_DECIMAL_BITS)
+            .map(FixedPoint)
+            .map_err(|_| ArithmeticError::Overflow {
+                operation: ArithmeticOp::ScaleByUnit,
+                lhs: self.0,
+                rhs: scalar.0,
+                decimal_bits: N,
+            })
+    }
 }
 
 impl<const N: u8> Add for FixedPoint<N> {
@@ -283,7 +587,7 @@ impl<const N: u8> std::fmt::Debug for FixedPoint<N> {
 #[cfg(test)]
 #[allow(clippy::unwrap_used)] // this is test code.
 mod tests {
-    use super::FixedPoint;
+    use super::{ArithmeticError, ArithmeticOp, FixedPoint};
     type TestFp = FixedPoint<56>; // slightly larger integer range to keep testing easy.
 
     #[test]
@@ -336,6 +640,129 @@ mod tests {
         assert_eq!(TestFp::from_f64(1.5).unwrap().to_f64(), 1.5f64)
     }
 
+    #[test]
+    fn checked_happy_path() {
+        let (a, b) = (TestFp::from_int(6), TestFp::from_int(2));
+        assert_eq!(a.checked_add(b), Ok(TestFp::from_int(8)));
+        assert_eq!(a.checked_sub(b), Ok(TestFp::from_int(4)));
+        assert_eq!(a.checked_mul(b), Ok(TestFp::from_int(12)));
+        assert_eq!(a.checked_div(b), Ok(TestFp::from_int(3)));
+    }
+
+    #[test]
+    fn checked_overflow() {
+        let max = TestFp::with_internal(i64::MAX);
+        let one = TestFp::from_int(1);
+        let two = TestFp::from_int(2);
+        assert_eq!(
+            max.checked_add(one),
+            Err(ArithmeticError::Overflow {
+                operation: ArithmeticOp::Add,
+                lhs: i64::MAX,
+                rhs: 1 << 56,
+                decimal_bits: 56
+            })
+        );
+        assert!(TestFp::with_internal(i64::MIN).checked_sub(one).is_err());
+        assert!(max.checked_mul(two).is_err());
+        assert!(max.checked_div(one.rshift(4)).is_err());
+    }
+
+    #[test]
+    fn checked_div_by_zero() {
+        assert_eq!(
+            TestFp::from_int(3).checked_div(TestFp::from_int(0)),
+            Err(ArithmeticError::DivisionByZero {
+                dividend: 3 << 56,
+                decimal_bits: 56
+            })
+        );
+    }
+
+    #[test]
+    fn saturating() {
+        let max = TestFp::with_internal(i64::MAX);
+        let min = TestFp::with_internal(i64::MIN);
+        let two = TestFp::from_int(2);
+        assert_eq!(max.saturating_add(two), max);
+        assert_eq!(min.saturating_sub(two), min);
+        assert_eq!(max.saturating_mul(two), max);
+        assert_eq!(max.saturating_mul(-two), min);
+        assert_eq!(two.saturating_div(TestFp::from_int(0)), max);
+        assert_eq!((-two).saturating_div(TestFp::from_int(0)), min);
+        assert_eq!(
+            TestFp::from_int(0).saturating_div(TestFp::from_int(0)),
+            TestFp::from_int(0)
+        );
+        assert_eq!(two.saturating_mul(two), TestFp::from_int(4));
+    }
+
+    #[test]
+    fn wrapping() {
+        let max = TestFp::with_internal(i64::MAX);
+        let min = TestFp::with_internal(i64::MIN);
+        assert_eq!(max.wrapping_add(TestFp::with_internal(1)), min);
+        assert_eq!(min.wrapping_sub(TestFp::with_internal(1)), max);
+        assert_eq!(
+            TestFp::from_int(3).wrapping_mul(TestFp::from_int(2)),
+            TestFp::from_int(6)
+        );
+        assert_eq!(
+            TestFp::from_int(6).wrapping_div(TestFp::from_int(2)),
+            TestFp::from_int(3)
+        );
+        assert_eq!(TestFp::from_int(1).wrapping_div(TestFp::from_int(0)), max);
+    }
+
+    #[test]
+    fn convert_widening() {
+        let solar = FixedPoint::<6>::from_f64(-1234.5).unwrap();
+        let step: FixedPoint<40> = solar.convert().unwrap();
+        assert_eq!(step.to_f64(), -1234.5);
+        assert_eq!(solar.convert_lossless::<40>(), Ok(step));
+    }
+
+    #[test]
+    fn convert_widening_overflow() {
+        // 1000 fits in a SolarFp but not a UnitFp (+/- 8).
+        let solar = FixedPoint::<6>::from_int(1000);
+        assert_eq!(
+            solar.convert::<60>(),
+            Err(ArithmeticError::ConversionOverflow {
+                internal: 1000 << 6,
+                from_bits: 6,
+                to_bits: 60
+            })
+        );
+        assert!(FixedPoint::<6>::from_int(-1000).convert::<60>().is_err());
+        // boundary: largest value that still fits.
+        assert!(FixedPoint::<56>::from_int(7).convert::<60>().is_ok());
+        assert!(FixedPoint::<56>::from_int(8).convert::<60>().is_err());
+        assert!(FixedPoint::<56>::from_int(-8).convert::<60>().is_ok());
+    }
+
+    #[test]
+    fn convert_narrowing() {
+        let fine = FixedPoint::<40>::from_f64(2.0 + 1.0 / 128.0).unwrap();
+        let (coarse, remainder) = fine.convert_with_remainder::<6>().unwrap();
+        assert_eq!(coarse, FixedPoint::<6>::from_int(2));
+        assert_eq!(remainder.to_f64(), 1.0 / 128.0);
+        assert_eq!(
+            fine.convert_lossless::<6>(),
+            Err(ArithmeticError::PrecisionLost {
+                internal: fine.0,
+                from_bits: 40,
+                to_bits: 6,
+                lost_internal: 1 << 33
+            })
+        );
+        // negative values round down, leaving a positive remainder.
+        let negative = FixedPoint::<40>::from_f64(-1.0 / 128.0).unwrap();
+        let (coarse, remainder) = negative.convert_with_remainder::<6>().unwrap();
+        assert_eq!(coarse.to_f64(), -1.0 / 64.0);
+        assert_eq!(remainder.to_f64(), 1.0 / 128.0);
+    }
+
     #[test]
     fn abs() {
         assert_eq!(
@@ -1,10 +1,19 @@
+mod counted;
 mod fixed_point;
+mod matrix;
 mod quaternion;
+mod sim_time;
+mod trigonometry;
 mod vec3d;
 
-pub use fixed_point::{FixedPoint, FloatConversionError, SolarFp, StepFp, UnitFp};
-pub use quaternion::Quaternion;
-pub use vec3d::{PrintType, SolarVec3D, StepVec3D, UnitVec3D};
+pub use counted::{CostTable, Counted, OpCounts, OpTally};
+pub use fixed_point::{
+    ArithmeticError, ArithmeticOp, FixedPoint, FloatConversionError, SolarFp, StepFp, UnitFp,
+};
+pub use matrix::{Mat3, Matrix, MatrixError};
+pub use quaternion::{EulerAngles, Quaternion, QuaternionError, RotationMatrix};
+pub use sim_time::{SimClock, SimClockReader, SimTime, TimeFp};
+pub use vec3d::{PrintType, SolarVec3D, StepVec3D, UnitVec3D, Vec3D};
 
 // this is for testing!
 //mod vec3d_f64;
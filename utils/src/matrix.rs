@@ -0,0 +1,381 @@
+//! Small fixed point matrices. Used for rotation matrices, inertia tensors, frame transforms and filter covariances.
+//! Dimensions are const generics so every matrix lives on the stack (MR F.1); a 6x6 StepFp covariance is 288B.
+//! All arithmetic is checked; products are accumulated at i128 width before rescaling, as in Vec3D::dot().
+use crate::fixed_point::{ArithmeticError, ArithmeticOp, FixedPoint};
+use crate::vec3d::Vec3D;
+
+/// R rows by C columns of FixedPoint<N>, stored row-major.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Matrix<const R: usize, const C: usize, const N: u8>(pub [[FixedPoint<N>; C]; R]);
+
+pub type Mat3<const N: u8> = Matrix<3, 3, N>;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MatrixError {
+    Singular,            // no inverse exists; a pivot was exactly zero.
+    NotPositiveDefinite, // Cholesky met a non-positive diagonal; the matrix isn't a valid covariance.
+    Arithmetic(ArithmeticError),
+}
+
+impl From<ArithmeticError> for MatrixError {
+    fn from(value: ArithmeticError) -> Self {
+        MatrixError::Arithmetic(value)
+    }
+}
+
+impl<const R: usize, const C: usize, const N: u8> Default for Matrix<R, C, N> {
+    fn default() -> Self {
+        Self::zero()
+    }
+}
+
+impl<const R: usize, const C: usize, const N: u8> Matrix<R, C, N> {
+    pub fn zero() -> Self {
+        Self([[FixedPoint(0); C]; R])
+    }
+
+    pub fn get(&self, row: usize, column: usize) -> Option<FixedPoint<N>> {
+        //! bounds-checked element access.
+        self.0.get(row).and_then(|r| r.get(column)).copied()
+    }
+
+    pub fn transpose(&self) -> Matrix<C, R, N> {
+        let mut out = Matrix::<C, R, N>::zero();
+        for (r, row) in self.0.iter().enumerate() {
+            for (c, value) in row.iter().enumerate() {
+                out.0[c][r] = *value;
+            }
+        }
+        out
+    }
+
+    pub fn checked_add(&self, other: &Self) -> Result<Self, ArithmeticError> {
+        //! element-wise sum.
+        self.zip_with(other, FixedPoint::checked_add)
+    }
+
+    pub fn checked_sub(&self, other: &Self) -> Result<Self, ArithmeticError> {
+        //! element-wise difference.
+        self.zip_with(other, FixedPoint::checked_sub)
+    }
+
+    pub fn checked_scale(&self, scale_factor: FixedPoint<N>) -> Result<Self, ArithmeticError> {
+        //! multiplies every element by scale_factor.
+        let mut out = *self;
+        for value in out.0.iter_mut().flatten() {
+            *value = value.checked_mul(scale_factor)?;
+        }
+        Ok(out)
+    }
+
+    fn zip_with(
+        &self,
+        other: &Self,
+        operation: fn(FixedPoint<N>, FixedPoint<N>) -> Result<FixedPoint<N>, ArithmeticError>,
+    ) -> Result<Self, ArithmeticError> {
+        //! applies a checked element-wise operation to each pair of matching elements.
+        let mut out = *self;
+        for (out_row, other_row) in out.0.iter_mut().zip(other.0.iter()) {
+            for (value, other_value) in out_row.iter_mut().zip(other_row.iter()) {
+                *value = operation(*value, *other_value)?;
+            }
+        }
+        Ok(out)
+    }
+
+    pub fn checked_mul<const K: usize>(
+        &self,
+        other: &Matrix<C, K, N>,
+    ) -> Result<Matrix<R, K, N>, ArithmeticError> {
+        //! matrix product self * other.
+        let mut out = Matrix::<R, K, N>::zero();
+        for (r, row) in self.0.iter().enumerate() {
+            for k in 0..K {
+                let column = other.0.iter().map(|other_row| other_row[k]);
+                out.0[r][k] = wide_dot(row.iter().copied(), column)?;
+            }
+        }
+        Ok(out)
+    }
+}
+
+fn wide_dot<const N: u8>(
+    lhs: impl Iterator<Item = FixedPoint<N>>,
+    rhs: impl Iterator<Item = FixedPoint<N>>,
+) -> Result<FixedPoint<N>, ArithmeticError> {
+    //! sum of products at i128 width, rescaled once at the end. Errors on overflow, reporting the first pair.
+    let mut sum: i128 = 0;
+    let mut first = (0, 0);
+    for (i, (a, b)) in lhs.zip(rhs).enumerate() {
+        if i == 0 {
+            first = (a.0, b.0);
+        }
+        let product = a.0 as i128 * b.0 as i128; // (MR A.2a) i64 -> i128 direct superset; < 2^126.
+        sum = sum.checked_add(product).ok_or(ArithmeticError::Overflow {
+            operation: ArithmeticOp::Dot,
+            lhs: a.0,
+            rhs: b.0,
+            decimal_bits: N,
+        })?;
+    }
+    i64::try_from(sum >> N)
+        .map(FixedPoint)
+        .map_err(|_| ArithmeticError::Overflow {
+            operation: ArithmeticOp::Dot,
+            lhs: first.0,
+            rhs: first.1,
+            decimal_bits: N,
+        })
+}
+
+impl<const R: usize, const N: u8> Matrix<R, R, N> {
+    pub fn identity() -> Self {
+        let mut out = Self::zero();
+        for (i, row) in out.0.iter_mut().enumerate() {
+            row[i] = FixedPoint(1 << N);
+        }
+        out
+    }
+
+    fn pivot_row(&self, column: usize) -> usize {
+        //! the row at or below the diagonal with the largest magnitude in the given column (partial pivoting).
+        let mut best = column;
+        for row in column..R {
+            if self.0[row][column].abs() > self.0[best][column].abs() {
+                best = row;
+            }
+        }
+        best
+    }
+
+    fn eliminate_below(&mut self, column: usize) -> Result<(), ArithmeticError> {
+        //! subtracts multiples of the pivot row from every row below it, zeroing the column under the diagonal.
+        let pivot_row = self.0[column];
+        for row in self.0.iter_mut().skip(column + 1) {
+            let factor = row[column].checked_div(pivot_row[column])?;
+            for (value, pivot_value) in row.iter_mut().zip(pivot_row.iter()).skip(column) {
+                *value = value.checked_sub(factor.checked_mul(*pivot_value)?)?;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn determinant(&self) -> Result<FixedPoint<N>, ArithmeticError> {
+        //! determinant by Gaussian elimination with partial pivoting.
+        let mut working = *self;
+        let mut determinant = FixedPoint::<N>(1 << N);
+        for column in 0..R {
+            let pivot = working.pivot_row(column);
+            if working.0[pivot][column].0 == 0 {
+                return Ok(FixedPoint(0));
+            }
+            if pivot != column {
+                working.0.swap(pivot, column);
+                determinant = -determinant;
+            }
+            working.eliminate_below(column)?;
+            determinant = determinant.checked_mul(working.0[column][column])?;
+        }
+        Ok(determinant)
+    }
+
+    pub fn inverse(&self) -> Result<Self, MatrixError> {
+        //! inverse by Gauss-Jordan elimination with partial pivoting. Errors if the matrix is singular, or if the
+        //! inverse is too large to be held at scale N.
+        let mut working = *self;
+        let mut inverse = Self::identity();
+        for column in 0..R {
+            let pivot = working.pivot_row(column);
+            let pivot_value = working.0[pivot][column];
+            if pivot_value.0 == 0 {
+                return Err(MatrixError::Singular);
+            }
+            working.0.swap(pivot, column);
+            inverse.0.swap(pivot, column);
+
+            // scale the pivot row to put a 1 on the diagonal.
+            for value in working.0[column]
+                .iter_mut()
+                .chain(inverse.0[column].iter_mut())
+            {
+                *value = value.checked_div(pivot_value)?;
+            }
+            // then clear the column from every other row.
+            let (working_pivot, inverse_pivot) = (working.0[column], inverse.0[column]);
+            for row in (0..R).filter(|row| *row != column) {
+                let factor = working.0[row][column];
+                for k in 0..R {
+                    working.0[row][k] =
+                        working.0[row][k].checked_sub(factor.checked_mul(working_pivot[k])?)?;
+                    inverse.0[row][k] =
+                        inverse.0[row][k].checked_sub(factor.checked_mul(inverse_pivot[k])?)?;
+                }
+            }
+        }
+        Ok(inverse)
+    }
+
+    pub fn cholesky(&self) -> Result<Self, MatrixError> {
+        //! lower triangular L such that L * L^T == self. Only the lower triangle of self is read, so self is assumed
+        //! symmetric. Errors if self is not positive definite.
+        let mut lower = Self::zero();
+        for i in 0..R {
+            for j in 0..=i {
+                let partial = wide_dot(
+                    lower.0[i].iter().take(j).copied(),
+                    lower.0[j].iter().take(j).copied(),
+                )?;
+                let remainder = self.0[i][j].checked_sub(partial)?;
+                lower.0[i][j] = if i == j {
+                    if remainder.0 <= 0 {
+                        return Err(MatrixError::NotPositiveDefinite);
+                    }
+                    remainder.sqrt()?
+                } else {
+                    remainder.checked_div(lower.0[j][j])?
+                };
+            }
+        }
+        Ok(lower)
+    }
+}
+
+impl<const N: u8> Mat3<N> {
+    pub fn from_rows(rows: [Vec3D<N>; 3]) -> Self {
+        let [a, b, c] = rows;
+        Self([[a.0, a.1, a.2], [b.0, b.1, b.2], [c.0, c.1, c.2]])
+    }
+
+    pub fn checked_mul_vec(&self, vector: &Vec3D<N>) -> Result<Vec3D<N>, ArithmeticError> {
+        //! matrix-vector product self * vector.
+        let column = [vector.0, vector.1, vector.2];
+        let row = |r: &[FixedPoint<N>; 3]| wide_dot(r.iter().copied(), column.iter().copied());
+        Ok(Vec3D(row(&self.0[0])?, row(&self.0[1])?, row(&self.0[2])?))
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+    use crate::fixed_point::StepFp;
+
+    type TestMat3 = Mat3<40>;
+
+    fn fp(value: f64) -> StepFp {
+        StepFp::from_f64(value).unwrap()
+    }
+
+    fn mat3(rows: [[f64; 3]; 3]) -> TestMat3 {
+        Matrix(rows.map(|row| row.map(fp)))
+    }
+
+    fn close<const R: usize, const C: usize>(a: &Matrix<R, C, 40>, b: &Matrix<R, C, 40>) -> bool {
+        a.0.iter()
+            .flatten()
+            .zip(b.0.iter().flatten())
+            .all(|(x, y)| (*x - *y).abs().to_f64() < 1e-9)
+    }
+
+    #[test]
+    fn multiply_and_transpose() {
+        let a = Matrix::<2, 3, 40>([[fp(1.0), fp(2.0), fp(3.0)], [fp(4.0), fp(5.0), fp(6.0)]]);
+        let product = a.checked_mul(&a.transpose()).unwrap();
+        assert_eq!(
+            product,
+            Matrix([[fp(14.0), fp(32.0)], [fp(32.0), fp(77.0)]])
+        );
+        assert_eq!(a.transpose().transpose(), a);
+        let identity = TestMat3::identity();
+        let m = mat3([[1.0, 2.0, 0.5], [0.0, -1.0, 3.0], [2.0, 2.0, 2.0]]);
+        assert_eq!(m.checked_mul(&identity), Ok(m));
+    }
+
+    #[test]
+    fn add_sub_scale() {
+        let m = mat3([[1.0, 2.0, 0.5], [0.0, -1.0, 3.0], [2.0, 2.0, 2.0]]);
+        let doubled = m.checked_scale(fp(2.0)).unwrap();
+        assert_eq!(m.checked_add(&m), Ok(doubled));
+        assert_eq!(doubled.checked_sub(&m), Ok(m));
+        assert_eq!(m.get(1, 2), Some(fp(3.0)));
+        assert_eq!(m.get(3, 0), None);
+    }
+
+    #[test]
+    fn overflow() {
+        let big = mat3([[5e6, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]]);
+        assert!(big.checked_mul(&big).is_err());
+        assert!(big.checked_add(&big).is_err());
+        assert!(big.checked_scale(fp(4.0)).is_err());
+        assert!(big
+            .checked_mul_vec(&Vec3D(fp(4.0), fp(0.0), fp(0.0)))
+            .is_err());
+    }
+
+    #[test]
+    fn matrix_vector() {
+        // a quarter turn about z
+        let m = mat3([[0.0, -1.0, 0.0], [1.0, 0.0, 0.0], [0.0, 0.0, 1.0]]);
+        let v = Vec3D(fp(3.0), fp(4.0), fp(5.0));
+        assert_eq!(m.checked_mul_vec(&v), Ok(Vec3D(fp(-4.0), fp(3.0), fp(5.0))));
+        let rows = Mat3::from_rows([v, v, v]);
+        assert_eq!(rows.get(2, 1), Some(fp(4.0)));
+    }
+
+    #[test]
+    fn determinant() {
+        let m = mat3([[2.0, 0.0, 1.0], [1.0, 3.0, 2.0], [1.0, 1.0, 2.0]]);
+        assert!((m.determinant().unwrap().to_f64() - 6.0).abs() < 1e-9);
+        // a zero leading element forces a row swap, flipping the sign.
+        let swapped = mat3([[0.0, 1.0, 0.0], [1.0, 0.0, 0.0], [0.0, 0.0, 1.0]]);
+        assert_eq!(swapped.determinant(), Ok(fp(-1.0)));
+        let singular = mat3([[1.0, 2.0, 3.0], [2.0, 4.0, 6.0], [1.0, 1.0, 1.0]]);
+        assert_eq!(singular.determinant(), Ok(fp(0.0)));
+    }
+
+    #[test]
+    fn inverse() {
+        let m = mat3([[2.0, 0.0, 1.0], [1.0, 3.0, 2.0], [1.0, 1.0, 2.0]]);
+        let inverse = m.inverse().unwrap();
+        assert!(close(
+            &m.checked_mul(&inverse).unwrap(),
+            &TestMat3::identity()
+        ));
+        // det is 6, so inverse[0][0] is the (0, 0) cofactor (3*2 - 2*1) over 6.
+        assert!((inverse.0[0][0].to_f64() - 4.0 / 6.0).abs() < 1e-9);
+
+        let singular = mat3([[1.0, 2.0, 3.0], [2.0, 4.0, 6.0], [1.0, 1.0, 1.0]]);
+        assert_eq!(singular.inverse(), Err(MatrixError::Singular));
+    }
+
+    #[test]
+    fn inverse_larger() {
+        let mut m = Matrix::<5, 5, 40>::identity()
+            .checked_scale(fp(4.0))
+            .unwrap();
+        m.0[0][4] = fp(1.0);
+        m.0[4][0] = fp(1.0);
+        m.0[2][3] = fp(-0.5);
+        let product = m.checked_mul(&m.inverse().unwrap()).unwrap();
+        assert!(close(&product, &Matrix::<5, 5, 40>::identity()));
+    }
+
+    #[test]
+    fn cholesky() {
+        let covariance = mat3([
+            [4.0, 12.0, -16.0],
+            [12.0, 37.0, -43.0],
+            [-16.0, -43.0, 98.0],
+        ]);
+        let lower = covariance.cholesky().unwrap();
+        let expected = mat3([[2.0, 0.0, 0.0], [6.0, 1.0, 0.0], [-8.0, 5.0, 3.0]]);
+        assert!(close(&lower, &expected));
+        assert!(close(
+            &lower.checked_mul(&lower.transpose()).unwrap(),
+            &covariance
+        ));
+
+        let indefinite = mat3([[1.0, 2.0, 0.0], [2.0, 1.0, 0.0], [0.0, 0.0, 1.0]]);
+        assert_eq!(indefinite.cholesky(), Err(MatrixError::NotPositiveDefinite));
+    }
+}
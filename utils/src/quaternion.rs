@@ -1,50 +1,144 @@
-use crate::fixed_point::{FloatConversionError, UnitFp, UNIT_FIXED_POINT_DECIMAL_BITS};
-use crate::vec3d::Vec3D;
+use crate::fixed_point::{
+    ArithmeticError, FixedPoint, FloatConversionError, UnitFp, UNIT_FIXED_POINT_DECIMAL_BITS,
+};
+use crate::vec3d::{UnitVec3D, Vec3D};
 
 /// the unit quaternion (1, 0, 0, 0) is defined herein as pointing in the postive x direction, with a roll such that the body's secondary axis is +z.
 /// to provide a human example, the human oriented (1, 0, 0, 0) would be lying down with their head on the +x end, looking upwards.
+/// Rotations are active: q.rotate(v) turns v from the body frame into the reference frame.
 
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub struct Quaternion(UnitFp, UnitFp, UnitFp, UnitFp);
 
+/// row-major 3x3 direction cosine matrix, as produced by Quaternion::to_rotation_matrix().
+pub type RotationMatrix = [[UnitFp; 3]; 3];
+
+/// aerospace (Z-Y-X, intrinsic) Euler angles in radians: yaw about z, then pitch about the new y, then roll about the new x.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EulerAngles {
+    pub roll: UnitFp,
+    pub pitch: UnitFp,
+    pub yaw: UnitFp,
+}
+
 #[derive(Debug, Clone, Copy, PartialEq)]
-enum _QuaternionError {
+pub enum QuaternionError {
     NotUnit,
     BadFloat(FloatConversionError),
+    Arithmetic(ArithmeticError),
+    InterpolantOutOfRange(UnitFp), // slerp fraction outside [0, 1].
 }
 
-impl From<FloatConversionError> for _QuaternionError {
+impl From<FloatConversionError> for QuaternionError {
     fn from(value: FloatConversionError) -> Self {
         // if a FloatConversionError propagates from generation, pass it up as QuaternionError::BadFloat
-        _QuaternionError::BadFloat(value)
+        QuaternionError::BadFloat(value)
     }
 }
 
-const _ERR_EPSILON: UnitFp = UnitFp::from_f64_trusted(1e-3);
+impl From<ArithmeticError> for QuaternionError {
+    fn from(value: ArithmeticError) -> Self {
+        QuaternionError::Arithmetic(value)
+    }
+}
 
+const ERR_EPSILON: UnitFp = UnitFp::from_f64_trusted(1e-3);
+const SLERP_LINEAR_THRESHOLD: UnitFp = UnitFp::from_f64_trusted(0.9995); // beyond this dot product, sin(theta) is too small to divide by.
+const ONE: UnitFp = FixedPoint(1 << UNIT_FIXED_POINT_DECIMAL_BITS);
+// below this |from x to|, two opposed directions are taken as exactly antiparallel; 2^-40, ~1e-12 rad off.
+const ANTIPARALLEL_EPSILON: UnitFp = FixedPoint(1 << (UNIT_FIXED_POINT_DECIMAL_BITS - 40));
+const ZERO: UnitFp = FixedPoint(0);
+
+// note on arithmetic: every component of a unit quaternion is within [-1, 1], so products and the short sums below
+// stay well inside UnitFp's +/-8 range. The operator (panicking) forms are used only where that bound holds.
 impl Quaternion {
-    fn _new(w: UnitFp, x: UnitFp, y: UnitFp, z: UnitFp) -> Result<Quaternion, _QuaternionError> {
-        //! creates a new Quaternion; checking the values provided produce a unit quaternion.
-        let mag = w * w + x * x + y * y + z * z;
-        if (mag - UnitFp::from_int(1)).abs() < _ERR_EPSILON {
+    pub const IDENTITY: Quaternion = Quaternion(ONE, ZERO, ZERO, ZERO);
+
+    pub fn new(w: UnitFp, x: UnitFp, y: UnitFp, z: UnitFp) -> Result<Quaternion, QuaternionError> {
+        //! creates a new Quaternion; checking the values provided produce a unit quaternion. Components too large to
+        //! square and sum are an arithmetic error.
+        let mag = w
+            .checked_mul(w)?
+            .checked_add(x.checked_mul(x)?)?
+            .checked_add(y.checked_mul(y)?)?
+            .checked_add(z.checked_mul(z)?)?;
+        if mag.checked_sub(ONE)?.abs() < ERR_EPSILON {
             Ok(Quaternion(w, x, y, z))
         } else {
-            Err(_QuaternionError::NotUnit)
+            Err(QuaternionError::NotUnit)
         }
     }
 
-    fn _from_floats(w: f64, x: f64, y: f64, z: f64) -> Result<Quaternion, _QuaternionError> {
+    pub fn from_floats(w: f64, x: f64, y: f64, z: f64) -> Result<Quaternion, QuaternionError> {
         let (wufp, xufp, yufp, zufp) = (
             UnitFp::from_f64(w)?,
             UnitFp::from_f64(x)?,
             UnitFp::from_f64(y)?,
             UnitFp::from_f64(z)?,
         );
-        Quaternion::_new(wufp, xufp, yufp, zufp)
+        Quaternion::new(wufp, xufp, yufp, zufp)
+    }
+
+    pub fn components(&self) -> (UnitFp, UnitFp, UnitFp, UnitFp) {
+        //! the (w, x, y, z) components.
+        (self.0, self.1, self.2, self.3)
+    }
+
+    pub fn from_axis_angle(axis: UnitVec3D, angle: UnitFp) -> Result<Quaternion, QuaternionError> {
+        //! rotation of angle radians (right hand rule) about axis. The axis need not be exactly unit length, but
+        //! must not be zero.
+        let axis = axis.to_unit_vector()?;
+        let (sin, cos) = angle.rshift(1).sin_cos();
+        Quaternion(cos, axis.0 * sin, axis.1 * sin, axis.2 * sin).normalised()
+    }
+
+    pub fn to_axis_angle(&self) -> (UnitVec3D, UnitFp) {
+        //! the axis and angle (within [0, 2PI)) of this rotation. The identity rotation reports the +x axis.
+        let vector = Vec3D(self.1, self.2, self.3);
+        match vector.to_unit_vector() {
+            Ok(axis) => {
+                let half_angle = vector.magnitude().atan2(self.0);
+                (axis, half_angle.lshift(1))
+            }
+            Err(_) => (Vec3D(ONE, ZERO, ZERO), ZERO),
+        }
     }
 
-    fn _mult(&self, other: &Self) -> Self {
-        //! produces self * other. Remember that order matters.
+    pub fn from_two_vectors(from: UnitVec3D, to: UnitVec3D) -> Result<Quaternion, QuaternionError> {
+        //! the shortest-arc rotation taking the direction of from onto the direction of to. Errors if either is zero.
+        let (from, to) = (from.to_unit_vector()?, to.to_unit_vector()?);
+        let dot = from.dot(&to)?;
+        let axis = from.cross(&to)?;
+        if dot < ZERO && axis.checked_magnitude()? < ANTIPARALLEL_EPSILON {
+            // antiparallel to within UnitFp precision, so the cross product has no usable direction. Any perpendicular
+            // axis will do, so use whichever of x/y is least parallel to from.
+            let x_axis = Vec3D(ONE, ZERO, ZERO);
+            let y_axis = Vec3D(ZERO, ONE, ZERO);
+            let helper = if from.0.abs() < from.1.abs() {
+                x_axis
+            } else {
+                y_axis
+            };
+            return Quaternion::from_axis_angle(from.cross(&helper)?, UnitFp::PI);
+        }
+        // (1 + cos t, sin t * axis) is the rotation by t scaled by 2cos(t/2); normalising fixes the scale.
+        Quaternion(ONE + dot, axis.0, axis.1, axis.2).normalised()
+    }
+
+    pub fn normalised(&self) -> Result<Quaternion, QuaternionError> {
+        //! rescales to exactly unit length; used to remove the drift that builds up over repeated multiplication.
+        let norm =
+            (self.0 * self.0 + self.1 * self.1 + self.2 * self.2 + self.3 * self.3).sqrt()?;
+        Ok(Self(
+            self.0.checked_div(norm)?,
+            self.1.checked_div(norm)?,
+            self.2.checked_div(norm)?,
+            self.3.checked_div(norm)?,
+        ))
+    }
+
+    pub fn mult(&self, other: &Self) -> Self {
+        //! produces self * other. Remember that order matters: self * other applies other first, then self.
         Self(
             self.0 * other.0 - self.1 * other.1 - self.2 * other.2 - self.3 * other.3,
             self.0 * other.1 + self.1 * other.0 + self.2 * other.3 - self.3 * other.2,
@@ -53,46 +147,225 @@ impl Quaternion {
         )
     }
 
-    fn _conjugated(&self) -> Self {
-        //! returns the conjugate pair of self.
+    pub fn conjugated(&self) -> Self {
+        //! returns the conjugate pair of self. For a unit quaternion this is the inverse rotation.
         Self(self.0, -self.1, -self.2, -self.3)
     }
 
-    fn _from_vector(vector: Vec3D<UNIT_FIXED_POINT_DECIMAL_BITS>) -> Self {
+    fn dot(&self, other: &Self) -> UnitFp {
+        //! 4D dot product; the cosine of half the angle between two attitudes.
+        self.0 * other.0 + self.1 * other.1 + self.2 * other.2 + self.3 * other.3
+    }
+
+    #[cfg(test)]
+    fn from_vector(vector: Vec3D<UNIT_FIXED_POINT_DECIMAL_BITS>) -> Self {
         //! convert vector to quaternion; used in the conversion of quaternion to vec.
         Quaternion(UnitFp::from_int(0), vector.0, vector.1, vector.2)
     }
 
-    fn _to_forward_vector(&self) -> Vec3D<UNIT_FIXED_POINT_DECIMAL_BITS> {
+    pub fn to_forward_vector(&self) -> Vec3D<UNIT_FIXED_POINT_DECIMAL_BITS> {
         //! find the unit-length forward vector of the quaternion.
         //! Done by producing a quaternion representing the principal vector and then multiplying the product by q's conjugate.
         //! Multiplication is done by hand rather than via mult() as many of the operations cancel.
         let qv = Self(-self.1, self.0, self.3, -self.2); // self * (0, 1, 0, 0)
-        let qvq_inv = qv._mult(&self._conjugated());
+        let qvq_inv = qv.mult(&self.conjugated());
         Vec3D(qvq_inv.1, qvq_inv.2, qvq_inv.3)
     }
 
+    pub fn rotate<const N: u8>(&self, vector: &Vec3D<N>) -> Result<Vec3D<N>, ArithmeticError> {
+        //! rotates a vector of any scale by this attitude. Errors only if the rotated vector can't be held at scale N.
+        let matrix = self.to_rotation_matrix();
+        let row = |r: &[UnitFp; 3]| {
+            r[0].checked_scale_by_other(vector.0)?
+                .checked_add(r[1].checked_scale_by_other(vector.1)?)?
+                .checked_add(r[2].checked_scale_by_other(vector.2)?)
+        };
+        Ok(Vec3D(row(&matrix[0])?, row(&matrix[1])?, row(&matrix[2])?))
+    }
+
+    pub fn to_rotation_matrix(&self) -> RotationMatrix {
+        //! the equivalent direction cosine matrix; M * v == q.rotate(v).
+        let Quaternion(w, x, y, z) = *self;
+        let two = UnitFp::from_int(2);
+        [
+            [
+                ONE - two * (y * y + z * z),
+                two * (x * y - w * z),
+                two * (x * z + w * y),
+            ],
+            [
+                two * (x * y + w * z),
+                ONE - two * (x * x + z * z),
+                two * (y * z - w * x),
+            ],
+            [
+                two * (x * z - w * y),
+                two * (y * z + w * x),
+                ONE - two * (x * x + y * y),
+            ],
+        ]
+    }
+
+    pub fn from_rotation_matrix(m: &RotationMatrix) -> Result<Quaternion, QuaternionError> {
+        //! recovers the attitude from a direction cosine matrix (Shepperd's method).
+        //! Picks the largest of w, x, y, z to solve for first, so the divisor is never small. Elements are the caller's,
+        //! so may be anything; every step is checked, and one that overflows is an arithmetic error.
+        let quarter = UnitFp::from_int(1).rshift(2);
+        let double_root = |radicand: UnitFp| -> Result<UnitFp, QuaternionError> {
+            let root = radicand.sqrt()?;
+            Ok(root.checked_add(root)?)
+        };
+        let trace = m[0][0].checked_add(m[1][1])?.checked_add(m[2][2])?;
+        let q = if trace > ZERO {
+            let s = double_root(trace.checked_add(ONE)?)?; // s = 4w
+            Quaternion(
+                s.checked_mul(quarter)?,
+                m[2][1].checked_sub(m[1][2])?.checked_div(s)?,
+                m[0][2].checked_sub(m[2][0])?.checked_div(s)?,
+                m[1][0].checked_sub(m[0][1])?.checked_div(s)?,
+            )
+        } else if m[0][0] > m[1][1] && m[0][0] > m[2][2] {
+            let s = double_root(
+                ONE.checked_add(m[0][0])?
+                    .checked_sub(m[1][1])?
+                    .checked_sub(m[2][2])?,
+            )?; // s = 4x
+            Quaternion(
+                m[2][1].checked_sub(m[1][2])?.checked_div(s)?,
+                s.checked_mul(quarter)?,
+                m[0][1].checked_add(m[1][0])?.checked_div(s)?,
+                m[0][2].checked_add(m[2][0])?.checked_div(s)?,
+            )
+        } else if m[1][1] > m[2][2] {
+            let s = double_root(
+                ONE.checked_add(m[1][1])?
+                    .checked_sub(m[0][0])?
+                    .checked_sub(m[2][2])?,
+            )?; // s = 4y
+            Quaternion(
+                m[0][2].checked_sub(m[2][0])?.checked_div(s)?,
+                m[0][1].checked_add(m[1][0])?.checked_div(s)?,
+                s.checked_mul(quarter)?,
+                m[1][2].checked_add(m[2][1])?.checked_div(s)?,
+            )
+        } else {
+            let s = double_root(
+                ONE.checked_add(m[2][2])?
+                    .checked_sub(m[0][0])?
+                    .checked_sub(m[1][1])?,
+            )?; // s = 4z
+            Quaternion(
+                m[1][0].checked_sub(m[0][1])?.checked_div(s)?,
+                m[0][2].checked_add(m[2][0])?.checked_div(s)?,
+                m[1][2].checked_add(m[2][1])?.checked_div(s)?,
+                s.checked_mul(quarter)?,
+            )
+        };
+        // a matrix that isn't a rotation gives a non-unit result; reject it before tidying up the rounding.
+        Quaternion::new(q.0, q.1, q.2, q.3)?.normalised()
+    }
+
+    pub fn from_euler(angles: EulerAngles) -> Self {
+        //! builds the attitude from aerospace Z-Y-X Euler angles.
+        let (sr, cr) = angles.roll.rshift(1).sin_cos();
+        let (sp, cp) = angles.pitch.rshift(1).sin_cos();
+        let (sy, cy) = angles.yaw.rshift(1).sin_cos();
+        Self(
+            cr * cp * cy + sr * sp * sy,
+            sr * cp * cy - cr * sp * sy,
+            cr * sp * cy + sr * cp * sy,
+            cr * cp * sy - sr * sp * cy,
+        )
+    }
+
+    pub fn to_euler(&self) -> EulerAngles {
+        //! decomposes into aerospace Z-Y-X Euler angles. At pitch = +/-PI/2 (gimbal lock) roll and yaw share one
+        //! degree of freedom and yaw absorbs it.
+        let Quaternion(w, x, y, z) = *self;
+        let two = UnitFp::from_int(2);
+        // clamp guards against |sin pitch| creeping just past 1 through rounding.
+        let sin_pitch = (two * (w * y - z * x)).clamp(-ONE, ONE);
+        EulerAngles {
+            roll: (two * (w * x + y * z)).atan2(ONE - two * (x * x + y * y)),
+            pitch: sin_pitch.asin().unwrap_or(ZERO), // (MR B.1) unreachable error; input is clamped to asin's domain.
+            yaw: (two * (w * z + x * y)).atan2(ONE - two * (y * y + z * z)),
+        }
+    }
+
+    pub fn slerp(&self, other: &Self, fraction: UnitFp) -> Result<Quaternion, QuaternionError> {
+        //! spherical linear interpolation: the attitude fraction (0..=1) of the way along the shortest slew to other.
+        if fraction < ZERO || fraction > ONE {
+            return Err(QuaternionError::InterpolantOutOfRange(fraction));
+        }
+        // q and -q are the same attitude; take whichever gives the short way round.
+        let (other, cos_theta) = match self.dot(other) {
+            dot if dot < ZERO => (Self(-other.0, -other.1, -other.2, -other.3), -dot),
+            dot => (*other, dot),
+        };
+        let (from_weight, to_weight) = if cos_theta > SLERP_LINEAR_THRESHOLD {
+            (ONE - fraction, fraction) // nearly parallel; linear interpolation is indistinguishable.
+        } else {
+            let theta = cos_theta.acos()?;
+            let sin_theta = theta.sin();
+            (
+                ((ONE - fraction) * theta).sin().checked_div(sin_theta)?,
+                (fraction * theta).sin().checked_div(sin_theta)?,
+            )
+        };
+        Self(
+            self.0 * from_weight + other.0 * to_weight,
+            self.1 * from_weight + other.1 * to_weight,
+            self.2 * from_weight + other.2 * to_weight,
+            self.3 * from_weight + other.3 * to_weight,
+        )
+        .normalised()
+    }
+
+    pub fn angle_to(&self, other: &Self) -> UnitFp {
+        //! the angle (within [0, PI]) of the single rotation that takes self onto other.
+        let difference = self.conjugated().mult(other);
+        let vector = Vec3D(difference.1, difference.2, difference.3);
+        vector.magnitude().atan2(difference.0.abs()).lshift(1)
+    }
+
     #[cfg(test)]
     fn equal_within_epsilon(&self, other: &Self) -> bool {
         // return true if all fields values are within ERR_EPSILON.
-        (self.0 - other.0).abs() < _ERR_EPSILON
-            && (self.1 - other.1).abs() < _ERR_EPSILON
-            && (self.2 - other.2).abs() < _ERR_EPSILON
-            && (self.3 - other.3).abs() < _ERR_EPSILON
+        (self.0 - other.0).abs() < ERR_EPSILON
+            && (self.1 - other.1).abs() < ERR_EPSILON
+            && (self.2 - other.2).abs() < ERR_EPSILON
+            && (self.3 - other.3).abs() < ERR_EPSILON
     }
 }
 
 #[cfg(test)]
 #[allow(clippy::unwrap_used)]
 mod quaternion_tests {
-    use crate::{vec3d::Vec3D, FloatConversionError};
+    use crate::{vec3d::Vec3D, FloatConversionError, StepVec3D, UnitVec3D};
+
+    use super::{EulerAngles, Quaternion, QuaternionError, UnitFp};
+
+    const TIGHT: f64 = 1e-12; // for checking the fixed point maths itself, rather than validation.
+
+    fn vec_close<const N: u8>(a: &Vec3D<N>, b: &Vec3D<N>, tolerance: f64) -> bool {
+        (a.0 - b.0).abs().to_f64() < tolerance
+            && (a.1 - b.1).abs().to_f64() < tolerance
+            && (a.2 - b.2).abs().to_f64() < tolerance
+    }
+
+    fn quat_close(a: &Quaternion, b: &Quaternion) -> bool {
+        // q and -q are the same attitude.
+        a.angle_to(b).to_f64() < TIGHT
+    }
 
-    use super::{Quaternion, UnitFp, _QuaternionError};
+    fn angle(radians: f64) -> UnitFp {
+        UnitFp::from_f64(radians).unwrap()
+    }
 
     #[test]
     fn test_new_valid() {
         // this creation passes validation because its magnitude is 1
-        let ff = Quaternion::_from_floats(0.0, 0.6, 0.8, 0.0);
+        let ff = Quaternion::from_floats(0.0, 0.6, 0.8, 0.0);
         let expected = Ok(Quaternion(
             UnitFp::from_f64_trusted(0.0),
             UnitFp::from_f64_trusted(0.6),
@@ -107,25 +380,36 @@ mod quaternion_tests {
     fn test_new_invalid() {
         // magnitude too far from 1, fail
         assert_eq!(
-            Quaternion::_from_floats(1.0, 1.0, 0.0, 0.0),
-            Err(_QuaternionError::NotUnit)
+            Quaternion::from_floats(1.0, 1.0, 0.0, 0.0),
+            Err(QuaternionError::NotUnit)
         );
 
         // bad float fed in, float fails bounds check
         assert_eq!(
-            Quaternion::_from_floats(1.0, 3.0, 1299.0, 3.0),
-            Err(_QuaternionError::BadFloat(
-                FloatConversionError::OutOfBounds
-            ))
-        )
+            Quaternion::from_floats(1.0, 3.0, 1299.0, 3.0),
+            Err(QuaternionError::BadFloat(FloatConversionError::OutOfBounds))
+        );
+
+        // components whose squares can't be summed are an error, not a panic.
+        let three = UnitFp::from_int(3);
+        let zero = UnitFp::from_int(0);
+        assert!(matches!(
+            Quaternion::new(three, zero, zero, zero),
+            Err(QuaternionError::Arithmetic(_))
+        ));
+        let two_and_a_half = UnitFp::from_f64_trusted(2.5); // squares fine, but they don't sum.
+        assert!(matches!(
+            Quaternion::new(two_and_a_half, two_and_a_half, zero, zero),
+            Err(QuaternionError::Arithmetic(_))
+        ));
     }
 
     #[test]
     fn test_mult() {
         // multiply two quaternions together, validate output is correct against external calculation (within ERR_EPSILON on each field).
-        let multed = Quaternion::_from_floats(0.3, 0.6, 0.5, 0.547722558)
+        let multed = Quaternion::from_floats(0.3, 0.6, 0.5, 0.547722558)
             .unwrap()
-            ._mult(&Quaternion::_from_floats(0.8, 0.1, 0.5, 0.316227766).unwrap());
+            .mult(&Quaternion::from_floats(0.8, 0.1, 0.5, 0.316227766).unwrap());
         let result = Quaternion(
             UnitFp::from_f64_trusted(-0.2432050809041),
             UnitFp::from_f64_trusted(0.394252604),
@@ -138,10 +422,10 @@ mod quaternion_tests {
 
     #[test]
     fn test_conjugate() {
-        let quat_test = Quaternion::_from_floats(0.0, 0.6, 0.8, 0.0).unwrap();
+        let quat_test = Quaternion::from_floats(0.0, 0.6, 0.8, 0.0).unwrap();
         assert_eq!(
-            quat_test._conjugated(),
-            Quaternion::_from_floats(0.0, -0.6, -0.8, -0.0).unwrap()
+            quat_test.conjugated(),
+            Quaternion::from_floats(0.0, -0.6, -0.8, -0.0).unwrap()
         );
     }
 
@@ -149,18 +433,248 @@ mod quaternion_tests {
     fn test_from_vector() {
         let test_vector = Vec3D::from_floats(0.6, 0.8, 0.0).unwrap();
         assert_eq!(
-            Quaternion::_from_vector(test_vector),
-            Quaternion::_from_floats(0.0, 0.6, 0.8, 0.0).unwrap()
+            Quaternion::from_vector(test_vector),
+            Quaternion::from_floats(0.0, 0.6, 0.8, 0.0).unwrap()
         )
     }
 
     #[test]
     fn test_to_forward_vector() {
         assert_eq!(
-            Quaternion::_from_floats(1.0, 0.0, 0.0, 0.0)
+            Quaternion::from_floats(1.0, 0.0, 0.0, 0.0)
                 .unwrap()
-                ._to_forward_vector(),
+                .to_forward_vector(),
             Vec3D::from_floats(1.0, 0.0, 0.0).unwrap()
         )
     }
+
+    #[test]
+    fn test_axis_angle() {
+        let z = UnitVec3D::from_floats(0.0, 0.0, 1.0).unwrap();
+        let quarter_turn = Quaternion::from_axis_angle(z, UnitFp::FRAC_PI_2).unwrap();
+        let expected = Quaternion::from_floats(0.5f64.sqrt(), 0.0, 0.0, 0.5f64.sqrt()).unwrap();
+        assert!(quat_close(&quarter_turn, &expected));
+
+        let (axis, recovered) = quarter_turn.to_axis_angle();
+        assert!(vec_close(&axis, &z, TIGHT));
+        assert!((recovered - UnitFp::FRAC_PI_2).abs().to_f64() < TIGHT);
+
+        let (_, none) = Quaternion::IDENTITY.to_axis_angle();
+        assert_eq!(none, UnitFp::from_int(0));
+        assert!(Quaternion::from_axis_angle(UnitVec3D::new(), UnitFp::PI).is_err());
+    }
+
+    #[test]
+    fn test_rotate() {
+        // a quarter turn about z takes +x to +y, whatever the scale of the vector.
+        let z = UnitVec3D::from_floats(0.0, 0.0, 1.0).unwrap();
+        let quarter_turn = Quaternion::from_axis_angle(z, UnitFp::FRAC_PI_2).unwrap();
+        let unit_x = UnitVec3D::from_floats(1.0, 0.0, 0.0).unwrap();
+        assert!(vec_close(
+            &quarter_turn.rotate(&unit_x).unwrap(),
+            &UnitVec3D::from_floats(0.0, 1.0, 0.0).unwrap(),
+            TIGHT
+        ));
+        let step = StepVec3D::from_floats(1000.0, 0.0, 250.0).unwrap();
+        assert!(vec_close(
+            &quarter_turn.rotate(&step).unwrap(),
+            &StepVec3D::from_floats(0.0, 1000.0, 250.0).unwrap(),
+            1e-9
+        ));
+        // the forward vector is the rotated +x axis.
+        let q = Quaternion::from_floats(0.3, 0.6, 0.5, 0.547722558).unwrap();
+        assert!(vec_close(
+            &q.rotate(&unit_x).unwrap(),
+            &q.to_forward_vector(),
+            1e-3
+        ));
+    }
+
+    #[test]
+    fn test_rotate_overflow() {
+        let x = UnitVec3D::from_floats(1.0, 0.0, 0.0).unwrap();
+        let turn = Quaternion::from_axis_angle(x, UnitFp::FRAC_PI_2.rshift(1)).unwrap();
+        // (0, 6, 6) rotated 45 degrees about x has a z component of 6 * sqrt 2, which can't be held in a UnitFp.
+        let big = UnitVec3D::from_floats(0.0, 6.0, 6.0).unwrap();
+        assert!(turn.rotate(&big).is_err());
+    }
+
+    #[test]
+    fn test_from_two_vectors() {
+        let from = UnitVec3D::from_floats(1.0, 0.0, 0.0).unwrap();
+        let to = UnitVec3D::from_floats(0.0, 0.6, 0.8).unwrap();
+        let q = Quaternion::from_two_vectors(from, to).unwrap();
+        assert!(vec_close(&q.rotate(&from).unwrap(), &to, TIGHT));
+
+        // parallel: identity
+        let same = Quaternion::from_two_vectors(from, from).unwrap();
+        assert!(quat_close(&same, &Quaternion::IDENTITY));
+
+        // antiparallel: a half turn about something perpendicular
+        let flipped = Quaternion::from_two_vectors(from, -from).unwrap();
+        assert!(vec_close(&flipped.rotate(&from).unwrap(), &-from, TIGHT));
+
+        // nearly antiparallel: still the exact shortest arc, not a half turn that lands on -from.
+        let (c, s) = (179f64.to_radians().cos(), 179f64.to_radians().sin());
+        let near = UnitVec3D::from_floats(c, s, 0.0).unwrap();
+        let q = Quaternion::from_two_vectors(from, near).unwrap();
+        assert!(vec_close(&q.rotate(&from).unwrap(), &near, TIGHT));
+        let near = UnitVec3D::from_floats(c, 0.0, -s).unwrap();
+        let q = Quaternion::from_two_vectors(from, near).unwrap();
+        assert!(vec_close(&q.rotate(&from).unwrap(), &near, TIGHT));
+
+        assert!(Quaternion::from_two_vectors(from, UnitVec3D::new()).is_err());
+    }
+
+    #[test]
+    fn test_normalise_after_repeated_mult() {
+        let axis = UnitVec3D::from_floats(1.0, 2.0, 3.0).unwrap();
+        let step = Quaternion::from_axis_angle(axis, angle(0.001)).unwrap();
+        let mut attitude = Quaternion::IDENTITY;
+        for _ in 0..1000 {
+            attitude = attitude.mult(&step);
+        }
+        let renormalised = attitude.normalised().unwrap();
+        let (w, x, y, z) = renormalised.components();
+        let norm = (w * w + x * x + y * y + z * z).to_f64();
+        assert!((norm - 1.0).abs() < TIGHT);
+        let expected = Quaternion::from_axis_angle(axis, angle(1.0)).unwrap();
+        assert!(renormalised.angle_to(&expected).to_f64() < 1e-9);
+
+        let zero = Quaternion(
+            UnitFp::from_int(0),
+            UnitFp::from_int(0),
+            UnitFp::from_int(0),
+            UnitFp::from_int(0),
+        );
+        assert!(matches!(
+            zero.normalised(),
+            Err(QuaternionError::Arithmetic(_))
+        ));
+    }
+
+    #[test]
+    fn test_rotation_matrix_round_trip() {
+        // one attitude per Shepperd branch: trace > 0, then x, y and z largest.
+        let axes = [
+            (1.0, 2.0, 3.0, 0.5),
+            (1.0, 0.1, 0.1, 3.0),
+            (0.1, 1.0, 0.1, 3.0),
+            (0.1, 0.1, 1.0, 3.0),
+        ];
+        for (x, y, z, radians) in axes {
+            let axis = UnitVec3D::from_floats(x, y, z).unwrap();
+            let q = Quaternion::from_axis_angle(axis, angle(radians)).unwrap();
+            let matrix = q.to_rotation_matrix();
+            let recovered = Quaternion::from_rotation_matrix(&matrix).unwrap();
+            assert!(quat_close(&q, &recovered), "axis {x} {y} {z}");
+        }
+        // a matrix that isn't a rotation is rejected.
+        let zero = UnitFp::from_int(0);
+        let two = UnitFp::from_int(2);
+        let scaled = [[two, zero, zero], [zero, two, zero], [zero, zero, two]];
+        assert!(Quaternion::from_rotation_matrix(&scaled).is_err());
+        // nor is one too large to take the trace of, or to difference across the diagonal; neither panics.
+        let seven = UnitFp::from_int(7);
+        let huge = [
+            [seven, zero, zero],
+            [zero, seven, zero],
+            [zero, zero, seven],
+        ];
+        assert!(matches!(
+            Quaternion::from_rotation_matrix(&huge),
+            Err(QuaternionError::Arithmetic(_))
+        ));
+        let one = UnitFp::from_int(1);
+        let skewed = [[one, zero, zero], [zero, one, -seven], [zero, seven, one]];
+        assert!(matches!(
+            Quaternion::from_rotation_matrix(&skewed),
+            Err(QuaternionError::Arithmetic(_))
+        ));
+    }
+
+    #[test]
+    fn test_euler_round_trip() {
+        let angles = EulerAngles {
+            roll: angle(0.3),
+            pitch: angle(-0.7),
+            yaw: angle(2.5),
+        };
+        let recovered = Quaternion::from_euler(angles).to_euler();
+        assert!((recovered.roll - angles.roll).abs().to_f64() < TIGHT);
+        assert!((recovered.pitch - angles.pitch).abs().to_f64() < TIGHT);
+        assert!((recovered.yaw - angles.yaw).abs().to_f64() < TIGHT);
+
+        // pure yaw matches an axis-angle turn about z.
+        let yaw_only = Quaternion::from_euler(EulerAngles {
+            roll: angle(0.0),
+            pitch: angle(0.0),
+            yaw: angle(1.0),
+        });
+        let z = UnitVec3D::from_floats(0.0, 0.0, 1.0).unwrap();
+        assert!(quat_close(
+            &yaw_only,
+            &Quaternion::from_axis_angle(z, angle(1.0)).unwrap()
+        ));
+    }
+
+    #[test]
+    fn test_euler_gimbal_lock() {
+        let locked = Quaternion::from_euler(EulerAngles {
+            roll: angle(0.0),
+            pitch: UnitFp::FRAC_PI_2,
+            yaw: angle(0.0),
+        });
+        let pitch = locked.to_euler().pitch;
+        assert!((pitch - UnitFp::FRAC_PI_2).abs().to_f64() < 1e-6);
+    }
+
+    #[test]
+    fn test_slerp() {
+        let z = UnitVec3D::from_floats(0.0, 0.0, 1.0).unwrap();
+        let start = Quaternion::IDENTITY;
+        let end = Quaternion::from_axis_angle(z, angle(1.2)).unwrap();
+        let halfway = start.slerp(&end, angle(0.5)).unwrap();
+        let expected = Quaternion::from_axis_angle(z, angle(0.6)).unwrap();
+        assert!(quat_close(&halfway, &expected));
+        assert!(quat_close(&start.slerp(&end, angle(0.0)).unwrap(), &start));
+        assert!(quat_close(&start.slerp(&end, angle(1.0)).unwrap(), &end));
+
+        // -end is the same attitude; slerp should still take the short way.
+        let negated = Quaternion(-end.0, -end.1, -end.2, -end.3);
+        assert!(quat_close(
+            &start.slerp(&negated, angle(0.5)).unwrap(),
+            &expected
+        ));
+
+        // nearly identical attitudes fall back to linear interpolation.
+        let tiny = Quaternion::from_axis_angle(z, angle(1e-4)).unwrap();
+        let tiny_half = Quaternion::from_axis_angle(z, angle(5e-5)).unwrap();
+        assert!(quat_close(
+            &start.slerp(&tiny, angle(0.5)).unwrap(),
+            &tiny_half
+        ));
+
+        assert_eq!(
+            start.slerp(&end, angle(1.5)),
+            Err(QuaternionError::InterpolantOutOfRange(angle(1.5)))
+        );
+        assert!(start.slerp(&end, angle(-0.1)).is_err());
+    }
+
+    #[test]
+    fn test_angle_to() {
+        let x = UnitVec3D::from_floats(1.0, 0.0, 0.0).unwrap();
+        let a = Quaternion::from_axis_angle(x, angle(0.25)).unwrap();
+        let b = Quaternion::from_axis_angle(x, angle(1.0)).unwrap();
+        assert!((a.angle_to(&b).to_f64() - 0.75).abs() < TIGHT);
+        assert!((b.angle_to(&a).to_f64() - 0.75).abs() < TIGHT);
+        assert_eq!(a.angle_to(&a), UnitFp::from_int(0));
+        // the long way round is never reported; 3PI/2 one way is PI/2 the other.
+        let c = Quaternion::from_axis_angle(x, angle(3.0 * std::f64::consts::FRAC_PI_2)).unwrap();
+        assert!(
+            (Quaternion::IDENTITY.angle_to(&c).to_f64() - std::f64::consts::FRAC_PI_2).abs()
+                < TIGHT
+        );
+    }
 }
@@ -0,0 +1,171 @@
+//! Deterministic simulation time. Replaces wall-clock time (Instant::now()) everywhere the simulation needs a timestamp,
+//! so that runs are reproducible and can go faster than real time.
+//! SimTime is a fixed point number of seconds since epoch; SimClock is the single source of truth for "now",
+//! owned by the physics System and read by every sensor thread through a SimClockReader.
+use std::{
+    fmt::Display,
+    sync::{
+        atomic::{AtomicI64, Ordering},
+        Arc,
+    },
+};
+
+use crate::fixed_point::{
+    FixedPoint, FloatConversionError, SolarFp, TIME_FIXED_POINT_DECIMAL_BITS,
+};
+
+pub type TimeFp = FixedPoint<TIME_FIXED_POINT_DECIMAL_BITS>;
+
+/// A point in (or span of) simulation time, in seconds. Used both as a timestamp and a duration.
+#[must_use]
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub struct SimTime(TimeFp);
+
+impl SimTime {
+    pub const ZERO: SimTime = SimTime(FixedPoint(0));
+
+    pub const fn from_secs(secs: i64) -> Self {
+        //! creates a SimTime representing a whole number of seconds.
+        Self(FixedPoint(secs << TIME_FIXED_POINT_DECIMAL_BITS))
+    }
+
+    pub const fn from_f64(secs: f64) -> Result<Self, FloatConversionError> {
+        //! creates a SimTime from a float number of seconds, bounds checked.
+        match TimeFp::from_f64(secs) {
+            Ok(fp) => Ok(Self(fp)),
+            Err(e) => Err(e),
+        }
+    }
+
+    pub const fn from_fp(fp: TimeFp) -> Self {
+        Self(fp)
+    }
+
+    pub fn as_fp(self) -> TimeFp {
+        //! the raw fixed point number of seconds.
+        self.0
+    }
+
+    pub fn as_solar_fp(self) -> SolarFp {
+        //! number of seconds at SolarFp scale; used when multiplying rates against elapsed time.
+        self.0.as_solar_fp()
+    }
+
+    pub fn to_f64(self) -> f64 {
+        self.0.to_f64()
+    }
+
+    pub fn saturating_add(self, other: Self) -> Self {
+        //! adds a duration to this time. Saturation is well beyond any sensible mission length (~280k years).
+        Self(FixedPoint(self.0 .0.saturating_add(other.0 .0)))
+    }
+
+    pub fn saturating_sub(self, other: Self) -> Self {
+        //! subtracts a duration from this time, saturating at the numeric limits.
+        Self(FixedPoint(self.0 .0.saturating_sub(other.0 .0)))
+    }
+
+    pub fn elapsed_since(self, earlier: Self) -> Self {
+        //! the duration between an earlier time and self. Clamped to zero if "earlier" is actually later.
+        Self(FixedPoint(self.0 .0.saturating_sub(earlier.0 .0).max(0)))
+    }
+
+    pub fn has_reached(self, deadline: Self) -> bool {
+        //! true if self is at or after the given deadline. Used for freeze/reboot timeouts.
+        self >= deadline
+    }
+}
+
+impl Display for SimTime {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "T+{:.3}s", self.0.to_f64())
+    }
+}
+
+/// The writing half of the simulation clock. Only one of these exists per simulation, owned by whatever steps physics forwards.
+/// Not Clone, so only the owner can advance time.
+#[derive(Debug, Default)]
+pub struct SimClock(Arc<AtomicI64>);
+
+/// A read-only handle onto a SimClock. Cheap to clone and safe to send to other threads.
+#[derive(Debug, Clone)]
+pub struct SimClockReader(Arc<AtomicI64>);
+
+impl SimClock {
+    pub fn new() -> Self {
+        //! creates a clock starting at T+0.
+        Self(Arc::new(AtomicI64::new(0)))
+    }
+
+    pub fn now(&self) -> SimTime {
+        SimTime(FixedPoint(self.0.load(Ordering::Acquire)))
+    }
+
+    pub fn advance(&mut self, duration: SimTime) {
+        //! moves the clock forwards by duration.
+        //! There is only ever one writer, so a plain load/store is sufficient.
+        let next = self.now().saturating_add(duration);
+        self.0.store(next.0 .0, Ordering::Release);
+    }
+
+    pub fn reader(&self) -> SimClockReader {
+        //! produces a read-only handle sharing this clock, to be handed to sensor threads.
+        SimClockReader(Arc::clone(&self.0))
+    }
+}
+
+impl SimClockReader {
+    pub fn now(&self) -> SimTime {
+        SimTime(FixedPoint(self.0.load(Ordering::Acquire)))
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn from_secs_matches_float() {
+        assert_eq!(SimTime::from_secs(43), SimTime::from_f64(43.0).unwrap());
+        assert_eq!(SimTime::from_f64(1.5).unwrap().to_f64(), 1.5);
+    }
+
+    #[test]
+    fn elapsed_since() {
+        let earlier = SimTime::from_secs(10);
+        let later = SimTime::from_f64(12.5).unwrap();
+        assert_eq!(
+            later.elapsed_since(earlier),
+            SimTime::from_f64(2.5).unwrap()
+        );
+        // going backwards clamps to zero rather than producing a negative duration
+        assert_eq!(earlier.elapsed_since(later), SimTime::ZERO);
+    }
+
+    #[test]
+    fn deadlines() {
+        let deadline = SimTime::from_secs(100);
+        assert!(!SimTime::from_secs(99).has_reached(deadline));
+        assert!(SimTime::from_secs(100).has_reached(deadline));
+        assert!(SimTime::from_secs(101).has_reached(deadline));
+    }
+
+    #[test]
+    fn saturation() {
+        let max = SimTime::from_fp(TimeFp::with_internal(i64::MAX));
+        assert_eq!(max.saturating_add(SimTime::from_secs(1)), max);
+    }
+
+    #[test]
+    fn reader_sees_advances() {
+        let mut clock = SimClock::new();
+        let reader = clock.reader();
+        assert_eq!(reader.now(), SimTime::ZERO);
+
+        clock.advance(SimTime::from_f64(43.2).unwrap());
+        let moved = std::thread::spawn(move || reader.now()).join().unwrap();
+        assert_eq!(moved, SimTime::from_f64(43.2).unwrap());
+        assert_eq!(clock.now(), moved);
+    }
+}
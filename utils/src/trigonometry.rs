@@ -0,0 +1,374 @@
+//! Integer-only square root and trigonometry for FixedPoint, so flight code never has to fall back on f64.
+//! Trig functions are built on CORDIC (shift-and-add rotations against a table of atan(2^-i)), carried out at
+//! UnitFp scale (60 fractional bits) regardless of the scale of the caller, then converted back.
+//!
+//! Error bounds:
+//! - sqrt: exact; result is the floor of the true root at the input's scale.
+//! - sin, cos, atan2: 60 truncating CORDIC iterations give an error of order 2^-54 (~6e-17) at UnitFp scale, which is
+//!   below f64's own rounding; the tests below check agreement with f64 to 5e-16. StepFp results are within a
+//!   couple of ulp (2^-40). Range reduction of very large angles adds ~|angle| * 2^-61.
+//! - asin, acos: as above away from the ends of the domain. Near |x| = 1 the square root in the reduction is
+//!   ill-conditioned; within 1e-7 of the end the error is bounded by 1e-9.
+use crate::fixed_point::{
+    ArithmeticError, ArithmeticOp, FixedPoint, UnitFp, UNIT_FIXED_POINT_DECIMAL_BITS,
+};
+
+const WORKING_BITS: u8 = UNIT_FIXED_POINT_DECIMAL_BITS; // all CORDIC work is done at UnitFp precision.
+const WORKING_ONE: i64 = 1 << WORKING_BITS;
+const CORDIC_ITERATIONS: u8 = WORKING_BITS; // one bit of accuracy per iteration.
+
+// constants below are correctly rounded at 60 fractional bits (computed to 300 bits with mpmath).
+const PI_INTERNAL: i64 = 3622009729038561421;
+const HALF_PI_INTERNAL: i64 = 1811004864519280711;
+const TAU_INTERNAL: i64 = 7244019458077122842;
+const CORDIC_GAIN_INV: i64 = 700114967507363238; // product of 1/sqrt(1 + 2^-2i); pre-scales rotations to unit length.
+
+/// atan(2^-i) at 60 fractional bits. From i = 20 onwards atan(2^-i) rounds to exactly 2^-i, so the table stops there.
+const ATAN_TABLE: [i64; 20] = [
+    905502432259640355, // atan(2^-0)
+    534549298976576474, // atan(2^-1)
+    282441168888798124, // atan(2^-2)
+    143371547418228444, // atan(2^-3)
+    71963988336308046,  // atan(2^-4)
+    36017075762092179,  // atan(2^-5)
+    18012932708689205,  // atan(2^-6)
+    9007016009513623,   // atan(2^-7)
+    4503576721087964,   // atan(2^-8)
+    2251796950380271,   // atan(2^-9)
+    1125899548928887,   // atan(2^-10)
+    562949908682076,    // atan(2^-11)
+    281474971118251,    // atan(2^-12)
+    140737487656277,    // atan(2^-13)
+    70368744090283,     // atan(2^-14)
+    35184372077909,     // atan(2^-15)
+    17592186043051,     // atan(2^-16)
+    8796093022037,      // atan(2^-17)
+    4398046511083,      // atan(2^-18)
+    2199023255549,      // atan(2^-19)
+];
+
+impl UnitFp {
+    pub const PI: UnitFp = FixedPoint(PI_INTERNAL);
+    pub const FRAC_PI_2: UnitFp = FixedPoint(HALF_PI_INTERNAL);
+    pub const TAU: UnitFp = FixedPoint(TAU_INTERNAL);
+}
+
+impl<const N: u8> FixedPoint<N> {
+    pub fn sqrt(self) -> Result<Self, ArithmeticError> {
+        //! square root, exact to the floor of the last bit. Errors on negative input.
+        if self.0 < 0 {
+            return Err(ArithmeticError::OutOfDomain {
+                operation: ArithmeticOp::Sqrt,
+                operand: self.0,
+                decimal_bits: N,
+            });
+        }
+        // sqrt(v * 2^N) * 2^N == sqrt(v * 2^2N), so widen the internal by N before the integer root.
+        let widened = (self.0 as u128) << N; // (MR A.2b) non-negativity checked above; <= 63 + 63 bits.
+        i64::try_from(widened.isqrt())
+            .map(Self)
+            .map_err(|_| ArithmeticError::Overflow {
+                operation: ArithmeticOp::Sqrt,
+                lhs: self.0,
+                rhs: 0,
+                decimal_bits: N,
+            })
+    }
+
+    pub fn sin_cos(self) -> (Self, Self) {
+        //! sine and cosine of self (in radians) from a single CORDIC pass.
+        let (sin, cos) = sin_cos_working(to_working(self));
+        (from_working(sin), from_working(cos))
+    }
+
+    pub fn sin(self) -> Self {
+        self.sin_cos().0
+    }
+
+    pub fn cos(self) -> Self {
+        self.sin_cos().1
+    }
+
+    pub fn atan2(self, x: Self) -> UnitFp {
+        //! four-quadrant arctangent of self / x, in radians within [-PI, PI]. As with f64, atan2(0, 0) is 0.
+        FixedPoint(atan2_working(self.0 as i128, x.0 as i128)) // (MR A.2a) i64 -> i128 direct superset.
+    }
+
+    pub fn asin(self) -> Result<UnitFp, ArithmeticError> {
+        //! arcsine in radians within [-PI/2, PI/2]. Errors outside [-1, 1].
+        let (sin, cos) = self.unit_circle_pair(ArithmeticOp::Asin)?;
+        Ok(FixedPoint(atan2_working(sin, cos)))
+    }
+
+    pub fn acos(self) -> Result<UnitFp, ArithmeticError> {
+        //! arccosine in radians within [0, PI]. Errors outside [-1, 1].
+        let (cos, sin) = self.unit_circle_pair(ArithmeticOp::Acos)?;
+        Ok(FixedPoint(atan2_working(sin, cos)))
+    }
+
+    fn unit_circle_pair(self, operation: ArithmeticOp) -> Result<(i128, i128), ArithmeticError> {
+        //! for |self| <= 1, returns (self, sqrt(1 - self^2)) at working scale; the two legs of a unit right triangle.
+        let value = to_working(self);
+        let one = WORKING_ONE as i128; // (MR A.2a) i64 -> i128 direct superset.
+        if value.abs() > one {
+            return Err(ArithmeticError::OutOfDomain {
+                operation,
+                operand: self.0,
+                decimal_bits: N,
+            });
+        }
+        let remainder = (one * one - value * value).unsigned_abs(); // (MR A.2b) |value| <= one, so this is >= 0.
+        Ok((value, remainder.isqrt() as i128)) // (MR A.2b) sqrt of <= 2^120 is <= 2^60.
+    }
+}
+
+fn to_working<const N: u8>(value: FixedPoint<N>) -> i128 {
+    //! re-expresses an internal of any scale at working (60 fractional bit) scale, widened so it cannot overflow.
+    let wide = value.0 as i128; // (MR A.2a) i64 -> i128 direct superset.
+    if N <= WORKING_BITS {
+        wide << (WORKING_BITS - N)
+    } else {
+        wide >> (N - WORKING_BITS)
+    }
+}
+
+fn from_working<const N: u8>(working: i64) -> FixedPoint<N> {
+    //! converts a working-scale value of magnitude <= 1 back to scale N.
+    if N <= WORKING_BITS {
+        FixedPoint(working >> (WORKING_BITS - N))
+    } else {
+        FixedPoint(working << (N - WORKING_BITS))
+    }
+}
+
+fn atan_entry(i: u8) -> i64 {
+    //! atan(2^-i) at working scale.
+    match ATAN_TABLE.get(i as usize) {
+        Some(entry) => *entry,
+        None => WORKING_ONE >> i,
+    }
+}
+
+fn sin_cos_working(angle: i128) -> (i64, i64) {
+    //! CORDIC rotation mode. Reduces the angle to [-PI/2, PI/2] then rotates (1/K, 0) through it.
+    let tau = TAU_INTERNAL as i128; // (MR A.2a) i64 -> i128 direct superset.
+    let pi = PI_INTERNAL as i128;
+    let half_pi = HALF_PI_INTERNAL as i128;
+
+    // reduce into (-PI, PI], then fold the outer half-circle back onto the inner one, remembering to flip cosine.
+    let mut reduced = angle.rem_euclid(tau);
+    if reduced > pi {
+        reduced -= tau;
+    }
+    let (reduced, cos_sign) = if reduced > half_pi {
+        (pi - reduced, -1)
+    } else if reduced < -half_pi {
+        (-pi - reduced, -1)
+    } else {
+        (reduced, 1)
+    };
+
+    let (mut x, mut y, mut z) = (CORDIC_GAIN_INV, 0i64, reduced as i64); // (MR A.2b) |reduced| <= PI/2.
+    for i in 0..CORDIC_ITERATIONS {
+        let (dx, dy) = (y >> i, x >> i);
+        if z >= 0 {
+            (x, y, z) = (x - dx, y + dy, z - atan_entry(i));
+        } else {
+            (x, y, z) = (x + dx, y - dy, z + atan_entry(i));
+        }
+    }
+    (y, cos_sign * x)
+}
+
+fn atan2_working(y: i128, x: i128) -> i64 {
+    //! CORDIC vectoring mode. Rotates (x, y) onto the +x axis, accumulating the angle turned through.
+    //! Inputs can be at any common scale, as only their ratio matters.
+    if x == 0 && y == 0 {
+        return 0;
+    }
+
+    // normalise so the larger leg lies in [0.5, 1) at working scale; CORDIC growth (~1.65 * sqrt 2) then stays under 8.
+    let largest = x.unsigned_abs().max(y.unsigned_abs());
+    let target_zeros = 128 - WORKING_BITS as u32; // (MR A.2a) u8 -> u32 direct superset.
+    let (x, y) = match largest.leading_zeros().checked_sub(target_zeros) {
+        Some(shift) => (x << shift, y << shift),
+        None => {
+            let shift = target_zeros - largest.leading_zeros();
+            (x >> shift, y >> shift)
+        }
+    };
+
+    // move the left half-plane onto the right half-plane, leaving a +/-PI offset.
+    let (mut x, mut y, offset) = if x < 0 {
+        let offset = if y >= 0 { PI_INTERNAL } else { -PI_INTERNAL };
+        (-x as i64, -y as i64, offset) // (MR A.2b) normalised above to < 2^60.
+    } else {
+        (x as i64, y as i64, 0) // (MR A.2b) normalised above to < 2^60.
+    };
+
+    let mut z = 0i64;
+    for i in 0..CORDIC_ITERATIONS {
+        let (dx, dy) = (y >> i, x >> i);
+        if y > 0 {
+            (x, y, z) = (x + dx, y - dy, z + atan_entry(i));
+        } else {
+            (x, y, z) = (x - dx, y + dy, z - atan_entry(i));
+        }
+    }
+    z + offset
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+    use crate::fixed_point::StepFp;
+
+    const UNIT_TOLERANCE: f64 = 5e-16; // 2 ulp of an f64 in [1, 4); the oracle is the limiting factor.
+    const STEP_TOLERANCE: f64 = 2e-12; // a couple of ulp at 40 fractional bits.
+
+    fn sample_angles() -> impl Iterator<Item = f64> {
+        //! a sweep of angles over a little more than one full turn each way.
+        (-700..=700).map(|i| i as f64 * 0.01)
+    }
+
+    #[test]
+    fn constants() {
+        assert_eq!(UnitFp::PI.to_f64(), std::f64::consts::PI);
+        assert_eq!(UnitFp::FRAC_PI_2.to_f64(), std::f64::consts::FRAC_PI_2);
+        assert_eq!(UnitFp::TAU.to_f64(), std::f64::consts::TAU);
+    }
+
+    #[test]
+    fn sqrt() {
+        assert_eq!(UnitFp::from_int(4).sqrt(), Ok(UnitFp::from_int(2)));
+        assert_eq!(UnitFp::from_int(0).sqrt(), Ok(UnitFp::from_int(0)));
+        let root_two = UnitFp::from_int(2).sqrt().unwrap().to_f64();
+        assert!((root_two - std::f64::consts::SQRT_2).abs() < UNIT_TOLERANCE);
+        let big = StepFp::from_f64(1.5e6).unwrap().sqrt().unwrap().to_f64();
+        assert!((big - 1.5e6f64.sqrt()).abs() < STEP_TOLERANCE);
+    }
+
+    #[test]
+    fn sqrt_negative() {
+        assert_eq!(
+            UnitFp::from_int(-1).sqrt(),
+            Err(ArithmeticError::OutOfDomain {
+                operation: ArithmeticOp::Sqrt,
+                operand: -1 << 60,
+                decimal_bits: 60
+            })
+        );
+    }
+
+    #[test]
+    fn sin_cos_unit() {
+        for angle in sample_angles() {
+            let fp = UnitFp::from_f64(angle).unwrap();
+            let exact = fp.to_f64();
+            let (sin, cos) = fp.sin_cos();
+            assert!(
+                (sin.to_f64() - exact.sin()).abs() < UNIT_TOLERANCE,
+                "sin {angle}"
+            );
+            assert!(
+                (cos.to_f64() - exact.cos()).abs() < UNIT_TOLERANCE,
+                "cos {angle}"
+            );
+        }
+    }
+
+    #[test]
+    fn sin_cos_step() {
+        // StepFp angles can be far outside one revolution; range reduction must cope.
+        for angle in sample_angles().chain([1e3, -2.5e4, 1e6]) {
+            let fp = StepFp::from_f64(angle).unwrap();
+            let exact = fp.to_f64();
+            assert!(
+                (fp.sin().to_f64() - exact.sin()).abs() < STEP_TOLERANCE,
+                "sin {angle}"
+            );
+            assert!(
+                (fp.cos().to_f64() - exact.cos()).abs() < STEP_TOLERANCE,
+                "cos {angle}"
+            );
+        }
+    }
+
+    #[test]
+    fn atan2_all_quadrants() {
+        for angle in sample_angles() {
+            let (y, x) = (angle.sin() * 3.0, angle.cos() * 3.0);
+            let result = UnitFp::from_f64(y)
+                .unwrap()
+                .atan2(UnitFp::from_f64(x).unwrap());
+            let exact = y.atan2(x);
+            assert!(
+                (result.to_f64() - exact).abs() < UNIT_TOLERANCE,
+                "atan2 {angle}"
+            );
+        }
+    }
+
+    #[test]
+    fn atan2_boundaries() {
+        let zero = UnitFp::from_int(0);
+        let one = UnitFp::from_int(1);
+        assert_eq!(zero.atan2(zero), zero);
+        assert_eq!(zero.atan2(one), zero);
+        assert_eq!(zero.atan2(-one), UnitFp::PI);
+        assert!((one.atan2(zero) - UnitFp::FRAC_PI_2).abs().to_f64() < UNIT_TOLERANCE);
+        assert!(((-one).atan2(zero) + UnitFp::FRAC_PI_2).abs().to_f64() < UNIT_TOLERANCE);
+        // huge StepFp legs are normalised down before rotating.
+        let big = StepFp::from_f64(8e6).unwrap();
+        assert!((big.atan2(big).to_f64() - std::f64::consts::FRAC_PI_4).abs() < UNIT_TOLERANCE);
+        // tiny legs are normalised up.
+        let tiny = UnitFp::with_internal(3);
+        assert!(
+            (tiny.atan2(-tiny).to_f64() - 3.0 * std::f64::consts::FRAC_PI_4).abs() < UNIT_TOLERANCE
+        );
+    }
+
+    #[test]
+    fn asin_acos() {
+        for i in -100..=100 {
+            let x = i as f64 * 0.0099;
+            let fp = UnitFp::from_f64(x).unwrap();
+            let exact = fp.to_f64();
+            assert!(
+                (fp.asin().unwrap().to_f64() - exact.asin()).abs() < UNIT_TOLERANCE,
+                "asin {x}"
+            );
+            assert!(
+                (fp.acos().unwrap().to_f64() - exact.acos()).abs() < UNIT_TOLERANCE,
+                "acos {x}"
+            );
+        }
+    }
+
+    #[test]
+    fn asin_acos_domain_edges() {
+        let one = UnitFp::from_int(1);
+        assert!((one.asin().unwrap() - UnitFp::FRAC_PI_2).abs().to_f64() < UNIT_TOLERANCE);
+        assert_eq!(one.acos(), Ok(UnitFp::from_int(0)));
+        assert_eq!((-one).acos(), Ok(UnitFp::PI));
+        let nearly_one = UnitFp::from_f64(1.0 - 1e-7).unwrap();
+        assert!((nearly_one.asin().unwrap().to_f64() - nearly_one.to_f64().asin()).abs() < 1e-9);
+        let step_half = StepFp::from_f64(0.5).unwrap();
+        assert!((step_half.asin().unwrap().to_f64() - 0.5f64.asin()).abs() < UNIT_TOLERANCE);
+    }
+
+    #[test]
+    fn asin_acos_out_of_domain() {
+        let beyond = UnitFp::from_f64(1.0001).unwrap();
+        assert_eq!(
+            beyond.asin(),
+            Err(ArithmeticError::OutOfDomain {
+                operation: ArithmeticOp::Asin,
+                operand: beyond.0,
+                decimal_bits: 60
+            })
+        );
+        assert!((-beyond).acos().is_err());
+    }
+}
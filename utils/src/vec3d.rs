@@ -1,8 +1,8 @@
 //! basic 3D vector. Used for movement, forces etc.
 
 use crate::fixed_point::{
-    FixedPoint, FloatConversionError, SOLAR_FIXED_POINT_DECIMAL_BITS,
-    STEP_FIXED_POINT_DECIMAL_BITS, UNIT_FIXED_POINT_DECIMAL_BITS,
+    ArithmeticError, ArithmeticOp, FixedPoint, FloatConversionError, UnitFp,
+    SOLAR_FIXED_POINT_DECIMAL_BITS, STEP_FIXED_POINT_DECIMAL_BITS, UNIT_FIXED_POINT_DECIMAL_BITS,
 };
 
 #[derive(Debug, PartialEq, PartialOrd, Copy, Clone)]
@@ -91,15 +91,164 @@ impl<const N: u8> Vec3D<N> {
         )
     }
 
-    pub fn to_unit_vector(self) -> UnitVec3D {
+    pub fn checked_add(&self, other: &Self) -> Result<Self, ArithmeticError> {
+        //! panic-free add(); fails on the first overflowing component.
+        Ok(Self(
+            self.0.checked_add(other.0)?,
+            self.1.checked_add(other.1)?,
+            self.2.checked_add(other.2)?,
+        ))
+    }
+
+    pub fn checked_sub(&self, other: &Self) -> Result<Self, ArithmeticError> {
+        //! panic-free sub(); fails on the first overflowing component.
+        Ok(Self(
+            self.0.checked_sub(other.0)?,
+            self.1.checked_sub(other.1)?,
+            self.2.checked_sub(other.2)?,
+        ))
+    }
+
+    pub fn checked_magnitude(&self) -> Result<FixedPoint<N>, ArithmeticError> {
+        //! panic-free magnitude(); fails if the sum of squares can't be held or its root can't fit back in an i64.
+        let error = ArithmeticError::MagnitudeOverflow {
+            components: (self.0 .0, self.1 .0, self.2 .0),
+            decimal_bits: N,
+        };
+        let squared_internals: (u128, u128, u128) = (
+            (self.0 .0.unsigned_abs() as u128).pow(2),
+            (self.1 .0.unsigned_abs() as u128).pow(2),
+            (self.2 .0.unsigned_abs() as u128).pow(2),
+        ); // (MR A.2a) u64 -> u128 direct superset; each square is < 2^128.
+        let sum = squared_internals
+            .0
+            .checked_add(squared_internals.1)
+            .and_then(|partial| partial.checked_add(squared_internals.2))
+            .ok_or(error)?;
+        let sqrt = i64::try_from(sum.isqrt()).map_err(|_| error)?;
+        Ok(FixedPoint::<N>(sqrt))
+    }
+
+    pub fn checked_scale(&self, scale_factor: FixedPoint<N>) -> Result<Self, ArithmeticError> {
+        //! panic-free scale(); fails on the first overflowing component.
+        Ok(Self(
+            self.0.checked_mul(scale_factor)?,
+            self.1.checked_mul(scale_factor)?,
+            self.2.checked_mul(scale_factor)?,
+        ))
+    }
+
+    pub fn convert<const M: u8>(&self) -> Result<Vec3D<M>, ArithmeticError> {
+        //! re-expresses each component at scale M; see FixedPoint::convert().
+        Ok(Vec3D(
+            self.0.convert()?,
+            self.1.convert()?,
+            self.2.convert()?,
+        ))
+    }
+
+    pub fn convert_lossless<const M: u8>(&self) -> Result<Vec3D<M>, ArithmeticError> {
+        //! re-expresses each component at scale M, erroring if any precision would be dropped.
+        Ok(Vec3D(
+            self.0.convert_lossless()?,
+            self.1.convert_lossless()?,
+            self.2.convert_lossless()?,
+        ))
+    }
+
+    pub fn to_unit_vector(self) -> Result<UnitVec3D, ArithmeticError> {
+        //! the direction of self as a unit vector. Errors on the zero vector, which has no direction.
+        // only the ratio between components matters, so internals are reinterpreted at unit scale rather than converted.
         let shrunk = Vec3D::<UNIT_FIXED_POINT_DECIMAL_BITS>(
             FixedPoint(self.0 .0),
             FixedPoint(self.1 .0),
             FixedPoint(self.2 .0),
         );
-        let divisor = shrunk.magnitude();
+        let divisor = shrunk.checked_magnitude()?;
+        if divisor.0 == 0 {
+            return Err(ArithmeticError::ZeroVector { decimal_bits: N });
+        }
+
+        Ok(Vec3D(
+            shrunk.0.checked_div(divisor)?,
+            shrunk.1.checked_div(divisor)?,
+            shrunk.2.checked_div(divisor)?,
+        ))
+    }
+
+    fn internals(&self) -> (i64, i64, i64) {
+        (self.0 .0, self.1 .0, self.2 .0)
+    }
+
+    fn wide_internals(&self) -> (i128, i128, i128) {
+        (self.0 .0 as i128, self.1 .0 as i128, self.2 .0 as i128) // (MR A.2a) i64 -> i128 direct superset.
+    }
+
+    fn overflow(&self, operation: ArithmeticOp, other: (i64, i64, i64)) -> ArithmeticError {
+        //! builds the diagnostic error for an overflowing vector operation.
+        ArithmeticError::VectorOverflow {
+            operation,
+            lhs: self.internals(),
+            rhs: other,
+            decimal_bits: N,
+        }
+    }
+
+    pub fn dot(&self, other: &Self) -> Result<FixedPoint<N>, ArithmeticError> {
+        //! dot product. Products are summed at full i128 width before rescaling, as in magnitude().
+        let error = self.overflow(ArithmeticOp::Dot, other.internals());
+        let (a, b) = (self.wide_internals(), other.wide_internals());
+        let sum = (a.0 * b.0) // each product of two i64s is at most 2^126, so cannot overflow alone.
+            .checked_add(a.1 * b.1)
+            .and_then(|partial| partial.checked_add(a.2 * b.2))
+            .ok_or(error)?;
+        i64::try_from(sum >> N).map(FixedPoint).map_err(|_| error)
+    }
 
-        shrunk.scale_down(divisor)
+    pub fn cross(&self, other: &Self) -> Result<Self, ArithmeticError> {
+        //! cross product self x other, following the right hand rule. Computed at full i128 width before rescaling.
+        let error = self.overflow(ArithmeticOp::Cross, other.internals());
+        let (a, b) = (self.wide_internals(), other.wide_internals());
+        let component = |l1: i128, r2: i128, l2: i128, r1: i128| {
+            (l1 * r2)
+                .checked_sub(l2 * r1)
+                .and_then(|wide| i64::try_from(wide >> N).ok())
+                .map(FixedPoint)
+                .ok_or(error)
+        };
+        Ok(Self(
+            component(a.1, b.2, a.2, b.1)?,
+            component(a.2, b.0, a.0, b.2)?,
+            component(a.0, b.1, a.1, b.0)?,
+        ))
+    }
+
+    pub fn component_along(&self, direction: &UnitVec3D) -> Result<FixedPoint<N>, ArithmeticError> {
+        //! the signed length of self along a unit direction, i.e. the dot product with mixed scales.
+        let (a, d) = (self.wide_internals(), direction.wide_internals());
+        // |self| < 2^63 and |direction| <= ~2^60, so no product or sum can reach 2^127.
+        let sum = a.0 * d.0 + a.1 * d.1 + a.2 * d.2;
+        i64::try_from(sum >> UNIT_FIXED_POINT_DECIMAL_BITS)
+            .map(FixedPoint)
+            .map_err(|_| self.overflow(ArithmeticOp::Dot, direction.internals()))
+    }
+
+    pub fn projection_onto(&self, other: &Self) -> Result<Self, ArithmeticError> {
+        //! the part of self parallel to other. Errors if other is the zero vector.
+        let direction = other.to_unit_vector()?;
+        direction.checked_scale_from_unit(self.component_along(&direction)?)
+    }
+
+    pub fn rejection_from(&self, other: &Self) -> Result<Self, ArithmeticError> {
+        //! the part of self perpendicular to other. Errors if other is the zero vector.
+        self.checked_sub(&self.projection_onto(other)?)
+    }
+
+    pub fn angle_between(&self, other: &Self) -> Result<UnitFp, ArithmeticError> {
+        //! the unsigned angle between self and other in radians, within [0, PI]. Errors if either is the zero vector.
+        //! Uses atan2(|a x b|, a . b) on the unit directions, which stays accurate near 0 and PI unlike acos.
+        let (a, b) = (self.to_unit_vector()?, other.to_unit_vector()?);
+        Ok(a.cross(&b)?.checked_magnitude()?.atan2(a.dot(&b)?))
     }
 
     pub fn as_solar(&self) -> SolarVec3D {
@@ -120,6 +269,51 @@ impl UnitVec3D {
             self.2.scale_by_other(scalar),
         )
     }
+
+    pub fn checked_scale_from_unit<const N: u8>(
+        self,
+        scalar: FixedPoint<N>,
+    ) -> Result<Vec3D<N>, ArithmeticError> {
+        //! panic-free scale_from_unit().
+        Ok(Vec3D(
+            self.0.checked_scale_by_other(scalar)?,
+            self.1.checked_scale_by_other(scalar)?,
+            self.2.checked_scale_by_other(scalar)?,
+        ))
+    }
+}
+
+// std::ops traits are referred to by path so that importing this module doesn't shadow the inherent add()/sub().
+impl<const N: u8> std::ops::Add for Vec3D<N> {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self::Output {
+        Vec3D::add(&self, &rhs)
+    }
+}
+
+impl<const N: u8> std::ops::Sub for Vec3D<N> {
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self::Output {
+        Vec3D::sub(&self, &rhs)
+    }
+}
+
+impl<const N: u8> std::ops::Neg for Vec3D<N> {
+    type Output = Self;
+
+    fn neg(self) -> Self::Output {
+        Self(-self.0, -self.1, -self.2)
+    }
+}
+
+impl<const N: u8> std::ops::Mul<FixedPoint<N>> for Vec3D<N> {
+    type Output = Self;
+
+    fn mul(self, rhs: FixedPoint<N>) -> Self::Output {
+        self.scale(rhs)
+    }
 }
 
 pub enum PrintType {
@@ -170,10 +364,166 @@ mod tests {
         )
     }
 
+    #[test]
+    fn checked_ops() {
+        let (v1, v2) = get_test_vecs();
+        assert_eq!(v1.checked_add(&v2), Ok(v1.add(&v2)));
+        assert_eq!(v1.checked_sub(&v2), Ok(v1.sub(&v2)));
+        assert_eq!(
+            v1.checked_scale(FixedPoint::<56>::from_int(2)),
+            Ok(v1.scale(FixedPoint::<56>::from_int(2)))
+        );
+        let mag = Vec3D::<56>::from_floats(3.0, 4.0, 0.0).unwrap();
+        assert_eq!(mag.checked_magnitude(), Ok(FixedPoint::<56>::from_int(5)));
+    }
+
+    #[test]
+    fn checked_ops_overflow() {
+        let (v1, _v2) = get_test_vecs();
+        let big = Vec3D::<56>(
+            FixedPoint::<56>::with_internal(i64::MAX),
+            FixedPoint::<56>::with_internal(i64::MAX),
+            FixedPoint::<56>::with_internal(i64::MIN),
+        );
+        assert!(big.checked_add(&v1).is_err());
+        assert!(big
+            .checked_sub(&v1.scale(FixedPoint::<56>::from_int(-1)))
+            .is_err());
+        assert!(big.checked_scale(FixedPoint::<56>::from_int(2)).is_err());
+        assert_eq!(
+            big.checked_magnitude(),
+            Err(ArithmeticError::MagnitudeOverflow {
+                components: (i64::MAX, i64::MAX, i64::MIN),
+                decimal_bits: 56
+            })
+        );
+    }
+
+    #[test]
+    fn convert() {
+        let solar = SolarVec3D::from_floats(1.5, -2.25, 0.0).unwrap();
+        let step: StepVec3D = solar.convert().unwrap();
+        assert_eq!(step, StepVec3D::from_floats(1.5, -2.25, 0.0).unwrap());
+        assert_eq!(step.convert_lossless(), Ok(solar));
+
+        let too_big = SolarVec3D::from_floats(0.0, 0.0, 1e7).unwrap();
+        assert!(too_big.convert::<STEP_FIXED_POINT_DECIMAL_BITS>().is_err());
+
+        let too_fine = StepVec3D::from_floats(1.0 / 256.0, 0.0, 0.0).unwrap();
+        assert_eq!(
+            too_fine.convert::<SOLAR_FIXED_POINT_DECIMAL_BITS>(),
+            Ok(SolarVec3D::new())
+        );
+        assert!(too_fine
+            .convert_lossless::<SOLAR_FIXED_POINT_DECIMAL_BITS>()
+            .is_err());
+    }
+
+    #[test]
+    fn operators() {
+        let (v1, v2) = get_test_vecs();
+        assert_eq!(v1 + v2, v1.add(&v2));
+        assert_eq!(v1 - v2, v1.sub(&v2));
+        assert_eq!(-v1, Vec3D::from_floats(-5.0, -7.0, -10.0).unwrap());
+        assert_eq!(
+            v1 * FixedPoint::<56>::from_int(2),
+            Vec3D::from_floats(10.0, 14.0, 20.0).unwrap()
+        );
+    }
+
+    #[test]
+    fn dot() {
+        let a = Vec3D::<56>::from_floats(1.0, 2.0, 3.0).unwrap();
+        let b = Vec3D::<56>::from_floats(4.0, -5.0, 6.0).unwrap();
+        assert_eq!(a.dot(&b), Ok(FixedPoint::<56>::from_int(12)));
+        // 5*2 + 7*9 + 10*15 = 223 exceeds the +/-128 range of FixedPoint<56>.
+        let (v1, v2) = get_test_vecs();
+        assert!(v1.dot(&v2).is_err());
+        // large Solar-scale values are summed at full width before rescaling.
+        let far = SolarVec3D::from_floats(1e9, 1e9, 1e9).unwrap();
+        let near = SolarVec3D::from_floats(1e3, -1e3, 1e3).unwrap();
+        assert_eq!(far.dot(&near).unwrap().to_f64(), 1e12);
+    }
+
+    #[test]
+    fn cross() {
+        let x = UnitVec3D::from_floats(1.0, 0.0, 0.0).unwrap();
+        let y = UnitVec3D::from_floats(0.0, 1.0, 0.0).unwrap();
+        let z = UnitVec3D::from_floats(0.0, 0.0, 1.0).unwrap();
+        assert_eq!(x.cross(&y), Ok(z));
+        assert_eq!(y.cross(&x), Ok(-z));
+        assert_eq!(x.cross(&x), Ok(UnitVec3D::new()));
+        let (v1, v2) = get_test_vecs();
+        assert_eq!(
+            v1.cross(&v2),
+            Ok(Vec3D::from_floats(15.0, -55.0, 31.0).unwrap())
+        );
+    }
+
+    #[test]
+    fn dot_cross_overflow() {
+        let big = SolarVec3D::from_floats(1e15, 1e15, 1e15).unwrap();
+        assert!(matches!(
+            big.dot(&big),
+            Err(ArithmeticError::VectorOverflow {
+                operation: ArithmeticOp::Dot,
+                ..
+            })
+        ));
+        let other = SolarVec3D::from_floats(-1e15, 1e15, 0.0).unwrap();
+        assert!(matches!(
+            big.cross(&other),
+            Err(ArithmeticError::VectorOverflow {
+                operation: ArithmeticOp::Cross,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn projection_and_rejection() {
+        let v = StepVec3D::from_floats(3.0, 4.0, 5.0).unwrap();
+        let along = StepVec3D::from_floats(10.0, 0.0, 0.0).unwrap();
+        let projection = v.projection_onto(&along).unwrap();
+        let rejection = v.rejection_from(&along).unwrap();
+        assert!((projection.0.to_f64() - 3.0).abs() < 1e-9);
+        assert!(projection.1.to_f64().abs() < 1e-9 && projection.2.to_f64().abs() < 1e-9);
+        assert!(rejection.0.to_f64().abs() < 1e-9);
+        assert!((rejection.1.to_f64() - 4.0).abs() < 1e-9);
+        assert!((rejection.2.to_f64() - 5.0).abs() < 1e-9);
+        assert_eq!(
+            v.projection_onto(&StepVec3D::new()),
+            Err(ArithmeticError::ZeroVector { decimal_bits: 40 })
+        );
+    }
+
+    #[test]
+    fn angle_between() {
+        let x = StepVec3D::from_floats(2.0, 0.0, 0.0).unwrap();
+        let y = StepVec3D::from_floats(0.0, 5.0, 0.0).unwrap();
+        let diagonal = StepVec3D::from_floats(1.0, 1.0, 0.0).unwrap();
+        let right_angle = x.angle_between(&y).unwrap().to_f64();
+        assert!((right_angle - std::f64::consts::FRAC_PI_2).abs() < 1e-12);
+        let eighth = x.angle_between(&diagonal).unwrap().to_f64();
+        assert!((eighth - std::f64::consts::FRAC_PI_4).abs() < 1e-12);
+        assert_eq!(x.angle_between(&x), Ok(UnitFp::from_int(0)));
+        let opposite = x.angle_between(&-x).unwrap().to_f64();
+        assert!((opposite - std::f64::consts::PI).abs() < 1e-12);
+        assert!(x.angle_between(&StepVec3D::new()).is_err());
+    }
+
+    #[test]
+    fn unitise_zero() {
+        assert_eq!(
+            SolarVec3D::new().to_unit_vector(),
+            Err(ArithmeticError::ZeroVector { decimal_bits: 6 })
+        );
+    }
+
     #[test]
     fn unitise() {
         let vec = UnitVec3D::from_floats(1.0, 2.0, 2.0).unwrap();
-        let unit = vec.to_unit_vector();
+        let unit = vec.to_unit_vector().unwrap();
         let unit_spawned = UnitVec3D::from_floats(1.0 / 3.0, 2.0 / 3.0, 2.0 / 3.0).unwrap();
         println!("{:?}\n{:?}", unit, unit_spawned);
         assert!(